@@ -2,30 +2,496 @@
 // Logs all agent transactions to Solana for permanent verification
 
 use anchor_lang::prelude::*;
+#[cfg(not(test))]
+use anchor_spl::token_interface::transfer_checked;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked};
+use solana_sha256_hasher::hashv;
 
 declare_id!("2ZoSk1adD16aXyXYsornCS8qao2hYb6KSkqyCuYNeKKc");
 
+/// `msg!` for non-essential logs, skipped when the arena's `verbose` flag is off
+macro_rules! arena_msg {
+    ($arena:expr, $($arg:tt)*) => {
+        if $arena.config.verbose {
+            msg!($($arg)*);
+        }
+    };
+}
+
+pub const BPS_DENOMINATOR: u16 = 10_000;
+/// Upper bound on balances passed to `compute_median`, keeping the sort within compute limits
+pub const MAX_MEDIAN_INPUTS: usize = 128;
+/// Maximum size of the M-of-N signer set
+pub const MAX_SIGNERS: usize = 5;
+// Flag bit 0 held the pause switch before `ArenaState::Paused` and stays reserved
+/// Only accept bets on agents with a registered `Agent` account
+pub const FLAG_REQUIRE_REGISTERED_AGENTS: u8 = 1;
+/// Only roster members may be registered or bet on
+pub const FLAG_ROSTER_ONLY: u8 = 2;
+/// Number of defined flags; higher bits are rejected by `set_flag`
+pub const FLAG_COUNT: u8 = 3;
+/// Seconds a proposed authority must wait before it can accept the role
+pub const AUTHORITY_DELAY: i64 = 48 * 60 * 60;
+/// Maximum number of fee split recipients
+pub const MAX_FEE_RECIPIENTS: usize = 5;
+/// Maximum number of agent ids on an arena's roster
+pub const MAX_ROSTER_LEN: usize = 32;
+/// Maximum number of paid places in a podium payout
+pub const MAX_PAYOUT_TIERS: usize = 5;
+/// Maximum length of a transaction memo
+pub const MAX_MEMO_LEN: usize = 100;
+/// Maximum number of discovery tags on an arena
+pub const MAX_TAGS: usize = 8;
+/// Maximum length of a discovery tag
+pub const MAX_TAG_LEN: usize = 24;
+/// Maximum entries handled by one batch instruction (`sweep_expired_bets`,
+/// `auto_declare_winner`, `multi_arena_summary`). Each entry costs a PDA
+/// derivation, an account deserialization and at most a lamport move, roughly
+/// 15k CU in the worst case, so 8 entries stay well inside the default 200k CU
+/// budget with headroom for the rest of the instruction. Checked before any
+/// entry is processed.
+pub const MAX_BATCH_LEN: usize = 8;
+/// Maximum length of the note attached to a voided arena
+pub const MAX_VOID_NOTE_LEN: usize = 64;
+/// Maximum length of an agent version string
+pub const MAX_VERSION_LEN: usize = 32;
+/// Maximum length of a PDA-seeding identifier (the per-seed limit)
+pub const MAX_ID_LEN: usize = 32;
+/// Transactions between health computations that earn full activity credit
+pub const HEALTH_ACTIVITY_TARGET: u64 = 100;
+/// Unique bettors that earn full engagement credit
+pub const HEALTH_ENGAGEMENT_TARGET: u64 = 50;
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+/// Width of a `VolumeBucket` in seconds
+pub const BUCKET_SECONDS: i64 = 3600;
+/// Maximum buckets summed by one `volume_between` call
+pub const MAX_BUCKETS_READ: usize = 48;
+/// Shortest time after resolution before unclaimed lamports may be rolled over,
+/// whatever the configured dispute window and keeper grace
+pub const MIN_CLAIM_PERIOD: i64 = 7 * 24 * 60 * 60;
+/// `void_reason` of an arena voided because a tie needed randomness that was
+/// committed but never revealed
+pub const VOID_REASON_UNREVEALED_RANDOMNESS: u8 = 255;
+/// Layout version of `Arena`, bumped on schema changes and carried by `Checkpoint`
+pub const ARENA_SCHEMA_VERSION: u8 = 1;
+/// Shortest wait after resolution before keepers may settle bets, so winners get
+/// a chance to claim in full first
+pub const MIN_KEEPER_GRACE: i64 = 24 * 60 * 60;
+/// Largest share of a settled payout a keeper may be configured to keep
+pub const MAX_KEEPER_REWARD_BPS: u16 = 500;
+
 #[program]
 pub mod arena_logger {
     use super::*;
 
     /// Initialize the arena (one-time setup)
-    pub fn initialize_arena(ctx: Context<InitializeArena>, arena_id: String) -> Result<()> {
+    pub fn initialize_arena(
+        ctx: Context<InitializeArena>,
+        arena_id: String,
+        guardian: Pubkey,
+    ) -> Result<()> {
+        validate_identifier(&arena_id)?;
         let arena = &mut ctx.accounts.arena;
         arena.arena_id = arena_id;
         arena.total_transactions = 0;
         arena.total_agents = 0;
         arena.total_volume = 0;
-        arena.started_at = Clock::get()?.unix_timestamp;
+        arena.started_at = now(ctx.remaining_accounts)?;
         arena.authority = ctx.accounts.authority.key();
         arena.total_bets = 0;
         arena.total_bet_volume = 0;
+        arena.guardian = guardian;
+        arena.config.verbose = true;
+        ctx.accounts.vault.arena = arena.key();
 
         msg!("Arena initialized: {}", arena.arena_id);
         Ok(())
     }
 
-    /// Log an agent transaction
+    /// Create a new arena that reuses another arena's configuration
+    pub fn clone_arena(ctx: Context<CloneArena>, new_arena_id: String, round: u32) -> Result<()> {
+        validate_identifier(&new_arena_id)?;
+        let source = &ctx.accounts.source;
+        let arena = &mut ctx.accounts.arena;
+        arena.arena_id = new_arena_id;
+        arena.authority = ctx.accounts.authority.key();
+        arena.started_at = now(ctx.remaining_accounts)?;
+        arena.guardian = source.guardian;
+        arena.config = source.config.clone();
+        // Rules carry over; the new arena starts `Open` whatever the source's state
+        arena.flags = source.flags;
+        arena.round = round;
+        ctx.accounts.vault.arena = arena.key();
+
+        msg!(
+            "Arena cloned: {} -> {} (round {})",
+            source.arena_id,
+            arena.arena_id,
+            round
+        );
+        Ok(())
+    }
+
+    /// Bar a wallet from placing or increasing bets; its existing bets stay claimable
+    pub fn blacklist_bettor(ctx: Context<BlacklistBettor>, wallet: Pubkey) -> Result<()> {
+        let entry = &mut ctx.accounts.entry;
+        entry.arena = ctx.accounts.arena.key();
+        entry.wallet = wallet;
+
+        msg!("Bettor blacklisted: {}", wallet);
+        Ok(())
+    }
+
+    /// Lift a wallet's blacklisting, refunding the marker's rent to the authority
+    pub fn unblacklist_bettor(_ctx: Context<UnblacklistBettor>, wallet: Pubkey) -> Result<()> {
+        msg!("Bettor unblacklisted: {}", wallet);
+        Ok(())
+    }
+
+    /// Bar your own wallet from betting in every arena until `until`. An existing
+    /// exclusion can only be extended, never shortened.
+    pub fn self_exclude(ctx: Context<SelfExclude>, until: i64) -> Result<()> {
+        let exclusion = &mut ctx.accounts.exclusion;
+        require!(
+            until > now(ctx.remaining_accounts)? && until >= exclusion.until,
+            ArenaError::InvalidExclusion
+        );
+        exclusion.wallet = ctx.accounts.wallet.key();
+        exclusion.until = until;
+
+        msg!("Wallet {} self-excluded until {}", exclusion.wallet, until);
+        Ok(())
+    }
+
+    /// Publish the arena's official agent roster, replacing any previous one
+    pub fn set_roster(ctx: Context<SetRoster>, roster: Vec<String>) -> Result<()> {
+        require!(roster.len() <= MAX_ROSTER_LEN, ArenaError::InvalidRoster);
+        for (i, agent_id) in roster.iter().enumerate() {
+            validate_identifier(agent_id)?;
+            require!(!roster[..i].contains(agent_id), ArenaError::InvalidRoster);
+        }
+
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        arena.roster = roster;
+
+        msg!(
+            "Arena {} roster: {} agents",
+            arena.arena_id,
+            arena.roster.len()
+        );
+        Ok(())
+    }
+
+    /// Replace the arena's discovery tags, used by directories to filter arenas
+    pub fn set_tags(ctx: Context<SetTags>, tags: Vec<String>) -> Result<()> {
+        require!(tags.len() <= MAX_TAGS, ArenaError::InvalidTags);
+        for (i, tag) in tags.iter().enumerate() {
+            require!(
+                !tag.is_empty() && tag.len() <= MAX_TAG_LEN && !tags[..i].contains(tag),
+                ArenaError::InvalidTags
+            );
+        }
+
+        let arena = &mut ctx.accounts.arena;
+        arena.tags = tags;
+
+        msg!("Arena {} tags: {:?}", arena.arena_id, arena.tags);
+        Ok(())
+    }
+
+    /// Grow an arena created under an older schema to the current size. New fields
+    /// read as zero/empty/`None`, the defaults every instruction already handles.
+    pub fn realloc_arena(ctx: Context<ReallocArena>) -> Result<()> {
+        let arena_info = ctx.accounts.arena.to_account_info();
+
+        // The old layout may not deserialize as `Arena`, so only read its header
+        let header = {
+            let data = arena_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == *Arena::DISCRIMINATOR,
+                ArenaError::NotAnArena
+            );
+            ArenaHeader::deserialize(&mut &data[8..])?
+        };
+        require_keys_eq!(
+            header.authority,
+            ctx.accounts.authority.key(),
+            ArenaError::Unauthorized
+        );
+
+        let new_len = 8 + Arena::INIT_SPACE;
+        let old_len = arena_info.data_len();
+        require!(old_len < new_len, ArenaError::ArenaUpToDate);
+        grow_account(
+            &arena_info,
+            &ctx.accounts.authority.to_account_info(),
+            new_len,
+        )?;
+
+        msg!(
+            "Arena {} reallocated: {} -> {} bytes",
+            header.arena_id,
+            old_len,
+            new_len
+        );
+        Ok(())
+    }
+
+    /// Grow an agent record created under an older schema to the current size,
+    /// like `realloc_arena`; new fields read as zero
+    pub fn realloc_agent(ctx: Context<ReallocAgent>, agent_id: String) -> Result<()> {
+        let agent_info = ctx.accounts.agent.to_account_info();
+        {
+            // Only the discriminator is read; the old layout may not deserialize
+            let data = agent_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == *Agent::DISCRIMINATOR,
+                ArenaError::NotAnAgent
+            );
+        }
+
+        let new_len = 8 + Agent::INIT_SPACE;
+        let old_len = agent_info.data_len();
+        require!(old_len < new_len, ArenaError::AgentUpToDate);
+        grow_account(
+            &agent_info,
+            &ctx.accounts.authority.to_account_info(),
+            new_len,
+        )?;
+
+        msg!(
+            "Agent {} reallocated: {} -> {} bytes",
+            agent_id,
+            old_len,
+            new_len
+        );
+        Ok(())
+    }
+
+    /// Give an arena created before vaults existed its vault, moving the escrow
+    /// it holds (everything above its own rent) across
+    pub fn init_vault(ctx: Context<InitVault>) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        let vault = &mut ctx.accounts.vault;
+        vault.arena = arena.key();
+
+        let arena_info = arena.to_account_info();
+        let rent = Rent::get()?.minimum_balance(arena_info.data_len());
+        let escrow = arena_info.lamports().saturating_sub(rent);
+        if escrow > 0 {
+            pay_from_vault(&arena_info, &vault.to_account_info(), escrow)?;
+        }
+
+        msg!(
+            "Arena {} vault initialized: {} lamports moved",
+            arena.arena_id,
+            escrow
+        );
+        Ok(())
+    }
+
+    /// Register an agent in the arena with its starting balance. `entry_fee` must
+    /// equal the configured `agent_entry_fee`; it is seeded into the prize pool.
+    /// `identity` names the career the agent's results are credited to.
+    pub fn register_agent(
+        ctx: Context<RegisterAgent>,
+        agent_id: String,
+        name: String,
+        balance: u64,
+        entry_fee: u64,
+        identity: Pubkey,
+    ) -> Result<()> {
+        validate_identifier(&agent_id)?;
+        let arena = &mut ctx.accounts.arena;
+        check_roster(arena, &agent_id)?;
+        require!(
+            entry_fee == arena.config.agent_entry_fee,
+            ArenaError::InvalidEntryFee
+        );
+        if entry_fee > 0 {
+            require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+            require!(!arena.resolved(), ArenaError::ArenaResolved);
+            require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+            escrow_in(
+                arena,
+                &ctx.accounts.authority.to_account_info(),
+                &ctx.accounts.vault.to_account_info(),
+                entry_fee,
+            )?;
+            arena.seeded_amount += entry_fee;
+        }
+        let agent = &mut ctx.accounts.agent;
+        agent.arena = arena.key();
+        agent.agent_id = agent_id;
+        agent.name = name;
+        agent.balance = balance;
+        agent.registered_at = now(ctx.remaining_accounts)?;
+        agent.identity = identity;
+        mark_agent_seen(&mut ctx.accounts.seen, arena, &agent.agent_id);
+
+        arena.alive_agents += 1;
+        arena.total_balance_sum += balance;
+        arena.recompute_avg_balance();
+
+        arena_msg!(
+            arena,
+            "Agent registered: {} ({})",
+            agent.name,
+            agent.agent_id
+        );
+        Ok(())
+    }
+
+    /// Contribute SOL to a live agent's bankroll. Sponsorship is held in the
+    /// agent's own vault until its operator withdraws it; it is not a bet, never
+    /// enters the betting pools and leaves the agent's logged balance to the
+    /// operator's own reports.
+    pub fn sponsor_agent(ctx: Context<SponsorAgent>, agent_id: String, amount: u64) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        let agent = &ctx.accounts.agent;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(!agent.dead, ArenaError::AgentAlreadyDead);
+        require!(amount > 0, ArenaError::InvalidSponsorAmount);
+
+        transfer_lamports(
+            &ctx.accounts.sponsor.to_account_info(),
+            &ctx.accounts.agent_vault.to_account_info(),
+            amount,
+        )?;
+
+        let agent_vault = &mut ctx.accounts.agent_vault;
+        agent_vault.agent = agent.key();
+        agent_vault.sponsored += amount;
+
+        arena_msg!(
+            arena,
+            "Agent {} sponsored by {}: {} lamports",
+            agent_id,
+            ctx.accounts.sponsor.key(),
+            amount
+        );
+        Ok(())
+    }
+
+    /// Move sponsorship out of an agent's vault to the arena authority, which
+    /// operates the agents; the vault keeps its rent
+    pub fn withdraw_sponsorship(
+        ctx: Context<WithdrawSponsorship>,
+        agent_id: String,
+        amount: u64,
+    ) -> Result<()> {
+        let vault_info = ctx.accounts.agent_vault.to_account_info();
+        let rent = Rent::get()?.minimum_balance(vault_info.data_len());
+        require!(
+            amount > 0 && amount <= vault_info.lamports().saturating_sub(rent),
+            ArenaError::InvalidSponsorAmount
+        );
+
+        pay_from_vault(
+            &vault_info,
+            &ctx.accounts.authority.to_account_info(),
+            amount,
+        )?;
+
+        msg!(
+            "Sponsorship withdrawn from agent {}: {} lamports",
+            agent_id,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Record which software version and model build an agent runs
+    pub fn log_agent_version(
+        ctx: Context<LogAgentVersion>,
+        agent_id: String,
+        version: String,
+        model_hash: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            !version.is_empty() && version.len() <= MAX_VERSION_LEN,
+            ArenaError::InvalidVersion
+        );
+
+        let agent = &mut ctx.accounts.agent;
+        agent.version = version;
+        agent.model_hash = model_hash;
+
+        msg!("Agent {} version: {}", agent_id, agent.version);
+        Ok(())
+    }
+
+    /// Fold an agent that was mistakenly split off into the record it belongs to.
+    /// Both records must be alive; a dead agent's result is already on the books.
+    pub fn merge_agents(
+        ctx: Context<MergeAgents>,
+        keep_id: String,
+        merge_id: String,
+    ) -> Result<()> {
+        require!(keep_id != merge_id, ArenaError::InvalidMerge);
+
+        // Bets are keyed by agent id, so moving them would strand their pool
+        if let Some(pool) = load_pool(&ctx.accounts.merge_pool)? {
+            require!(pool.bet_count == 0, ArenaError::AgentHasBets);
+        }
+
+        let arena = &mut ctx.accounts.arena;
+        let keep = &mut ctx.accounts.keep;
+        let merged = &ctx.accounts.merged;
+        require!(!keep.dead && !merged.dead, ArenaError::AgentAlreadyDead);
+
+        // The merged balance stays in the alive sum, now counted under one agent
+        keep.balance += merged.balance;
+        keep.tx_count += merged.tx_count;
+        keep.services_completed += merged.services_completed;
+        arena.alive_agents = arena.alive_agents.saturating_sub(1);
+        arena.recompute_avg_balance();
+
+        arena_msg!(arena, "Agent {} merged into {}", merge_id, keep_id);
+        Ok(())
+    }
+
+    /// Record an agent's balance for an epoch; each epoch can be snapshotted once
+    pub fn snapshot_agent_balance(
+        ctx: Context<SnapshotAgentBalance>,
+        agent_id: String,
+        epoch: u64,
+    ) -> Result<()> {
+        let agent = &ctx.accounts.agent;
+        let snapshot = &mut ctx.accounts.snapshot;
+        // An epoch's snapshot is written once; a later balance belongs to a later epoch
+        require!(
+            snapshot.agent == Pubkey::default(),
+            ArenaError::SnapshotExists
+        );
+        snapshot.agent = agent.key();
+        snapshot.epoch = epoch;
+        snapshot.balance = agent.balance;
+        snapshot.taken_at = now(ctx.remaining_accounts)?;
+
+        arena_msg!(
+            ctx.accounts.arena,
+            "Agent {} balance at epoch {}: {}",
+            agent_id,
+            epoch,
+            snapshot.balance
+        );
+        Ok(())
+    }
+
+    /// Create the cross-arena career record for an agent identity
+    pub fn init_career(ctx: Context<InitCareer>, identity: Pubkey) -> Result<()> {
+        let career = &mut ctx.accounts.career;
+        career.identity = identity;
+
+        msg!("Agent career created: {}", identity);
+        Ok(())
+    }
+
+    /// Log an agent transaction, optionally annotated with a free-form memo
+    #[allow(clippy::too_many_arguments)]
     pub fn log_transaction(
         ctx: Context<LogTransaction>,
         transaction_id: String,
@@ -33,23 +499,102 @@ pub mod arena_logger {
         to_agent: String,
         amount: u64,
         service_type: String,
+        hour_index: i64,
+        memo: Option<String>,
+        external_ref: Option<[u8; 64]>,
     ) -> Result<()> {
+        validate_identifier(&transaction_id)?;
+        validate_identifier(&from_agent)?;
+        validate_identifier(&to_agent)?;
+        let memo = memo.unwrap_or_default();
+        require!(memo.len() <= MAX_MEMO_LEN, ArenaError::MemoTooLong);
         let transaction = &mut ctx.accounts.transaction;
         let arena = &mut ctx.accounts.arena;
 
+        // Logger retries replay the same transaction id; accept exact repeats as
+        // no-ops. An exact repeat names the bucket, stats and seen records the
+        // original created, so it pays no rent and writes nothing.
+        if transaction.arena != Pubkey::default() {
+            require!(
+                transaction.arena == arena.key()
+                    && transaction.from_agent == from_agent
+                    && transaction.to_agent == to_agent
+                    && transaction.amount == amount
+                    && transaction.service_type == service_type
+                    && transaction.memo == memo
+                    && transaction.external_ref == external_ref
+                    && transaction.hour_index == hour_index,
+                ArenaError::DuplicateTransaction
+            );
+            arena_msg!(arena, "Transaction already logged: {}", transaction_id);
+            return Ok(());
+        }
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(!arena.ready_to_resolve, ArenaError::TransactionCapReached);
+
+        // The logger computes the hour off-chain, so allow for its clock drift
+        // across an hour boundary
+        let timestamp = now(ctx.remaining_accounts)?;
+        require!(
+            (hour_index - timestamp / BUCKET_SECONDS).abs() <= 1,
+            ArenaError::InvalidHourIndex
+        );
+
         transaction.transaction_id = transaction_id;
         transaction.from_agent = from_agent;
         transaction.to_agent = to_agent;
         transaction.amount = amount;
         transaction.service_type = service_type;
-        transaction.timestamp = Clock::get()?.unix_timestamp;
+        transaction.timestamp = timestamp;
         transaction.arena = arena.key();
+        transaction.memo = memo;
+        transaction.external_ref = external_ref;
+        transaction.hour_index = hour_index;
+
+        let bucket = &mut ctx.accounts.bucket;
+        if bucket.arena == Pubkey::default() {
+            bucket.arena = arena.key();
+            bucket.hour_index = hour_index;
+        }
+        bucket.volume += amount;
+        bucket.transaction_count += 1;
+
+        let service_stats = &mut ctx.accounts.service_stats;
+        if service_stats.arena == Pubkey::default() {
+            service_stats.arena = arena.key();
+            service_stats.service_type = transaction.service_type.clone();
+            arena.distinct_service_types += 1;
+        }
+        service_stats.volume += amount;
+        service_stats.transaction_count += 1;
+
+        if let Some(agent) = ctx.accounts.from_agent_account.as_mut() {
+            agent.tx_count += 1;
+        }
+        // The receiving agent is paid for a service it delivered
+        if let Some(agent) = ctx.accounts.to_agent_account.as_mut() {
+            agent.services_completed += 1;
+        }
 
         // Update arena stats
         arena.total_transactions += 1;
+        if arena.total_transactions == arena.config.max_transactions {
+            arena.ready_to_resolve = true;
+            msg!(
+                "Arena {} reached {} transactions; ready to resolve",
+                arena.arena_id,
+                arena.total_transactions
+            );
+        }
         arena.total_volume += amount;
+        let fee_bps = service_fee_bps(&ctx.accounts.service_fee, arena.config.tx_fee_bps)?;
+        arena.accumulated_tx_fees += bps_of(amount, fee_bps);
+        mark_agent_seen(&mut ctx.accounts.from_seen, arena, &transaction.from_agent);
+        mark_agent_seen(&mut ctx.accounts.to_seen, arena, &transaction.to_agent);
 
-        msg!(
+        emit!(volume_updated(arena.key(), arena, transaction));
+        arena_msg!(
+            arena,
             "Transaction logged: {} -> {} | {} SOL",
             transaction.from_agent,
             transaction.to_agent,
@@ -59,6 +604,26 @@ pub mod arena_logger {
         Ok(())
     }
 
+    /// Set the notional fee rate for one service type, overriding `tx_fee_bps`
+    pub fn set_service_fee(
+        ctx: Context<SetServiceFee>,
+        service_type: String,
+        fee_bps: u16,
+    ) -> Result<()> {
+        require!(
+            !service_type.is_empty() && service_type.len() <= 20,
+            ArenaError::InvalidConfig
+        );
+        require!(fee_bps <= BPS_DENOMINATOR, ArenaError::InvalidConfig);
+        let service_fee = &mut ctx.accounts.service_fee;
+        service_fee.arena = ctx.accounts.arena.key();
+        service_fee.service_type = service_type;
+        service_fee.fee_bps = fee_bps;
+
+        msg!("Service {} fee: {} bps", service_fee.service_type, fee_bps);
+        Ok(())
+    }
+
     /// Log agent death
     pub fn log_death(
         ctx: Context<LogDeath>,
@@ -67,18 +632,105 @@ pub mod arena_logger {
         final_balance: u64,
         services_completed: u32,
     ) -> Result<()> {
-        let death = &mut ctx.accounts.death;
+        validate_identifier(&agent_id)?;
         let arena = &mut ctx.accounts.arena;
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+
+        // Deaths arrive in elimination order, so the first death takes last place
+        let placement = arena.total_agents.saturating_sub(arena.deaths_logged);
+        arena.deaths_logged += 1;
+
+        // No new stake may back an eliminated agent, whatever the betting window
+        let timestamp = now(ctx.remaining_accounts)?;
+        let pool = &mut ctx.accounts.pool;
+        require!(pool.frozen_at == 0, ArenaError::AgentAlreadyDead);
+        if pool.arena == Pubkey::default() {
+            pool.arena = arena.key();
+            pool.agent_id = agent_id.clone();
+        }
+        pool.frozen_at = timestamp;
+
+        // The agent leaves the alive set, so the average is kept current per event
+        let agent = &mut ctx.accounts.agent;
+        require!(!agent.dead, ArenaError::AgentAlreadyDead);
+        agent.dead = true;
+        arena.total_balance_sum = arena.total_balance_sum.saturating_sub(agent.balance);
+        arena.alive_agents = arena.alive_agents.saturating_sub(1);
+        arena.dead_agents += 1;
+        arena.recompute_avg_balance();
+        if let Some(career) = ctx.accounts.career.as_mut() {
+            career.losses += 1;
+            career.total_earnings += final_balance;
+            career.total_backed += pool.total_staked - pool.die_staked;
+        }
+
+        // Agents below the arena's service threshold are only tallied, saving rent
+        if services_completed < arena.config.min_services {
+            require!(
+                ctx.accounts.death.is_none(),
+                ArenaError::DeathBelowMinServices
+            );
+            arena.trivial_deaths += 1;
+
+            arena_msg!(
+                arena,
+                "Trivial agent death tallied: {} ({} services)",
+                agent_name,
+                services_completed
+            );
+            return Ok(());
+        }
+
+        let Some(death) = ctx.accounts.death.as_mut() else {
+            return err!(ArenaError::DeathAccountRequired);
+        };
 
         death.agent_id = agent_id;
         death.agent_name = agent_name;
         death.final_balance = final_balance;
         death.services_completed = services_completed;
-        death.timestamp = Clock::get()?.unix_timestamp;
+        death.placement = placement;
+        death.timestamp = timestamp;
         death.arena = arena.key();
 
-        msg!("Agent death logged: {} (Balance: {})", death.agent_name, final_balance);
+        arena_msg!(
+            arena,
+            "Agent death logged: {} (Balance: {})",
+            death.agent_name,
+            final_balance
+        );
+
+        Ok(())
+    }
+
+    /// Correct the final balance and services of a logged death (authority only)
+    pub fn amend_death(
+        ctx: Context<AmendDeath>,
+        agent_id: String,
+        final_balance: u64,
+        services_completed: u32,
+    ) -> Result<()> {
+        let death = &mut ctx.accounts.death;
+        let previous_balance = death.final_balance;
+        let previous_services = death.services_completed;
+        death.final_balance = final_balance;
+        death.services_completed = services_completed;
 
+        emit!(AmendedDeath {
+            arena: death.arena,
+            agent_id,
+            previous_balance,
+            final_balance,
+            previous_services,
+            services_completed,
+            amended_at: now(ctx.remaining_accounts)?,
+        });
+        msg!(
+            "Agent death amended: {} (Balance: {} -> {})",
+            death.agent_name,
+            previous_balance,
+            final_balance
+        );
         Ok(())
     }
 
@@ -91,14 +743,27 @@ pub mod arena_logger {
         gini_coefficient: u16,
     ) -> Result<()> {
         let arena = &mut ctx.accounts.arena;
+        // Deaths are permanent, and once agents are counted on-chain the split
+        // cannot claim more of them than have been seen
+        require!(
+            dead_agents >= arena.dead_agents && dead_agents >= arena.deaths_logged,
+            ArenaError::InconsistentStats
+        );
+        if arena.total_agents > 0 {
+            require!(
+                alive_agents as u64 + dead_agents as u64 <= arena.total_agents as u64,
+                ArenaError::InconsistentStats
+            );
+        }
 
-        arena.total_agents = alive_agents + dead_agents;
         arena.alive_agents = alive_agents;
         arena.dead_agents = dead_agents;
-        arena.avg_balance = avg_balance;
+        // An average over no alive agents is meaningless; store 0 rather than a stale value
+        arena.avg_balance = if alive_agents == 0 { 0 } else { avg_balance };
         arena.gini_coefficient = gini_coefficient;
 
-        msg!(
+        arena_msg!(
+            arena,
             "Stats updated: {} alive, {} dead, avg balance: {}",
             alive_agents,
             dead_agents,
@@ -108,198 +773,11055 @@ pub mod arena_logger {
         Ok(())
     }
 
-    /// Place a bet on an agent (user wallet interaction)
-    pub fn place_bet(
-        ctx: Context<PlaceBet>,
-        agent_id: String,
-        amount: u64,
-    ) -> Result<()> {
-        let bet = &mut ctx.accounts.bet;
+    /// Compute and store the Gini coefficient (0-100) of the given alive agent balances
+    pub fn compute_gini(ctx: Context<UpdateStats>, balances: Vec<u64>) -> Result<()> {
+        let mut balances = balances;
+        require!(!balances.is_empty(), ArenaError::NoAliveAgents);
+        require!(
+            balances.len() <= MAX_MEDIAN_INPUTS,
+            ArenaError::TooManyBalances
+        );
+
+        balances.sort_unstable();
+        let gini = gini_coefficient(&balances);
+
         let arena = &mut ctx.accounts.arena;
+        arena.gini_coefficient = gini;
 
-        // Transfer SOL from bettor to arena
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.bettor.key(),
-            &arena.key(),
-            amount,
+        arena_msg!(arena, "Gini updated: {} ({} agents)", gini, balances.len());
+        Ok(())
+    }
+
+    /// Compute and store the median of the given agent balances
+    pub fn compute_median(ctx: Context<UpdateStats>, balances: Vec<u64>) -> Result<()> {
+        let mut balances = balances;
+        require!(!balances.is_empty(), ArenaError::NoBalances);
+        require!(
+            balances.len() <= MAX_MEDIAN_INPUTS,
+            ArenaError::TooManyBalances
         );
-        anchor_lang::solana_program::program::invoke(
-            &ix,
-            &[
-                ctx.accounts.bettor.to_account_info(),
-                arena.to_account_info(),
-            ],
-        )?;
 
-        bet.bettor = ctx.accounts.bettor.key();
-        bet.agent_id = agent_id;
-        bet.amount = amount;
-        bet.timestamp = Clock::get()?.unix_timestamp;
-        bet.arena = arena.key();
-        bet.claimed = false;
+        balances.sort_unstable();
+        let mid = balances.len() / 2;
+        let median = if balances.len().is_multiple_of(2) {
+            ((balances[mid - 1] as u128 + balances[mid] as u128) / 2) as u64
+        } else {
+            balances[mid]
+        };
 
-        arena.total_bets += 1;
-        arena.total_bet_volume += amount;
+        let arena = &mut ctx.accounts.arena;
+        arena.median_balance = median;
 
-        msg!(
-            "Bet placed: {} on agent {} for {} lamports",
-            bet.bettor,
-            bet.agent_id,
-            amount
+        arena_msg!(
+            arena,
+            "Median balance updated: {} ({} agents)",
+            median,
+            balances.len()
         );
-
         Ok(())
     }
-}
 
-// Account Structures
+    /// The arena's full rule set (fees, caps, windows and thresholds), so a client
+    /// can check a bet before building it
+    pub fn arena_config(ctx: Context<ReadArenaConfig>) -> Result<ArenaConfig> {
+        Ok(ctx.accounts.arena.config.clone())
+    }
 
-#[derive(Accounts)]
-#[instruction(arena_id: String)]
-pub struct InitializeArena<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Arena::INIT_SPACE,
-        seeds = [b"arena", arena_id.as_bytes()],
-        bump
-    )]
-    pub arena: Account<'info, Arena>,
+    /// Summaries of up to `MAX_BATCH_LEN` arenas passed as remaining accounts, in
+    /// order, for directory pages. The packed result stays well under the 1024-byte
+    /// return data limit.
+    pub fn multi_arena_summary(ctx: Context<MultiArenaSummary>) -> Result<Vec<ArenaSummary>> {
+        require!(
+            ctx.remaining_accounts.len() <= MAX_BATCH_LEN,
+            ArenaError::BatchTooLarge
+        );
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+        let mut summaries = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts {
+            require_keys_eq!(*info.owner, crate::ID, ArenaError::InvalidBatch);
+            let arena = Arena::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            summaries.push(ArenaSummary {
+                arena: info.key(),
+                state: arena.state,
+                total_agents: arena.total_agents,
+                alive_agents: arena.alive_agents,
+                total_bets: arena.total_bets,
+                total_bet_volume: arena.total_bet_volume,
+                unique_bettors: arena.unique_bettors,
+                betting_closes_at: arena.betting_closes_at,
+            });
+        }
 
-    pub system_program: Program<'info, System>,
-}
+        Ok(summaries)
+    }
 
-#[derive(Accounts)]
-#[instruction(transaction_id: String)]
-pub struct LogTransaction<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Transaction::INIT_SPACE,
-        seeds = [b"transaction", transaction_id.as_bytes()],
-        bump
-    )]
-    pub transaction: Account<'info, Transaction>,
+    /// Sum logged volume over `[start_hour, end_hour]` from the `VolumeBucket`s
+    /// passed in `remaining_accounts`, in ascending hour order. Hours with no
+    /// bucket had no volume and are simply omitted.
+    pub fn volume_between(
+        ctx: Context<VolumeBetween>,
+        start_hour: i64,
+        end_hour: i64,
+    ) -> Result<u64> {
+        require!(start_hour <= end_hour, ArenaError::InvalidBucketRange);
+        require!(
+            ctx.remaining_accounts.len() <= MAX_BUCKETS_READ,
+            ArenaError::InvalidBucketRange
+        );
 
-    #[account(mut)]
-    pub arena: Account<'info, Arena>,
+        let arena = ctx.accounts.arena.key();
+        let mut volume: u64 = 0;
+        let mut last_hour = start_hour - 1;
+        for info in ctx.remaining_accounts {
+            require_keys_eq!(*info.owner, crate::ID, ArenaError::InvalidBucketRange);
+            let bucket = VolumeBucket::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            // Strictly ascending hours rule out counting a bucket twice
+            require!(
+                bucket.arena == arena
+                    && bucket.hour_index > last_hour
+                    && bucket.hour_index <= end_hour,
+                ArenaError::InvalidBucketRange
+            );
+            last_hour = bucket.hour_index;
+            volume += bucket.volume;
+        }
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+        Ok(volume)
+    }
 
-    pub system_program: Program<'info, System>,
-}
+    /// Audit `total_volume` against the amounts of the arena's logged transactions
+    pub fn recompute_volume(
+        ctx: Context<RecomputeVolume>,
+        transaction_amounts: Vec<u64>,
+    ) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        let sum = transaction_amounts
+            .iter()
+            .try_fold(0u64, |sum, amount| sum.checked_add(*amount))
+            .ok_or_else(|| error!(ArenaError::MathOverflow))?;
+        require!(sum == arena.total_volume, ArenaError::VolumeMismatch);
 
-#[derive(Accounts)]
-#[instruction(agent_id: String)]
-pub struct LogDeath<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + AgentDeath::INIT_SPACE,
-        seeds = [b"death", agent_id.as_bytes()],
-        bump
-    )]
-    pub death: Account<'info, AgentDeath>,
+        msg!(
+            "Volume verified: {} over {} transactions",
+            sum,
+            transaction_amounts.len()
+        );
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub arena: Account<'info, Arena>,
+    /// Score the arena 0-100 for directory ranking (see `health_score`)
+    pub fn compute_health(ctx: Context<UpdateStats>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let recent_transactions = arena.total_transactions - arena.health_tx_checkpoint;
+        let score = health_score(
+            arena.alive_agents,
+            arena.total_agents,
+            recent_transactions,
+            arena.unique_bettors,
+        );
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+        arena.health_score = score;
+        arena.health_tx_checkpoint = arena.total_transactions;
 
-    pub system_program: Program<'info, System>,
-}
+        msg!("Arena health: {}/100", score);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct UpdateStats<'info> {
-    #[account(mut, has_one = authority)]
-    pub arena: Account<'info, Arena>,
+    /// Emit a `Checkpoint` of the arena's counters so a restarting indexer can
+    /// resume from the last one instead of replaying every event
+    pub fn checkpoint(ctx: Context<UpdateStats>, block_height: u64) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        arena.checkpoint_id += 1;
 
-    pub authority: Signer<'info>,
-}
+        emit!(arena_checkpoint(
+            arena.key(),
+            arena,
+            block_height,
+            now(ctx.remaining_accounts)?
+        ));
+        msg!(
+            "Checkpoint {} at block {}",
+            arena.checkpoint_id,
+            block_height
+        );
+        Ok(())
+    }
+
+    /// Place a bet on an agent (user wallet interaction). Betting again on the same
+    /// agent adds to the existing bet, as `increase_bet` would.
+    pub fn place_bet(
+        ctx: Context<PlaceBet>,
+        agent_id: String,
+        amount: u64,
+        bet_side: BetSide,
+        predicted_placement: u32,
+    ) -> Result<()> {
+        validate_identifier(&agent_id)?;
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+
+        let timestamp = now(ctx.remaining_accounts)?;
+        require!(
+            ctx.accounts.blacklisted.data_is_empty(),
+            ArenaError::BettorBlacklisted
+        );
+        check_not_self_excluded(&ctx.accounts.self_exclusion, timestamp)?;
+        check_new_bet(
+            arena,
+            &agent_id,
+            ctx.accounts.agent.is_some(),
+            &ctx.accounts.pool,
+            &ctx.accounts.profile,
+            amount,
+            timestamp,
+        )?;
+
+        // Escrow SOL from the bettor in the arena's vault
+        escrow_in(
+            arena,
+            &ctx.accounts.bettor.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            amount,
+        )?;
+
+        open_or_add_to_bet(
+            arena,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.profile,
+            &mut ctx.accounts.bet,
+            ctx.accounts.bettor.key(),
+            agent_id,
+            amount,
+            bet_side,
+            predicted_placement,
+            timestamp,
+        )
+    }
+
+    /// Place a bet sized in whole credits, converted at the arena's `credit_value`
+    pub fn place_bet_credits(
+        ctx: Context<PlaceBet>,
+        agent_id: String,
+        credits: u64,
+        bet_side: BetSide,
+        predicted_placement: u32,
+    ) -> Result<()> {
+        let credit_value = ctx.accounts.arena.config.credit_value;
+        require!(credit_value > 0, ArenaError::CreditsDisabled);
+        let amount = credits
+            .checked_mul(credit_value)
+            .ok_or_else(|| error!(ArenaError::MathOverflow))?;
+
+        place_bet(ctx, agent_id, amount, bet_side, predicted_placement)
+    }
+
+    /// Place a bet in a token-denominated arena, under the same confirmation and
+    /// top-up rules as `place_bet`
+    pub fn place_bet_spl(
+        ctx: Context<PlaceBetSpl>,
+        agent_id: String,
+        amount: u64,
+        bet_side: BetSide,
+        predicted_placement: u32,
+    ) -> Result<()> {
+        validate_identifier(&agent_id)?;
+        let arena = &mut ctx.accounts.arena;
+        let timestamp = now(ctx.remaining_accounts)?;
+        require!(
+            ctx.accounts.blacklisted.data_is_empty(),
+            ArenaError::BettorBlacklisted
+        );
+        check_not_self_excluded(&ctx.accounts.self_exclusion, timestamp)?;
+        check_new_bet(
+            arena,
+            &agent_id,
+            ctx.accounts.agent.is_some(),
+            &ctx.accounts.pool,
+            &ctx.accounts.profile,
+            amount,
+            timestamp,
+        )?;
+
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.bettor_token.to_account_info(),
+                    mint: ctx.accounts.bet_mint.to_account_info(),
+                    to: ctx.accounts.token_vault.to_account_info(),
+                    authority: ctx.accounts.bettor.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.bet_mint.decimals,
+        )?;
+
+        open_or_add_to_bet(
+            arena,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.profile,
+            &mut ctx.accounts.bet,
+            ctx.accounts.bettor.key(),
+            agent_id,
+            amount,
+            bet_side,
+            predicted_placement,
+            timestamp,
+        )
+    }
+
+    /// Activate an escrowed bet, booking it into its pool
+    pub fn confirm_bet(ctx: Context<ConfirmBet>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+        require!(!arena.is_paused(), ArenaError::ArenaPaused);
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(!bet.confirmed, ArenaError::BetAlreadyConfirmed);
+        require!(ctx.accounts.pool.frozen_at == 0, ArenaError::PoolFrozen);
+
+        let timestamp = now(ctx.remaining_accounts)?;
+        require!(
+            timestamp.saturating_sub(bet.timestamp) < arena.config.confirm_timeout,
+            ArenaError::ConfirmationWindowClosed
+        );
+
+        bet.confirmed = true;
+        arena.unconfirmed_escrow -= bet.gross_amount;
+        record_bet(
+            arena,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.profile,
+            bet,
+        );
+
+        arena_msg!(
+            arena,
+            "Bet confirmed: {} on agent {}",
+            bet.bettor,
+            bet.agent_id
+        );
+        Ok(())
+    }
+
+    /// Refund and close a bet that was never confirmed (callable by anyone)
+    pub fn sweep_unconfirmed_bet(ctx: Context<SweepUnconfirmedBet>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &ctx.accounts.bet;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+
+        settle_unconfirmed(arena, bet, now(ctx.remaining_accounts)?)?;
+        pay_out(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.bettor.to_account_info(),
+            bet.gross_amount,
+        )?;
+
+        msg!(
+            "Unconfirmed bet swept: {} refunded {} lamports",
+            bet.bettor,
+            bet.gross_amount
+        );
+        Ok(())
+    }
+
+    /// Refund and close a token bet that was never confirmed (callable by anyone)
+    pub fn sweep_unconfirmed_bet_spl(ctx: Context<SweepUnconfirmedBetSpl>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &ctx.accounts.bet;
+
+        let amount = settle_unconfirmed(arena, bet, now(ctx.remaining_accounts)?)?;
+        let bump = [ctx.bumps.arena];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"arena", arena.arena_id.as_bytes(), &bump]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    mint: ctx.accounts.bet_mint.to_account_info(),
+                    to: ctx.accounts.bettor_token.to_account_info(),
+                    authority: arena.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            ctx.accounts.bet_mint.decimals,
+        )?;
+
+        msg!(
+            "Unconfirmed token bet swept: {} refunded {}",
+            bet.bettor,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Refund and close a batch of bets whose confirmation window lapsed. The first
+    /// `2 * count` remaining accounts are `(bet, bettor)` pairs; anyone may call it.
+    pub fn sweep_expired_bets<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepExpiredBets<'info>>,
+        count: u8,
+    ) -> Result<()> {
+        let count = count as usize;
+        require!(count <= MAX_BATCH_LEN, ArenaError::BatchTooLarge);
+        require!(
+            ctx.remaining_accounts.len() >= 2 * count,
+            ArenaError::InvalidBatch
+        );
+        let (pairs, rest) = ctx.remaining_accounts.split_at(2 * count);
+        let timestamp = now(rest)?;
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        let vault_info = ctx.accounts.vault.to_account_info();
+
+        let mut refunded = 0;
+        for pair in pairs.chunks_exact(2) {
+            let (bet_info, bettor_info) = (&pair[0], &pair[1]);
+            let bet = Account::<Bet>::try_from(bet_info)?;
+            require_keys_eq!(bet.arena, arena.key(), ArenaError::InvalidBatch);
+            require_keys_eq!(bet.bettor, bettor_info.key(), ArenaError::InvalidBatch);
+
+            settle_unconfirmed(arena, &bet, timestamp)?;
+            pay_out(arena, &vault_info, bettor_info, bet.gross_amount)?;
+            refunded += bet.gross_amount;
+            bet.close(bettor_info.clone())?;
+        }
+
+        msg!(
+            "Expired bets swept: {} refunded {} lamports",
+            count,
+            refunded
+        );
+        Ok(())
+    }
+
+    /// Add to an existing bet (same position, so the pool's bet count is unchanged).
+    /// Rejected if the agent pool's stake would end above `max_pool_after`, unless 0.
+    pub fn increase_bet(ctx: Context<IncreaseBet>, amount: u64, max_pool_after: u64) -> Result<()> {
+        let bet = &mut ctx.accounts.bet;
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        require!(!arena.is_paused(), ArenaError::ArenaPaused);
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(bet.confirmed, ArenaError::BetNotConfirmed);
+        require!(ctx.accounts.pool.frozen_at == 0, ArenaError::PoolFrozen);
+        require!(
+            ctx.accounts.blacklisted.data_is_empty(),
+            ArenaError::BettorBlacklisted
+        );
+        require!(amount > 0, ArenaError::InvalidBetAmount);
+        check_bet_amount(&arena.config, amount)?;
+        let pool_after =
+            ctx.accounts.pool.total_staked + arena.config.stake_after_entry_fee(amount)?;
+        require!(
+            max_pool_after == 0 || pool_after <= max_pool_after,
+            ArenaError::PoolMovedTooFar
+        );
+
+        let timestamp = now(ctx.remaining_accounts)?;
+        check_not_self_excluded(&ctx.accounts.self_exclusion, timestamp)?;
+        check_betting_open(arena, timestamp)?;
+        check_bet_cooldown(&arena.config, &ctx.accounts.profile, timestamp)?;
+
+        escrow_in(
+            arena,
+            &ctx.accounts.bettor.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            amount,
+        )?;
+
+        add_to_bet(
+            arena,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.profile,
+            bet,
+            amount,
+            timestamp,
+        )
+    }
+
+    /// Withdraw a bet while betting is open, refunding its stake and closing it.
+    /// The entry fee of a live bet is kept; an unconfirmed bet is refunded in full.
+    /// Either way `cancel_fee_bps` of the refund is withheld as protocol fees.
+    pub fn cancel_bet(ctx: Context<CancelBet>) -> Result<()> {
+        let bet = &ctx.accounts.bet;
+        let pool = &mut ctx.accounts.pool;
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        require!(!arena.is_paused(), ArenaError::ArenaPaused);
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        check_betting_open(arena, now(ctx.remaining_accounts)?)?;
+
+        let refund = if bet.confirmed {
+            pool.bet_count -= 1;
+            pool.remove_stake(bet.bet_side, bet.amount);
+            let profile = &mut ctx.accounts.profile;
+            profile.bet_count = profile.bet_count.saturating_sub(1);
+            profile.total_staked = profile.total_staked.saturating_sub(bet.amount);
+            arena.total_bets -= 1;
+            arena.remove_bet_volume(bet.bet_side, bet.amount);
+            bet.amount
+        } else {
+            arena.unconfirmed_escrow -= bet.gross_amount;
+            bet.gross_amount
+        };
+        let cancel_fee = bps_of(refund, arena.config.cancel_fee_bps);
+        arena.withdrawable_fees += cancel_fee;
+        let refund = refund - cancel_fee;
+        pay_out(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.bettor.to_account_info(),
+            refund,
+        )?;
+
+        emit!(bet_cancelled(arena.key(), bet, pool, refund));
+        arena_msg!(
+            arena,
+            "Bet cancelled: {} on agent {} refunded {} lamports ({} cancel fee)",
+            bet.bettor,
+            bet.agent_id,
+            refund,
+            cancel_fee
+        );
+
+        Ok(())
+    }
+
+    /// Hand a bet to a new owner, optionally charging the buyer `price` lamports.
+    /// The bet's stake moves with it from the seller's profile to the buyer's.
+    /// Positions only trade while betting is open on a live agent.
+    pub fn transfer_bet(ctx: Context<TransferBet>, new_owner: Pubkey, price: u64) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.is_paused(), ArenaError::ArenaPaused);
+        require!(arena.state == ArenaState::Open, ArenaError::BettingClosed);
+        require!(ctx.accounts.pool.frozen_at == 0, ArenaError::PoolFrozen);
+        require!(bet.confirmed, ArenaError::BetNotConfirmed);
+        // The two profiles must be distinct accounts for the stake to move
+        require_keys_neq!(new_owner, bet.bettor, ArenaError::SelfTransfer);
+
+        if price > 0 {
+            let Some(buyer) = ctx.accounts.buyer.as_ref() else {
+                return err!(ArenaError::BuyerMismatch);
+            };
+            require_keys_eq!(buyer.key(), new_owner, ArenaError::BuyerMismatch);
+            transfer_lamports(
+                &buyer.to_account_info(),
+                &ctx.accounts.bettor.to_account_info(),
+                price,
+            )?;
+        }
+
+        let previous = bet.bettor;
+        bet.bettor = new_owner;
+
+        let from_profile = &mut ctx.accounts.from_profile;
+        from_profile.bet_count = from_profile.bet_count.saturating_sub(1);
+        from_profile.total_staked = from_profile.total_staked.saturating_sub(bet.amount);
+        let to_profile = &mut ctx.accounts.to_profile;
+        if to_profile.arena == Pubkey::default() {
+            to_profile.arena = arena.key();
+            to_profile.bettor = new_owner;
+            arena.unique_bettors += 1;
+        }
+        to_profile.bet_count = to_profile.bet_count.saturating_add(1);
+        to_profile.total_staked = to_profile.total_staked.saturating_add(bet.amount);
+
+        msg!(
+            "Bet transferred: {} -> {} for {} lamports",
+            previous,
+            new_owner,
+            price
+        );
+        Ok(())
+    }
+
+    /// Claim a winning bet's share of the pool
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        // Also enforced by `has_one`; restated so the payout destination is explicit
+        require_keys_eq!(
+            ctx.accounts.bettor.key(),
+            bet.bettor,
+            ArenaError::Unauthorized
+        );
+
+        begin_payout(arena)?;
+        let mut payout = settle_claim(
+            arena,
+            &mut ctx.accounts.pool,
+            bet,
+            now(ctx.remaining_accounts)?,
+        )?;
+        // Bonuses read whichever records exist on-chain, not what the caller passed
+        let death = load_death(&ctx.accounts.death)?.filter(|death| death.arena == arena.key());
+        let agent = load_agent(&ctx.accounts.agent)?;
+        let bonus = placement_bonus(arena, bet, death.as_ref(), payout);
+        arena.withdrawable_fees -= bonus;
+        let productivity = productivity_bonus(arena, death.as_ref(), agent.as_ref(), payout);
+        arena.withdrawable_fees -= productivity;
+        payout += bonus + productivity;
+        ctx.accounts.profile.total_won += payout;
+        if let Some(career) = ctx.accounts.career.as_mut() {
+            if bet.bet_side == BetSide::Survive && bet.agent_id == arena.winner {
+                career.total_paid_to_backers += payout;
+            }
+        }
+        pay_out(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.bettor.to_account_info(),
+            payout,
+        )?;
+        arena.processing = false;
+
+        arena_msg!(
+            arena,
+            "Winnings claimed: {} received {} lamports ({} placement, {} productivity bonus)",
+            bet.bettor,
+            payout,
+            bonus,
+            productivity
+        );
+        Ok(())
+    }
+
+    /// Settle someone else's unclaimed winning bet once the keeper grace period
+    /// (at least `MIN_KEEPER_GRACE`) has passed; the caller keeps
+    /// `keeper_reward_bps` of the payout and the bet's rent returns to the bettor
+    pub fn keeper_settle(ctx: Context<KeeperSettle>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        let timestamp = now(ctx.remaining_accounts)?;
+        let grace = arena.config.keeper_grace.max(MIN_KEEPER_GRACE);
+        require!(
+            timestamp >= arena.resolved_at.saturating_add(grace),
+            ArenaError::KeeperGracePeriod
+        );
+
+        begin_payout(arena)?;
+        let payout = settle_claim(arena, &mut ctx.accounts.pool, bet, timestamp)?;
+        let reward = bps_of(payout, arena.config.keeper_reward_bps);
+        ctx.accounts.profile.total_won += payout - reward;
+        let vault_info = ctx.accounts.vault.to_account_info();
+        pay_out(
+            arena,
+            &vault_info,
+            &ctx.accounts.bettor.to_account_info(),
+            payout - reward,
+        )?;
+        pay_out(
+            arena,
+            &vault_info,
+            &ctx.accounts.keeper.to_account_info(),
+            reward,
+        )?;
+        arena.processing = false;
+
+        arena_msg!(
+            arena,
+            "Keeper settled: {} received {} lamports, keeper {} earned {}",
+            bet.bettor,
+            payout - reward,
+            ctx.accounts.keeper.key(),
+            reward
+        );
+        Ok(())
+    }
+
+    /// Approve a payout to a winning bettor for an external payout system to
+    /// execute later. The amount must be exactly what the bet is entitled to.
+    pub fn authorize_payout(
+        ctx: Context<AuthorizePayout>,
+        bettor: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        let bet = &ctx.accounts.bet;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        require!(
+            claim_status(arena, bet) == ClaimReason::Eligible,
+            ArenaError::NotWinningBet
+        );
+        let entitlement = compute_payout(arena, &ctx.accounts.pool, bet)?;
+        require!(
+            amount > 0 && amount == entitlement,
+            ArenaError::PayoutNotEntitlement
+        );
+
+        let authorization = &mut ctx.accounts.authorization;
+        authorization.arena = arena.key();
+        authorization.bet = bet.key();
+        authorization.bettor = bettor;
+        authorization.amount = amount;
+
+        msg!("Payout authorized: {} lamports to {}", amount, bettor);
+        Ok(())
+    }
+
+    /// Execute an authorized payout; anyone may call it. The bet is settled for the
+    /// authorized amount, which must still be its full entitlement.
+    pub fn execute_authorized_payout(ctx: Context<ExecuteAuthorizedPayout>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+        let amount = ctx.accounts.authorization.amount;
+
+        begin_payout(arena)?;
+        let entitlement = settle_claim(
+            arena,
+            &mut ctx.accounts.pool,
+            bet,
+            now(ctx.remaining_accounts)?,
+        )?;
+        require!(amount == entitlement, ArenaError::PayoutNotEntitlement);
+        ctx.accounts.profile.total_won += amount;
+        pay_out(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.bettor.to_account_info(),
+            amount,
+        )?;
+        arena.processing = false;
+
+        arena_msg!(
+            arena,
+            "Authorized payout executed: {} received {} lamports",
+            bet.bettor,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Claim a winning bet in a token-denominated arena
+    pub fn claim_winnings_spl(ctx: Context<ClaimWinningsSpl>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+
+        begin_payout(arena)?;
+        let payout = settle_claim(
+            arena,
+            &mut ctx.accounts.pool,
+            bet,
+            now(ctx.remaining_accounts)?,
+        )?;
+        ctx.accounts.profile.total_won += payout;
+        // Persist the claim before the token CPI, which may invoke transfer hooks
+        bet.exit(&crate::ID)?;
+        ctx.accounts.profile.exit(&crate::ID)?;
+        let bump = [ctx.bumps.arena];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"arena", arena.arena_id.as_bytes(), &bump]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    mint: ctx.accounts.bet_mint.to_account_info(),
+                    to: ctx.accounts.bettor_token.to_account_info(),
+                    authority: arena.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            payout,
+            ctx.accounts.bet_mint.decimals,
+        )?;
+        arena.processing = false;
+
+        arena_msg!(
+            arena,
+            "Token winnings claimed: {} received {}",
+            bet.bettor,
+            payout
+        );
+        Ok(())
+    }
+
+    /// Return a bet's principal from an arena in refund-only mode
+    pub fn refund_bet(ctx: Context<RefundBet>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+
+        let amount = settle_refund(arena, bet)?;
+        pay_out(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.bettor.to_account_info(),
+            amount,
+        )?;
+
+        arena_msg!(arena, "Bet refunded: {} received {}", bet.bettor, amount);
+        Ok(())
+    }
+
+    /// Return a token bet's principal from an arena in refund-only mode
+    pub fn refund_bet_spl(ctx: Context<RefundBetSpl>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+
+        let amount = settle_refund(arena, bet)?;
+        let bump = [ctx.bumps.arena];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"arena", arena.arena_id.as_bytes(), &bump]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    mint: ctx.accounts.bet_mint.to_account_info(),
+                    to: ctx.accounts.bettor_token.to_account_info(),
+                    authority: arena.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            ctx.accounts.bet_mint.decimals,
+        )?;
+
+        arena_msg!(
+            arena,
+            "Token bet refunded: {} received {}",
+            bet.bettor,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Commit to off-chain computed payouts for mass settlement. Once set, winners
+    /// claim with `claim_with_proof` instead of `claim_winnings`.
+    pub fn set_payout_root(ctx: Context<SetPayoutRoot>, root: [u8; 32]) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.resolved(), ArenaError::ArenaNotResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        require!(
+            arena.payout_root.is_none(),
+            ArenaError::PayoutRootAlreadySet
+        );
+        arena.payout_root = Some(root);
+
+        msg!("Payout root set for arena {}", arena.arena_id);
+        Ok(())
+    }
+
+    /// Claim a precomputed payout by proving `(bet, bettor, amount)` is in the
+    /// payout root. The bet is marked claimed like any other settlement.
+    pub fn claim_with_proof(
+        ctx: Context<ClaimWithProof>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let bet = &mut ctx.accounts.bet;
+        require!(!arena.is_paused(), ArenaError::ArenaPaused);
+        require!(!arena.voided(), ArenaError::ArenaVoided);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        check_result_final(arena, now(ctx.remaining_accounts)?)?;
+        let Some(root) = arena.payout_root else {
+            return err!(ArenaError::PayoutRootMissing);
+        };
+        require!(!bet.claimed, ArenaError::AlreadyClaimed);
+        require!(bet.confirmed, ArenaError::BetNotConfirmed);
+
+        let bettor = ctx.accounts.bettor.key();
+        let leaf = payout_leaf(&arena.key(), &bet.key(), &bettor, amount);
+        require!(
+            verify_proof(leaf, &proof, root),
+            ArenaError::InvalidPayoutProof
+        );
+
+        bet.claimed = true;
+        arena.total_claimed += amount;
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.arena = arena.key();
+        receipt.bet = bet.key();
+        receipt.bettor = bettor;
+        receipt.amount = amount;
+        ctx.accounts.profile.total_won += amount;
+
+        begin_payout(arena)?;
+        pay_out(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.bettor.to_account_info(),
+            amount,
+        )?;
+        arena.processing = false;
+
+        arena_msg!(
+            arena,
+            "Proof claim: {} received {} lamports",
+            bettor,
+            amount
+        );
+        Ok(())
+    }
+
+    /// Preview whether a bet can be claimed and what it would pay
+    pub fn preview_claim(ctx: Context<PreviewClaim>) -> Result<ClaimPreview> {
+        let arena = &ctx.accounts.arena;
+        let bet = &ctx.accounts.bet;
+
+        let reason = claim_status(arena, bet);
+        let payout = match reason {
+            ClaimReason::Eligible => compute_payout(arena, &ctx.accounts.pool, bet)?,
+            _ => 0,
+        };
+
+        Ok(ClaimPreview {
+            eligible: reason == ClaimReason::Eligible,
+            reason_code: reason as u8,
+            payout,
+            void_reason: arena.void_reason,
+        })
+    }
+
+    /// Aggregate position of `bettor` in this arena, and whether a bet on the
+    /// survive winner or the first to die is still waiting to be claimed. Only
+    /// first place is checked on a podium.
+    pub fn my_positions(ctx: Context<MyPositions>, bettor: Pubkey) -> Result<BettorPosition> {
+        let arena = &ctx.accounts.arena;
+        let profile = &ctx.accounts.profile;
+
+        let mut has_unclaimed = false;
+        for info in [&ctx.accounts.winner_bet, &ctx.accounts.first_dead_bet] {
+            if let Some(bet) = load_bet(info)? {
+                // A bet sold on keeps the seller-seeded address but is no longer theirs
+                has_unclaimed |= bet.arena == arena.key()
+                    && bet.bettor == bettor
+                    && claim_status(arena, &bet) == ClaimReason::Eligible;
+            }
+        }
+
+        msg!("Positions of {}: {} bets", bettor, profile.bet_count);
+        Ok(BettorPosition {
+            bet_count: profile.bet_count,
+            total_staked: profile.total_staked,
+            total_won: profile.total_won,
+            has_unclaimed,
+        })
+    }
+
+    /// Worst-case payout obligation if `agent_id` were declared the winner: both
+    /// markets' pots after fees plus the seed when the result pays out, otherwise a
+    /// refund of every stake
+    pub fn max_liability(ctx: Context<MaxLiability>, agent_id: String) -> Result<u64> {
+        let arena = &ctx.accounts.arena;
+        let pays_out = winner_backed(&ctx.accounts.pool)?
+            && arena.total_bet_volume >= arena.config.min_pool_to_payout;
+        let liability = if pays_out {
+            let fee_bps = arena.config.fee_bps;
+            arena.total_bet_volume
+                - bps_of(arena.side_volume(BetSide::Survive), fee_bps)
+                - bps_of(arena.side_volume(BetSide::Die), fee_bps)
+                + arena.seeded_amount
+        } else {
+            arena.total_bet_volume
+        };
+
+        msg!("Liability if {} wins: {}", agent_id, liability);
+        Ok(liability)
+    }
+
+    /// Winnings still owed to `agent_id`'s backers: the pots it won minus what has
+    /// been claimed. Includes rounding dust no single claim can withdraw. Zero
+    /// before resolution, in refund mode, or for an agent that did not win.
+    pub fn outstanding_claims(ctx: Context<MaxLiability>, agent_id: String) -> Result<u64> {
+        let owed = load_pool(&ctx.accounts.pool)?
+            .map_or(0, |pool| unclaimed_winnings(&ctx.accounts.arena, &pool));
+
+        msg!("Outstanding claims on {}: {}", agent_id, owed);
+        Ok(owed)
+    }
+
+    /// Close a settled arena's pool once its backers have been paid, returning the
+    /// rent to the authority. Claims on the agent are impossible afterwards.
+    pub fn close_pool(ctx: Context<ClosePool>, agent_id: String) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        let pool = &ctx.accounts.pool;
+        require!(
+            arena.resolved() || arena.refund_mode(),
+            ArenaError::ArenaNotSettled
+        );
+        require!(!arena.under_review, ArenaError::ResultUnderReview);
+        // Each claim rounds down, leaving up to a lamport per bet that nobody can claim
+        require!(
+            unclaimed_winnings(arena, pool) <= pool.bet_count,
+            ArenaError::PoolHasUnclaimedBets
+        );
+
+        msg!("Pool closed: {}", agent_id);
+        Ok(())
+    }
+
+    /// Add an operator-funded jackpot paid to survive-market winners on top of the pool.
+    /// Seed money is never part of bet principal, so refunds do not return it.
+    pub fn seed_pool(ctx: Context<SeedPool>, amount: u64) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(amount > 0, ArenaError::InvalidBetAmount);
+
+        escrow_in(
+            arena,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            amount,
+        )?;
+        arena.seeded_amount += amount;
+
+        msg!(
+            "Pool seeded: {} lamports (total seed {})",
+            amount,
+            arena.seeded_amount
+        );
+        Ok(())
+    }
+
+    /// Update arena configuration (authority only, before resolution)
+    pub fn configure_arena(ctx: Context<ConfigureArena>, update: ArenaConfigUpdate) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+
+        if let Some(min_duration) = update.min_duration {
+            require!(min_duration >= 0, ArenaError::InvalidConfig);
+            arena.config.min_duration = min_duration;
+        }
+        if let Some(min_services) = update.min_services {
+            arena.config.min_services = min_services;
+        }
+        if let Some(fee_bps) = update.fee_bps {
+            require!(fee_bps <= BPS_DENOMINATOR, ArenaError::InvalidConfig);
+            arena.config.fee_bps = fee_bps;
+        }
+        if let Some(verbose) = update.verbose {
+            arena.config.verbose = verbose;
+        }
+        if let Some(lamport_granularity) = update.lamport_granularity {
+            arena.config.lamport_granularity = lamport_granularity;
+        }
+        if let Some(bet_cooldown) = update.bet_cooldown {
+            require!(bet_cooldown >= 0, ArenaError::InvalidConfig);
+            arena.config.bet_cooldown = bet_cooldown;
+        }
+        if let Some(tx_fee_bps) = update.tx_fee_bps {
+            require!(tx_fee_bps <= BPS_DENOMINATOR, ArenaError::InvalidConfig);
+            arena.config.tx_fee_bps = tx_fee_bps;
+        }
+        if let Some(confirm_threshold) = update.confirm_threshold {
+            arena.config.confirm_threshold = confirm_threshold;
+        }
+        if let Some(confirm_timeout) = update.confirm_timeout {
+            require!(confirm_timeout >= 0, ArenaError::InvalidConfig);
+            arena.config.confirm_timeout = confirm_timeout;
+        }
+        if let Some(entry_fee_bps) = update.entry_fee_bps {
+            require!(entry_fee_bps < BPS_DENOMINATOR, ArenaError::InvalidConfig);
+            arena.config.entry_fee_bps = entry_fee_bps;
+        }
+        if let Some(snipe_window) = update.snipe_window {
+            require!(snipe_window >= 0, ArenaError::InvalidConfig);
+            arena.config.snipe_window = snipe_window;
+        }
+        if let Some(snipe_extension) = update.snipe_extension {
+            require!(snipe_extension >= 0, ArenaError::InvalidConfig);
+            arena.config.snipe_extension = snipe_extension;
+        }
+        if let Some(max_extensions) = update.max_extensions {
+            arena.config.max_extensions = max_extensions;
+        }
+        if let Some(keeper_grace) = update.keeper_grace {
+            require!(keeper_grace >= MIN_KEEPER_GRACE, ArenaError::InvalidConfig);
+            arena.config.keeper_grace = keeper_grace;
+        }
+        if let Some(keeper_reward_bps) = update.keeper_reward_bps {
+            require!(
+                keeper_reward_bps <= MAX_KEEPER_REWARD_BPS,
+                ArenaError::InvalidConfig
+            );
+            arena.config.keeper_reward_bps = keeper_reward_bps;
+        }
+        if let Some(placement_bonus_bps) = update.placement_bonus_bps {
+            arena.config.placement_bonus_bps = placement_bonus_bps;
+        }
+        if let Some(min_pool_to_payout) = update.min_pool_to_payout {
+            arena.config.min_pool_to_payout = min_pool_to_payout;
+        }
+        if let Some(cancel_fee_bps) = update.cancel_fee_bps {
+            require!(cancel_fee_bps < BPS_DENOMINATOR, ArenaError::InvalidConfig);
+            arena.config.cancel_fee_bps = cancel_fee_bps;
+        }
+        if let Some(max_transactions) = update.max_transactions {
+            require!(
+                max_transactions == 0 || max_transactions > arena.total_transactions,
+                ArenaError::InvalidConfig
+            );
+            arena.config.max_transactions = max_transactions;
+        }
+        if let Some(credit_value) = update.credit_value {
+            arena.config.credit_value = credit_value;
+        }
+        if let Some(dispute_window) = update.dispute_window {
+            require!(dispute_window >= 0, ArenaError::InvalidConfig);
+            arena.config.dispute_window = dispute_window;
+        }
+        if let Some(dispute_threshold) = update.dispute_threshold {
+            require!(dispute_threshold > 0, ArenaError::InvalidConfig);
+            arena.config.dispute_threshold = dispute_threshold;
+        }
+        if let Some(declaration_bond) = update.declaration_bond {
+            arena.config.declaration_bond = declaration_bond;
+        }
+        if let Some(max_bet_ratio_bps) = update.max_bet_ratio_bps {
+            require!(
+                max_bet_ratio_bps <= BPS_DENOMINATOR,
+                ArenaError::InvalidConfig
+            );
+            arena.config.max_bet_ratio_bps = max_bet_ratio_bps;
+        }
+        if let Some(productive_services) = update.productive_services {
+            arena.config.productive_services = productive_services;
+        }
+        if let Some(productivity_bonus_bps) = update.productivity_bonus_bps {
+            arena.config.productivity_bonus_bps = productivity_bonus_bps;
+        }
+        if let Some(agent_entry_fee) = update.agent_entry_fee {
+            arena.config.agent_entry_fee = agent_entry_fee;
+        }
+
+        msg!("Arena configured: {}", arena.arena_id);
+        Ok(())
+    }
+
+    /// Declare the winning agent and resolve the arena (settles the survive market only)
+    pub fn declare_winner(
+        ctx: Context<DeclareWinner>,
+        agent_id: String,
+        sol_usd_price: u64,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let resolver = ctx.accounts.resolver.key();
+        let backed = winner_backed(&ctx.accounts.winner_pool)?;
+        check_reserves(arena, &ctx.accounts.vault.to_account_info())?;
+        resolve_arena(arena, resolver, backed, now(ctx.remaining_accounts)?)?;
+        record_settlement_price(arena, sol_usd_price)?;
+        arena.winner = agent_id;
+        post_declaration_bond(
+            arena,
+            &ctx.accounts.resolver.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+        )?;
+        if let Some(career) = ctx.accounts.career.as_mut() {
+            credit_winner_career(arena, career, &ctx.accounts.winner_pool)?;
+        }
+
+        msg!("Winner declared: {}", arena.winner);
+        Ok(())
+    }
+
+    /// Set the podium payout split, in bps per place (1st first), summing to 10000.
+    /// An empty list restores winner-takes-all.
+    pub fn set_payout_tiers(ctx: Context<SetPayoutTiers>, payout_tiers: Vec<u16>) -> Result<()> {
+        require!(
+            payout_tiers.len() <= MAX_PAYOUT_TIERS,
+            ArenaError::InvalidPayoutTiers
+        );
+        if !payout_tiers.is_empty() {
+            let total: u32 = payout_tiers.iter().map(|&bps| bps as u32).sum();
+            require!(
+                total == BPS_DENOMINATOR as u32 && !payout_tiers.contains(&0),
+                ArenaError::InvalidPayoutTiers
+            );
+        }
+
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        arena.payout_tiers = payout_tiers;
+
+        msg!(
+            "Arena {} payout tiers: {:?}",
+            arena.arena_id,
+            arena.payout_tiers
+        );
+        Ok(())
+    }
+
+    /// Resolve the survive market as a podium, one agent per payout tier in
+    /// finishing order. The first `ranked_agents.len()` remaining accounts are the
+    /// ranked agents' pool PDAs in the same order (uninitialized if unbacked).
+    pub fn declare_podium(
+        ctx: Context<DeclarePodium>,
+        ranked_agents: Vec<String>,
+        sol_usd_price: u64,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(
+            !arena.payout_tiers.is_empty() && ranked_agents.len() == arena.payout_tiers.len(),
+            ArenaError::InvalidPodium
+        );
+        require!(
+            ctx.remaining_accounts.len() >= ranked_agents.len(),
+            ArenaError::InvalidPodium
+        );
+
+        let arena_key = arena.key();
+        let mut backed_bps = 0;
+        for (place, agent_id) in ranked_agents.iter().enumerate() {
+            validate_identifier(agent_id)?;
+            require!(
+                !ranked_agents[..place].contains(agent_id),
+                ArenaError::InvalidPodium
+            );
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"pool", arena_key.as_ref(), agent_id.as_bytes()],
+                &crate::ID,
+            );
+            let pool_info = &ctx.remaining_accounts[place];
+            require_keys_eq!(pool_info.key(), expected, ArenaError::InvalidPodium);
+            if winner_backed(pool_info)? {
+                backed_bps += arena.payout_tiers[place];
+            }
+        }
+
+        let resolver = ctx.accounts.resolver.key();
+        let timestamp = now(&ctx.remaining_accounts[ranked_agents.len()..])?;
+        check_reserves(arena, &ctx.accounts.vault.to_account_info())?;
+        resolve_arena(arena, resolver, backed_bps > 0, timestamp)?;
+        record_settlement_price(arena, sol_usd_price)?;
+        post_declaration_bond(
+            arena,
+            &ctx.accounts.resolver.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+        )?;
+        arena.winner = ranked_agents[0].clone();
+        arena.podium = ranked_agents;
+        arena.podium_backed_bps = backed_bps;
+
+        msg!("Podium declared: {:?}", arena.podium);
+        Ok(())
+    }
+
+    /// Resolve the survive market by splitting the pot across surviving agents in
+    /// proportion to their weights (e.g. final balances), listed by descending
+    /// weight. Each backer is paid by stake within their agent's slice. The first
+    /// `shares.len()` remaining accounts are the agents' pool PDAs in the same
+    /// order (uninitialized if unbacked); at most `MAX_BATCH_LEN` agents.
+    pub fn declare_proportional(
+        ctx: Context<DeclarePodium>,
+        shares: Vec<AgentShare>,
+        sol_usd_price: u64,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(shares.len() <= MAX_BATCH_LEN, ArenaError::BatchTooLarge);
+        require!(
+            !shares.is_empty() && ctx.remaining_accounts.len() >= shares.len(),
+            ArenaError::InvalidShares
+        );
+
+        let arena_key = arena.key();
+        let mut backed_weight = 0u64;
+        for (place, share) in shares.iter().enumerate() {
+            validate_identifier(&share.agent_id)?;
+            require!(
+                share.weight > 0
+                    && shares[..place]
+                        .iter()
+                        .all(|prev| prev.agent_id != share.agent_id && prev.weight >= share.weight),
+                ArenaError::InvalidShares
+            );
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"pool", arena_key.as_ref(), share.agent_id.as_bytes()],
+                &crate::ID,
+            );
+            let pool_info = &ctx.remaining_accounts[place];
+            require_keys_eq!(pool_info.key(), expected, ArenaError::InvalidShares);
+            if let Some(pool) = load_pool(pool_info)? {
+                // A frozen pool belongs to an agent that died
+                require!(pool.frozen_at == 0, ArenaError::InvalidShares);
+                if pool.side_staked(BetSide::Survive) > 0 {
+                    backed_weight = backed_weight
+                        .checked_add(share.weight)
+                        .ok_or_else(|| error!(ArenaError::MathOverflow))?;
+                }
+            }
+        }
+
+        let resolver = ctx.accounts.resolver.key();
+        let timestamp = now(&ctx.remaining_accounts[shares.len()..])?;
+        check_reserves(arena, &ctx.accounts.vault.to_account_info())?;
+        resolve_arena(arena, resolver, backed_weight > 0, timestamp)?;
+        record_settlement_price(arena, sol_usd_price)?;
+        post_declaration_bond(
+            arena,
+            &ctx.accounts.resolver.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+        )?;
+        arena.winner = shares[0].agent_id.clone();
+        arena.share_weights = shares.iter().map(|share| share.weight).collect();
+        arena.podium = shares.into_iter().map(|share| share.agent_id).collect();
+        arena.backed_weight = backed_weight;
+
+        msg!("Proportional result declared: {:?}", arena.podium);
+        Ok(())
+    }
+
+    /// Declare the alive agent with the highest balance the winner. The first
+    /// `2 * count` remaining accounts are `(agent, pool)` pairs covering every
+    /// alive agent (at most `MAX_BATCH_LEN`); a pool may be uninitialized if nobody
+    /// backed the agent.
+    ///
+    /// Ties are broken deterministically:
+    /// 1. the agent whose pool took its first bet earliest wins (unbacked agents last)
+    /// 2. if still tied, the smallest `tiebreak_key` wins: the agent id hashed with
+    ///    the revealed randomness, or the agent id itself if none was committed
+    ///
+    /// A tie that reaches step 2 while committed randomness is unrevealed voids the
+    /// arena instead, so withholding the reveal cannot steer the result.
+    pub fn auto_declare_winner<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AutoDeclareWinner<'info>>,
+        count: u8,
+        sol_usd_price: u64,
+    ) -> Result<()> {
+        let count = count as usize;
+        require!(count <= MAX_BATCH_LEN, ArenaError::BatchTooLarge);
+        let arena = &mut ctx.accounts.arena;
+        require!(
+            count as u32 == arena.alive_agents && ctx.remaining_accounts.len() >= 2 * count,
+            ArenaError::InvalidBatch
+        );
+        let (pairs, rest) = ctx.remaining_accounts.split_at(2 * count);
+
+        let arena_key = arena.key();
+        let mut seen: Vec<Pubkey> = Vec::with_capacity(count);
+        // (balance, first bet time, tie-break key, agent id, backed) of the best
+        // candidate so far
+        let mut best: Option<(u64, i64, Vec<u8>, String, bool)> = None;
+        // Whether the best candidate so far shares its balance and first bet time
+        let mut tied = false;
+        for pair in pairs.chunks_exact(2) {
+            let agent = Account::<Agent>::try_from(&pair[0])?;
+            require_keys_eq!(agent.arena, arena_key, ArenaError::InvalidBatch);
+            require!(
+                !agent.dead && !seen.contains(&agent.key()),
+                ArenaError::InvalidBatch
+            );
+            seen.push(agent.key());
+
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"pool", arena_key.as_ref(), agent.agent_id.as_bytes()],
+                &crate::ID,
+            );
+            require_keys_eq!(pair[1].key(), expected, ArenaError::InvalidBatch);
+            let pool = load_pool(&pair[1])?;
+            let first_bet_at = pool
+                .as_ref()
+                .filter(|pool| pool.first_bet_at > 0)
+                .map_or(i64::MAX, |pool| pool.first_bet_at);
+
+            let key = tiebreak_key(arena, &agent.agent_id);
+            let candidate = (agent.balance, first_bet_at, key.as_slice());
+            let same = best.as_ref().is_some_and(|(balance, first, _, _, _)| {
+                (*balance, *first) == (agent.balance, first_bet_at)
+            });
+            if best
+                .as_ref()
+                .is_none_or(|(balance, first, best_key, _, _)| {
+                    outranks(candidate, (*balance, *first, best_key))
+                })
+            {
+                tied = same;
+                best = Some((
+                    agent.balance,
+                    first_bet_at,
+                    key,
+                    agent.agent_id.clone(),
+                    winner_backed(&pair[1])?,
+                ));
+            } else {
+                tied |= same;
+            }
+        }
+        let Some((_, _, _, winner, backed)) = best else {
+            return err!(ArenaError::InvalidBatch);
+        };
+
+        let resolver = ctx.accounts.resolver.key();
+        if tied && randomness_withheld(arena) {
+            require_keys_eq!(
+                resolver,
+                arena.oracle.unwrap_or(arena.authority),
+                ArenaError::Unauthorized
+            );
+            require!(!arena.resolved(), ArenaError::ArenaResolved);
+            require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+            arena.transition(ArenaState::Voided)?;
+            arena.void_reason = VOID_REASON_UNREVEALED_RANDOMNESS;
+
+            emit!(ArenaVoided {
+                arena: arena.key(),
+                void_reason: arena.void_reason,
+                void_note: arena.void_note.clone(),
+                voided_at: now(rest)?,
+            });
+            msg!(
+                "Arena {} voided: tie needs unrevealed randomness",
+                arena.arena_id
+            );
+            return Ok(());
+        }
+        check_reserves(arena, &ctx.accounts.vault.to_account_info())?;
+        resolve_arena(arena, resolver, backed, now(rest)?)?;
+        record_settlement_price(arena, sol_usd_price)?;
+        arena.winner = winner;
+        post_declaration_bond(
+            arena,
+            &ctx.accounts.resolver.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+        )?;
+
+        msg!("Winner auto-declared: {}", arena.winner);
+        Ok(())
+    }
+
+    /// Resolve both markets: the surviving agent and the first agent to die. The
+    /// first remaining account is the first-dead agent's pool PDA (uninitialized if
+    /// unbacked), recording whether the die pot is owed to anyone.
+    pub fn resolve_two_sided(
+        ctx: Context<DeclareWinner>,
+        surviving_agent: String,
+        first_dead_agent: String,
+        sol_usd_price: u64,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let Some(first_dead_pool) = ctx.remaining_accounts.first() else {
+            return err!(ArenaError::InvalidBatch);
+        };
+        let (expected, _) = Pubkey::find_program_address(
+            &[b"pool", arena.key().as_ref(), first_dead_agent.as_bytes()],
+            &crate::ID,
+        );
+        require_keys_eq!(first_dead_pool.key(), expected, ArenaError::InvalidBatch);
+        arena.first_dead_backed =
+            load_pool(first_dead_pool)?.is_some_and(|pool| pool.die_staked > 0);
+        let resolver = ctx.accounts.resolver.key();
+        let backed = winner_backed(&ctx.accounts.winner_pool)?;
+        check_reserves(arena, &ctx.accounts.vault.to_account_info())?;
+        resolve_arena(arena, resolver, backed, now(ctx.remaining_accounts)?)?;
+        record_settlement_price(arena, sol_usd_price)?;
+        arena.winner = surviving_agent;
+        arena.first_dead = first_dead_agent;
+        post_declaration_bond(
+            arena,
+            &ctx.accounts.resolver.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+        )?;
+        if let Some(career) = ctx.accounts.career.as_mut() {
+            credit_winner_career(arena, career, &ctx.accounts.winner_pool)?;
+        }
+
+        msg!(
+            "Two-sided result: {} survived, {} died first",
+            arena.winner,
+            arena.first_dead
+        );
+        Ok(())
+    }
+
+    /// Set when betting closes; 0 keeps betting open until resolution
+    pub fn set_betting_deadline(ctx: Context<SetBettingDeadline>, closes_at: i64) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(closes_at >= 0, ArenaError::InvalidConfig);
+        arena.betting_closes_at = closes_at;
+        arena.extensions_used = 0;
+
+        msg!("Arena {} betting closes at {}", arena.arena_id, closes_at);
+        Ok(())
+    }
+
+    /// Start handing the arena to a new authority, or pass `None` to cancel. The
+    /// handover can only complete after `AUTHORITY_DELAY`, leaving time to react
+    /// to a proposal made with a compromised key.
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        arena.pending_authority = new_authority;
+        arena.transfer_proposed_at = now(ctx.remaining_accounts)?;
+
+        msg!(
+            "Arena {} authority proposal: {:?}",
+            arena.arena_id,
+            new_authority
+        );
+        Ok(())
+    }
+
+    /// Complete a proposed authority transfer once the timelock has elapsed
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let new_authority = ctx.accounts.new_authority.key();
+        require!(
+            arena.pending_authority == Some(new_authority),
+            ArenaError::Unauthorized
+        );
+        require!(
+            now(ctx.remaining_accounts)?
+                >= arena.transfer_proposed_at.saturating_add(AUTHORITY_DELAY),
+            ArenaError::AuthorityTimelocked
+        );
+
+        arena.authority = new_authority;
+        arena.pending_authority = None;
+
+        msg!(
+            "Arena {} authority is now {}",
+            arena.arena_id,
+            new_authority
+        );
+        Ok(())
+    }
+
+    /// Stop accepting bets ahead of resolution, whatever the deadline
+    pub fn close_betting(ctx: Context<CloseBetting>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        arena.transition(ArenaState::BettingClosed)?;
+
+        msg!("Arena {} betting closed", arena.arena_id);
+        Ok(())
+    }
+
+    /// Reopen betting closed by mistake, explicitly or by a passed deadline, with a
+    /// new deadline in the future (0 for none). Never reopens a settled market.
+    pub fn reopen_betting(ctx: Context<CloseBetting>, new_deadline: i64) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.voided(), ArenaError::ArenaVoided);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(
+            new_deadline == 0 || new_deadline > now(ctx.remaining_accounts)?,
+            ArenaError::InvalidConfig
+        );
+
+        if arena.state == ArenaState::BettingClosed {
+            arena.transition(ArenaState::Open)?;
+        }
+        arena.betting_closes_at = new_deadline;
+        arena.extensions_used = 0;
+
+        msg!(
+            "Arena {} betting reopened until {}",
+            arena.arena_id,
+            new_deadline
+        );
+        Ok(())
+    }
+
+    /// Commit to the arena's randomness as the SHA-256 of a secret preimage, fixed
+    /// while betting is still open so the operator cannot pick it after seeing
+    /// how the arena plays out
+    pub fn commit_randomness(ctx: Context<CommitRandomness>, commitment: [u8; 32]) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        check_betting_open(arena, now(ctx.remaining_accounts)?)?;
+        require!(
+            arena.randomness_commitment.is_none(),
+            ArenaError::RandomnessAlreadyCommitted
+        );
+        arena.randomness_commitment = Some(commitment);
+
+        msg!("Arena {} randomness committed", arena.arena_id);
+        Ok(())
+    }
+
+    /// Reveal the committed preimage (callable by anyone who knows it). The
+    /// revealed value seeds deterministic tie-breaks such as `auto_declare_winner`'s.
+    pub fn reveal_randomness(ctx: Context<RevealRandomness>, preimage: [u8; 32]) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(
+            arena.randomness.is_none(),
+            ArenaError::RandomnessAlreadyRevealed
+        );
+        let Some(commitment) = arena.randomness_commitment else {
+            return err!(ArenaError::RandomnessNotCommitted);
+        };
+        require!(
+            hashv(&[&preimage]).to_bytes() == commitment,
+            ArenaError::InvalidPreimage
+        );
+        arena.randomness = Some(preimage);
+
+        msg!("Arena {} randomness revealed", arena.arena_id);
+        Ok(())
+    }
+
+    /// Delegate result reporting to an oracle, or pass `None` to restore the authority
+    pub fn set_oracle(ctx: Context<SetOracle>, oracle: Option<Pubkey>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        arena.oracle = oracle;
+
+        msg!("Arena {} oracle: {:?}", arena.arena_id, oracle);
+        Ok(())
+    }
+
+    /// Toggle one of the arena's `FLAG_*` features (authority only)
+    pub fn set_flag(ctx: Context<SetFlag>, flag: u8, value: bool) -> Result<()> {
+        require!(flag > 0 && flag < FLAG_COUNT, ArenaError::InvalidFlag);
+        let arena = &mut ctx.accounts.arena;
+        arena.set_flag(flag, value);
+
+        msg!("Arena {} flag {} = {}", arena.arena_id, flag, value);
+        Ok(())
+    }
+
+    /// Pause or unpause betting and claims. The guardian may only pause.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let signer = ctx.accounts.signer.key();
+        let is_authority = signer == arena.authority;
+        require!(
+            is_authority || (paused && signer == arena.guardian),
+            ArenaError::Unauthorized
+        );
+
+        if paused {
+            arena.transition(ArenaState::Paused)?;
+        } else {
+            let resume = arena.paused_from;
+            arena.transition(resume)?;
+        }
+
+        msg!("Arena {} paused: {}", arena.arena_id, paused);
+        Ok(())
+    }
+
+    /// Retire an unresolved arena: betting, logging and resolution stop, bettors
+    /// reclaim their principal with `refund_bet`, and all records stay readable
+    pub fn enter_refund_mode(ctx: Context<EnterRefundMode>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        arena.transition(ArenaState::RefundMode)?;
+
+        msg!("Arena {} is now refund-only", arena.arena_id);
+        Ok(())
+    }
+
+    /// Void an unresolved arena for cause. Bettors get their principal back as in
+    /// refund mode, and the operator-defined reason is published with the result.
+    pub fn void_arena(ctx: Context<VoidArena>, void_reason: u8, void_note: String) -> Result<()> {
+        require!(
+            void_note.len() <= MAX_VOID_NOTE_LEN,
+            ArenaError::InvalidVoidNote
+        );
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.resolved(), ArenaError::ArenaResolved);
+        require!(!arena.voided(), ArenaError::ArenaVoided);
+
+        arena.transition(ArenaState::Voided)?;
+        arena.void_reason = void_reason;
+        arena.void_note = void_note;
+
+        emit!(ArenaVoided {
+            arena: arena.key(),
+            void_reason,
+            void_note: arena.void_note.clone(),
+            voided_at: now(ctx.remaining_accounts)?,
+        });
+        msg!("Arena {} voided (reason {})", arena.arena_id, void_reason);
+        Ok(())
+    }
+
+    /// Dispute the declared result. Once `dispute_threshold` bettors have disputed
+    /// within the dispute window, claims are frozen until the authority reviews.
+    pub fn file_dispute(ctx: Context<FileDispute>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.resolved(), ArenaError::ArenaNotResolved);
+        let timestamp = now(ctx.remaining_accounts)?;
+        require!(
+            arena.config.dispute_window > 0
+                && timestamp
+                    < arena
+                        .resolved_at
+                        .saturating_add(arena.config.dispute_window),
+            ArenaError::DisputeWindowClosed
+        );
+
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.arena = arena.key();
+        dispute.bettor = ctx.accounts.bettor.key();
+        dispute.filed_at = timestamp;
+
+        arena.dispute_count += 1;
+        if arena.config.dispute_threshold > 0
+            && arena.dispute_count >= arena.config.dispute_threshold
+        {
+            arena.under_review = true;
+        }
+
+        msg!(
+            "Dispute filed by {} ({} total)",
+            dispute.bettor,
+            arena.dispute_count
+        );
+        Ok(())
+    }
+
+    /// Uphold the result after reviewing disputes, unfreezing claims
+    pub fn dismiss_disputes(ctx: Context<DismissDisputes>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.under_review, ArenaError::NotUnderReview);
+        arena.under_review = false;
+
+        msg!("Arena {} disputes dismissed", arena.arena_id);
+        Ok(())
+    }
+
+    /// Overturn a disputed result: the arena is voided, the resolution fee is
+    /// returned to the pot and the declaration bond is forfeited to refunds
+    pub fn overturn_result(ctx: Context<OverturnResult>, void_note: String) -> Result<()> {
+        require!(
+            void_note.len() <= MAX_VOID_NOTE_LEN,
+            ArenaError::InvalidVoidNote
+        );
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.under_review, ArenaError::NotUnderReview);
+        require!(
+            arena.withdrawable_fees >= arena.resolution_fee,
+            ArenaError::InsufficientReserves
+        );
+
+        arena.transition(ArenaState::Voided)?;
+        arena.under_review = false;
+        arena.withdrawable_fees -= arena.resolution_fee;
+        arena.resolution_fee = 0;
+        arena.forfeited_bond = arena.declaration_bond;
+        arena.declaration_bond = 0;
+        arena.void_note = void_note;
+
+        emit!(ArenaVoided {
+            arena: arena.key(),
+            void_reason: arena.void_reason,
+            void_note: arena.void_note.clone(),
+            voided_at: now(ctx.remaining_accounts)?,
+        });
+        msg!(
+            "Arena {} result overturned, bond of {} forfeited",
+            arena.arena_id,
+            arena.forfeited_bond
+        );
+        Ok(())
+    }
+
+    /// Return the declaration bond once the dispute window has passed without the
+    /// result going under review (callable by anyone)
+    pub fn release_bond(ctx: Context<ReleaseBond>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.declaration_bond > 0, ArenaError::NoBond);
+        require!(!arena.under_review, ArenaError::ResultUnderReview);
+        let timestamp = now(ctx.remaining_accounts)?;
+        require!(
+            timestamp
+                >= arena
+                    .resolved_at
+                    .saturating_add(arena.config.dispute_window),
+            ArenaError::BondLocked
+        );
+
+        let bond = arena.declaration_bond;
+        arena.declaration_bond = 0;
+        pay_out(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.resolver.to_account_info(),
+            bond,
+        )?;
+
+        msg!(
+            "Declaration bond of {} returned to {}",
+            bond,
+            arena.bonded_by
+        );
+        Ok(())
+    }
+
+    /// Withdraw accumulated protocol fees to the authority
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        require!(arena.threshold == 0, ArenaError::MultisigRequired);
+
+        begin_payout(arena)?;
+        let amount = withdraw_all_fees(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            now(ctx.remaining_accounts)?,
+        )?;
+        arena.processing = false;
+
+        msg!("Fees withdrawn: {} lamports", amount);
+        Ok(())
+    }
+
+    /// Withdraw accumulated fees from a token-denominated arena to the authority
+    pub fn withdraw_fees_spl(ctx: Context<WithdrawFeesSpl>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.threshold == 0, ArenaError::MultisigRequired);
+        check_result_final(arena, now(ctx.remaining_accounts)?)?;
+        let amount = arena.withdrawable_fees;
+        require!(amount > 0, ArenaError::NoFeesToWithdraw);
+
+        arena.withdrawable_fees = 0;
+        begin_payout(arena)?;
+        let bump = [ctx.bumps.arena];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"arena", arena.arena_id.as_bytes(), &bump]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    mint: ctx.accounts.bet_mint.to_account_info(),
+                    to: ctx.accounts.authority_token.to_account_info(),
+                    authority: arena.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            ctx.accounts.bet_mint.decimals,
+        )?;
+        arena.processing = false;
+
+        msg!("Token fees withdrawn: {}", amount);
+        Ok(())
+    }
+
+    /// Configure the M-of-N signer set for critical actions; an empty set disables it.
+    /// Once a set is active, changes go through `propose_action` instead.
+    pub fn set_signers(
+        ctx: Context<SetSigners>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.threshold == 0, ArenaError::MultisigRequired);
+        apply_signer_set(arena, signers, threshold)?;
+
+        msg!(
+            "Signer set updated: {}-of-{}",
+            arena.threshold,
+            arena.signers.len()
+        );
+        Ok(())
+    }
+
+    /// Split lamport fees among partners; shares must total 100%, empty restores
+    /// withdrawal to the authority
+    pub fn set_fee_recipients(
+        ctx: Context<SetFeeRecipients>,
+        recipients: Vec<FeeRecipient>,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        // Recipients receive fees without approval, so the signer set must not be bypassed
+        require!(arena.threshold == 0, ArenaError::MultisigRequired);
+        require!(
+            recipients.len() <= MAX_FEE_RECIPIENTS,
+            ArenaError::InvalidFeeSplit
+        );
+        let total_bps: u32 = recipients.iter().map(|r| r.bps as u32).sum();
+        require!(
+            recipients.is_empty() || total_bps == BPS_DENOMINATOR as u32,
+            ArenaError::InvalidFeeSplit
+        );
+
+        arena.fee_recipients = recipients;
+
+        msg!(
+            "Fee split set across {} recipients",
+            arena.fee_recipients.len()
+        );
+        Ok(())
+    }
+
+    /// Pay withdrawable lamport fees out to the fee split. Recipient accounts are
+    /// passed in `remaining_accounts` in configured order; the last one also
+    /// receives rounding dust. Once a signer set is active this goes through
+    /// `propose_action` instead.
+    pub fn distribute_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, DistributeFees<'info>>,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.threshold == 0, ArenaError::MultisigRequired);
+
+        begin_payout(arena)?;
+        let amount = distribute_all_fees(
+            arena,
+            &ctx.accounts.vault.to_account_info(),
+            ctx.remaining_accounts,
+            now(ctx.remaining_accounts)?,
+        )?;
+        arena.processing = false;
+
+        msg!("Fees distributed: {} lamports", amount);
+        Ok(())
+    }
+
+    /// Propose a critical action for the signer set; the proposer's approval counts
+    pub fn propose_action(ctx: Context<ProposeAction>, action: ArenaAction) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let proposer = ctx.accounts.proposer.key();
+        require!(arena.signers.contains(&proposer), ArenaError::Unauthorized);
+
+        let pending = &mut ctx.accounts.pending_action;
+        pending.arena = arena.key();
+        pending.action_id = arena.next_action_id;
+        pending.action = action;
+        pending.approvals = vec![proposer];
+        pending.executed = false;
+        arena.next_action_id += 1;
+
+        msg!("Action {} proposed by {}", pending.action_id, proposer);
+        Ok(())
+    }
+
+    /// Approve a pending action as a member of the signer set
+    pub fn approve_action(ctx: Context<ApproveAction>) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        let pending = &mut ctx.accounts.pending_action;
+        let approver = ctx.accounts.approver.key();
+        require!(arena.signers.contains(&approver), ArenaError::Unauthorized);
+        require!(!pending.executed, ArenaError::ActionAlreadyExecuted);
+        require!(
+            !pending.approvals.contains(&approver),
+            ArenaError::AlreadyApproved
+        );
+        require!(
+            pending.approvals.len() < MAX_SIGNERS,
+            ArenaError::InvalidSignerSet
+        );
+
+        pending.approvals.push(approver);
+
+        msg!(
+            "Action {} approved: {}/{}",
+            pending.action_id,
+            pending.approvals.len(),
+            arena.threshold
+        );
+        Ok(())
+    }
+
+    /// Execute a pending action once it has reached the signer threshold. A fee
+    /// distribution takes its recipients in `remaining_accounts`, as
+    /// `distribute_fees` does.
+    pub fn execute_action<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteAction<'info>>,
+    ) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let pending = &mut ctx.accounts.pending_action;
+        require!(!pending.executed, ArenaError::ActionAlreadyExecuted);
+
+        // Only approvals from the current signer set count
+        let approvals = pending
+            .approvals
+            .iter()
+            .filter(|key| arena.signers.contains(key))
+            .count();
+        require!(
+            arena.threshold > 0 && approvals >= arena.threshold as usize,
+            ArenaError::NotEnoughApprovals
+        );
+
+        match pending.action.clone() {
+            ArenaAction::WithdrawFees { recipient } => {
+                require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+                let destination = &ctx.accounts.destination;
+                require_keys_eq!(destination.key(), recipient, ArenaError::Unauthorized);
+                let amount = withdraw_all_fees(
+                    arena,
+                    &ctx.accounts.vault.to_account_info(),
+                    destination,
+                    now(ctx.remaining_accounts)?,
+                )?;
+                msg!("Fees withdrawn by signer set: {} lamports", amount);
+            }
+            ArenaAction::DistributeFees => {
+                let amount = distribute_all_fees(
+                    arena,
+                    &ctx.accounts.vault.to_account_info(),
+                    ctx.remaining_accounts,
+                    now(ctx.remaining_accounts)?,
+                )?;
+                msg!("Fees distributed by signer set: {} lamports", amount);
+            }
+            ArenaAction::SetSigners { signers, threshold } => {
+                apply_signer_set(arena, signers, threshold)?;
+                msg!(
+                    "Signer set replaced by signer set: {}-of-{}",
+                    arena.threshold,
+                    arena.signers.len()
+                );
+            }
+        }
+        pending.executed = true;
+
+        Ok(())
+    }
+
+    /// Switch practice mode, letting frontends demo the full betting flow without
+    /// real funds. Only SOL arenas with nothing at stake may switch: no bets in any
+    /// state, no seed and no accrued fees.
+    pub fn set_practice(ctx: Context<SetPractice>, practice: bool) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(arena.total_bets == 0, ArenaError::BetsAlreadyPlaced);
+        require!(
+            arena.seeded_amount == 0
+                && arena.unconfirmed_escrow == 0
+                && arena.withdrawable_fees == 0
+                && arena.declaration_bond == 0,
+            ArenaError::ArenaHoldsFunds
+        );
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        arena.practice = practice;
+
+        msg!("Arena {} practice mode: {}", arena.arena_id, practice);
+        Ok(())
+    }
+
+    /// Denominate bets in an SPL mint, recording its decimals for display
+    pub fn set_bet_mint(ctx: Context<SetBetMint>, decimals: u8) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let mint = &ctx.accounts.bet_mint;
+        require!(arena.total_bets == 0, ArenaError::BetsAlreadyPlaced);
+        // Lamports already held would be owed in a currency the arena no longer pays
+        require!(
+            arena.seeded_amount == 0
+                && arena.unconfirmed_escrow == 0
+                && arena.withdrawable_fees == 0,
+            ArenaError::ArenaHoldsFunds
+        );
+        require!(!arena.practice, ArenaError::WrongBetCurrency);
+        require!(decimals == mint.decimals, ArenaError::MintDecimalsMismatch);
+
+        arena.bet_mint = mint.key();
+        arena.bet_mint_decimals = decimals;
+
+        msg!("Bet mint set: {} ({} decimals)", arena.bet_mint, decimals);
+        Ok(())
+    }
+
+    /// Emit the resolved arena's full result as a single `ArenaResult` event
+    pub fn emit_final_result(ctx: Context<EmitFinalResult>) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        require!(arena.resolved(), ArenaError::ArenaNotResolved);
+
+        emit!(arena_result(arena));
+        Ok(())
+    }
+
+    /// Roll the unowed remainder of a resolved arena's vault (e.g. an unbacked
+    /// market's pot) into the next arena's jackpot once the claim period has
+    /// passed: the longest of the dispute window, the keeper grace and
+    /// `MIN_CLAIM_PERIOD`. Fees, any declaration bond, winnings not yet claimed
+    /// and unconfirmed escrow stay behind, so winners can still claim.
+    pub fn rollover(ctx: Context<Rollover>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let next_arena = &mut ctx.accounts.next_arena;
+        require!(
+            !arena.is_spl() && !next_arena.is_spl(),
+            ArenaError::WrongBetCurrency
+        );
+        require!(arena.resolved(), ArenaError::ArenaNotResolved);
+        // Refunds return principal, which is never the operator's to move
+        require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+        require!(!arena.under_review, ArenaError::ResultUnderReview);
+        require!(
+            !next_arena.resolved() && !next_arena.refund_mode(),
+            ArenaError::ArenaResolved
+        );
+        // A practice arena never pays out, so real lamports would be stranded
+        require!(!next_arena.practice, ArenaError::InvalidRollover);
+        check_claim_period_over(arena, now(ctx.remaining_accounts)?)?;
+
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let rent = Rent::get()?.minimum_balance(vault_info.data_len());
+        let amount = rollover_amount(arena, vault_info.lamports(), rent);
+        require!(amount > 0, ArenaError::NothingToRollOver);
+
+        pay_from_vault(
+            &vault_info,
+            &ctx.accounts.next_vault.to_account_info(),
+            amount,
+        )?;
+        next_arena.seeded_amount += amount;
+        next_arena.rolled_in_amount += amount;
+
+        msg!(
+            "Arena {} rolled {} lamports into {}",
+            arena.arena_id,
+            amount,
+            next_arena.arena_id
+        );
+        Ok(())
+    }
+
+    /// Close a fully settled SOL arena and its vault, returning their rent to the
+    /// authority and adding the arena's final totals to the protocol-wide stats.
+    /// Whatever nobody is owed (the seed and agent entry fees of an arena that
+    /// refunded, rounding dust) is swept to the authority with the rent.
+    pub fn close_arena(ctx: Context<CloseArena>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+        arena.transition(ArenaState::Closed)?;
+        // Every payout, refund and fee must be out of the vault first
+        require!(is_settled(arena), ArenaError::ArenaNotSettled);
+
+        let stats = &mut ctx.accounts.protocol_stats;
+        stats.arenas_closed += 1;
+        stats.total_volume += arena.total_volume;
+        stats.total_bet_volume += arena.total_bet_volume;
+        stats.unique_bettors += arena.unique_bettors;
+
+        msg!(
+            "Arena {} closed ({} arenas closed protocol-wide)",
+            arena.arena_id,
+            stats.arenas_closed
+        );
+        Ok(())
+    }
+
+    /// Set the injected timestamp read by `now` (test builds only)
+    #[cfg(feature = "test-clock")]
+    pub fn set_test_clock(ctx: Context<SetTestClock>, unix_timestamp: i64) -> Result<()> {
+        ctx.accounts.test_clock.unix_timestamp = unix_timestamp;
+
+        msg!("Test clock set: {}", unix_timestamp);
+        Ok(())
+    }
+}
+
+// Helpers
+
+/// Current unix timestamp for an instruction.
+///
+/// Production builds always read the `Clock` sysvar. With the `test-clock`
+/// feature (and in unit tests), a `TestClock` account passed in
+/// `remaining_accounts` overrides it so deadline logic can be exercised
+/// deterministically.
+fn now(remaining_accounts: &[AccountInfo]) -> Result<i64> {
+    #[cfg(any(test, feature = "test-clock"))]
+    for info in remaining_accounts {
+        if info.owner != &crate::ID {
+            continue;
+        }
+        let data = info.try_borrow_data()?;
+        if let Ok(clock) = TestClock::try_deserialize(&mut &data[..]) {
+            return Ok(clock.unix_timestamp);
+        }
+    }
+    #[cfg(not(any(test, feature = "test-clock")))]
+    let _ = remaining_accounts;
+
+    Ok(Clock::get()?.unix_timestamp)
+}
+
+/// Arena health on a 0-100 scale, weighted as:
+/// - liveness (40): share of agents still alive
+/// - activity (30): transactions since the last health computation, saturating
+///   at `HEALTH_ACTIVITY_TARGET`
+/// - engagement (30): unique bettors, saturating at `HEALTH_ENGAGEMENT_TARGET`
+fn health_score(
+    alive_agents: u32,
+    total_agents: u32,
+    recent_transactions: u64,
+    unique_bettors: u64,
+) -> u8 {
+    let liveness = (alive_agents as u64 * 40)
+        .checked_div(total_agents as u64)
+        .unwrap_or(0)
+        .min(40);
+    let activity = recent_transactions.min(HEALTH_ACTIVITY_TARGET) * 30 / HEALTH_ACTIVITY_TARGET;
+    let engagement = unique_bettors.min(HEALTH_ENGAGEMENT_TARGET) * 30 / HEALTH_ENGAGEMENT_TARGET;
+    (liveness + activity + engagement) as u8
+}
+
+/// Gini coefficient scaled to 0-100 for sorted balances; all-zero balances yield 0
+fn gini_coefficient(sorted: &[u64]) -> u16 {
+    let n = sorted.len() as i128;
+    let sum: i128 = sorted.iter().map(|&b| b as i128).sum();
+    if n == 0 || sum == 0 {
+        return 0;
+    }
+    // G = sum((2i - n - 1) * x_i) / (n * sum), with i 1-based over ascending balances
+    let weighted: i128 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| (2 * (i as i128 + 1) - n - 1) * b as i128)
+        .sum();
+    (weighted * 100 / (n * sum)) as u16
+}
+
+/// Reject empty, over-long or non `[A-Za-z0-9_-]` ids before they seed a PDA
+fn validate_identifier(id: &str) -> Result<()> {
+    require!(
+        !id.is_empty()
+            && id.len() <= MAX_ID_LEN
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'),
+        ArenaError::InvalidIdentifier
+    );
+    Ok(())
+}
+
+/// Checks shared by every new-bet instruction
+fn check_new_bet(
+    arena: &Arena,
+    agent_id: &str,
+    agent_registered: bool,
+    pool: &AgentPool,
+    profile: &UserProfile,
+    amount: u64,
+    timestamp: i64,
+) -> Result<()> {
+    require!(!arena.is_paused(), ArenaError::ArenaPaused);
+    require!(pool.frozen_at == 0, ArenaError::PoolFrozen);
+    require!(!arena.resolved(), ArenaError::ArenaResolved);
+    require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+    require!(
+        !arena.flag(FLAG_REQUIRE_REGISTERED_AGENTS) || agent_registered,
+        ArenaError::UnknownAgent
+    );
+    check_roster(arena, agent_id)?;
+    check_betting_open(arena, timestamp)?;
+    check_bet_amount(&arena.config, amount)?;
+    check_bet_ratio(arena, amount)?;
+    check_bet_cooldown(&arena.config, profile, timestamp)
+}
+
+/// Keep one bet from dominating the market. Seeding the pool is how an operator
+/// makes room for the first bets under a tight cap.
+fn check_bet_ratio(arena: &Arena, amount: u64) -> Result<()> {
+    let max_ratio_bps = arena.config.max_bet_ratio_bps;
+    if max_ratio_bps == 0 {
+        return Ok(());
+    }
+    let pool_after = arena.total_bet_volume + arena.seeded_amount + amount;
+    require!(
+        amount <= bps_of(pool_after, max_ratio_bps),
+        ArenaError::BetTooLargeForPool
+    );
+    Ok(())
+}
+
+/// Reject a wallet whose self-exclusion has not yet expired
+fn check_not_self_excluded(exclusion: &AccountInfo, timestamp: i64) -> Result<()> {
+    if exclusion.data_is_empty() {
+        return Ok(());
+    }
+    let exclusion = SelfExclusion::try_deserialize(&mut &exclusion.try_borrow_data()?[..])?;
+    require!(timestamp >= exclusion.until, ArenaError::SelfExcluded);
+    Ok(())
+}
+
+/// In roster-only arenas, reject agents missing from the official roster
+fn check_roster(arena: &Arena, agent_id: &str) -> Result<()> {
+    require!(
+        !arena.flag(FLAG_ROSTER_ONLY) || arena.roster.iter().any(|id| id == agent_id),
+        ArenaError::NotOnRoster
+    );
+    Ok(())
+}
+
+/// Book a freshly filled-in bet into its pool, the bettor's profile and the arena
+fn record_bet(
+    arena: &mut Account<Arena>,
+    pool: &mut AgentPool,
+    profile: &mut UserProfile,
+    bet: &Bet,
+) {
+    if pool.arena == Pubkey::default() {
+        pool.arena = arena.key();
+        pool.agent_id = bet.agent_id.clone();
+    }
+    if pool.first_bet_at == 0 {
+        pool.first_bet_at = bet.timestamp;
+    }
+    pool.bet_count += 1;
+    pool.add_stake(bet.bet_side, bet.amount);
+
+    if profile.arena == Pubkey::default() {
+        profile.arena = arena.key();
+        profile.bettor = bet.bettor;
+        arena.unique_bettors += 1;
+    }
+    profile.bet_count += 1;
+    profile.total_staked += bet.amount;
+    profile.last_bet_at = bet.timestamp;
+
+    arena.total_bets += 1;
+    arena.add_bet_volume(bet.bet_side, bet.amount);
+    // Entry fees are only earned once a bet is live
+    arena.withdrawable_fees += bet.gross_amount - bet.amount;
+    extend_for_late_bet(arena, bet.timestamp);
+}
+
+/// Add an already-escrowed `amount` to a live bet, booking its entry fee
+/// Open a bet with `amount` already escrowed, or top up the bettor's existing
+/// position on the same agent. Large new bets wait for `confirm_bet`.
+#[allow(clippy::too_many_arguments)]
+fn open_or_add_to_bet(
+    arena: &mut Account<Arena>,
+    pool: &mut AgentPool,
+    profile: &mut UserProfile,
+    bet: &mut Bet,
+    bettor: Pubkey,
+    agent_id: String,
+    amount: u64,
+    bet_side: BetSide,
+    predicted_placement: u32,
+    timestamp: i64,
+) -> Result<()> {
+    // A repeat bet on the same agent tops up the existing position
+    if bet.arena != Pubkey::default() {
+        require!(
+            bet.arena == arena.key() && bet.bet_side == bet_side,
+            ArenaError::BetMismatch
+        );
+        // A sold bet keeps the seller-seeded address but belongs to its buyer
+        require_keys_eq!(bet.bettor, bettor, ArenaError::BetMismatch);
+        require!(bet.confirmed, ArenaError::BetNotConfirmed);
+        return add_to_bet(arena, pool, profile, bet, amount, timestamp);
+    }
+
+    bet.bettor = bettor;
+    bet.agent_id = agent_id;
+    bet.gross_amount = amount;
+    bet.amount = arena.config.stake_after_entry_fee(amount)?;
+    bet.timestamp = timestamp;
+    bet.arena = arena.key();
+    bet.claimed = false;
+    bet.bet_side = bet_side;
+    bet.predicted_placement = predicted_placement;
+    // Large bets stay escrowed outside the pool until the bettor confirms them
+    bet.confirmed = !arena.config.needs_confirmation(amount);
+    if bet.confirmed {
+        record_bet(arena, pool, profile, bet);
+    } else {
+        arena.unconfirmed_escrow += amount;
+    }
+
+    arena_msg!(
+        arena,
+        "Bet placed: {} on agent {} ({:?}) for {}",
+        bet.bettor,
+        bet.agent_id,
+        bet_side,
+        amount
+    );
+    Ok(())
+}
+
+/// Release a bet whose confirmation window lapsed without `confirm_bet`,
+/// returning the escrow owed back to its bettor
+fn settle_unconfirmed(arena: &mut Arena, bet: &Bet, timestamp: i64) -> Result<u64> {
+    require!(!bet.confirmed, ArenaError::BetAlreadyConfirmed);
+    // A refund-mode refund already returned the escrow
+    require!(!bet.claimed, ArenaError::AlreadyClaimed);
+    require!(
+        timestamp.saturating_sub(bet.timestamp) >= arena.config.confirm_timeout,
+        ArenaError::ConfirmationWindowOpen
+    );
+
+    arena.unconfirmed_escrow -= bet.gross_amount;
+    Ok(bet.gross_amount)
+}
+
+fn add_to_bet(
+    arena: &mut Account<Arena>,
+    pool: &mut AgentPool,
+    profile: &mut UserProfile,
+    bet: &mut Bet,
+    amount: u64,
+    timestamp: i64,
+) -> Result<()> {
+    let staked = arena.config.stake_after_entry_fee(amount)?;
+    arena.withdrawable_fees += amount - staked;
+    bet.gross_amount += amount;
+    bet.amount += staked;
+    pool.add_stake(bet.bet_side, staked);
+    profile.total_staked += staked;
+    profile.last_bet_at = timestamp;
+    arena.add_bet_volume(bet.bet_side, staked);
+    extend_for_late_bet(arena, timestamp);
+
+    emit!(bet_increased(arena.key(), bet, pool, staked));
+    arena_msg!(
+        arena,
+        "Bet increased: {} on agent {} now {} lamports",
+        bet.bettor,
+        bet.agent_id,
+        bet.amount
+    );
+    Ok(())
+}
+
+/// Reject bets once the betting deadline has passed; a deadline of 0 means none
+fn check_betting_open(arena: &Arena, timestamp: i64) -> Result<()> {
+    require!(
+        arena.stage() != ArenaState::BettingClosed,
+        ArenaError::BettingClosed
+    );
+    require!(
+        arena.betting_closes_at == 0 || timestamp < arena.betting_closes_at,
+        ArenaError::BettingClosed
+    );
+    Ok(())
+}
+
+/// Anti-sniping: a bet inside the closing window pushes the deadline out, up to
+/// `max_extensions` times
+fn extend_for_late_bet(arena: &mut Arena, timestamp: i64) {
+    let config = &arena.config;
+    if arena.betting_closes_at == 0
+        || config.snipe_extension == 0
+        || arena.extensions_used >= config.max_extensions
+        || timestamp < arena.betting_closes_at - config.snipe_window
+    {
+        return;
+    }
+    arena.betting_closes_at += config.snipe_extension;
+    arena.extensions_used += 1;
+}
+
+/// Validate a bet (or bet increase) amount against the arena's betting rules
+fn check_bet_amount(config: &ArenaConfig, amount: u64) -> Result<()> {
+    let granularity = config.lamport_granularity;
+    require!(
+        granularity == 0 || amount.is_multiple_of(granularity),
+        ArenaError::InvalidBetGranularity
+    );
+    Ok(())
+}
+
+/// Throttle repeat bets from the same wallet; a cooldown of 0 disables it
+fn check_bet_cooldown(config: &ArenaConfig, profile: &UserProfile, timestamp: i64) -> Result<()> {
+    require!(
+        profile.bet_count == 0
+            || timestamp.saturating_sub(profile.last_bet_at) >= config.bet_cooldown,
+        ArenaError::BetCooldown
+    );
+    Ok(())
+}
+
+/// Why a bet can or cannot be claimed, reported by `preview_claim`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClaimReason {
+    Eligible = 0,
+    NotResolved = 1,
+    AlreadyClaimed = 2,
+    LosingBet = 3,
+    Unconfirmed = 4,
+    Voided = 5,
+}
+
+fn claim_status(arena: &Arena, bet: &Bet) -> ClaimReason {
+    if arena.voided() {
+        ClaimReason::Voided
+    } else if !arena.resolved() {
+        ClaimReason::NotResolved
+    } else if bet.claimed {
+        ClaimReason::AlreadyClaimed
+    } else if !bet.confirmed {
+        ClaimReason::Unconfirmed
+    } else if !arena.is_winning_bet(bet) {
+        ClaimReason::LosingBet
+    } else {
+        ClaimReason::Eligible
+    }
+}
+
+/// Check a claim, mark the bet claimed and return its payout
+fn settle_claim(
+    arena: &mut Arena,
+    pool: &mut AgentPool,
+    bet: &mut Bet,
+    timestamp: i64,
+) -> Result<u64> {
+    require!(!arena.is_paused(), ArenaError::ArenaPaused);
+    check_result_final(arena, timestamp)?;
+    require!(arena.payout_root.is_none(), ArenaError::ProofClaimRequired);
+    require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+    match claim_status(arena, bet) {
+        ClaimReason::Eligible => {}
+        ClaimReason::NotResolved => return err!(ArenaError::ArenaNotResolved),
+        ClaimReason::AlreadyClaimed => return err!(ArenaError::AlreadyClaimed),
+        ClaimReason::LosingBet => return err!(ArenaError::NotWinningBet),
+        ClaimReason::Unconfirmed => return err!(ArenaError::BetNotConfirmed),
+        ClaimReason::Voided => return err!(ArenaError::ArenaVoided),
+    }
+
+    let payout = compute_payout(arena, pool, bet)?;
+    bet.claimed = true;
+    pool.claimed += payout;
+    arena.total_claimed += payout;
+    Ok(payout)
+}
+
+/// Leaf committed to by a payout root, one per winning bet; the arena key stops
+/// cross-arena replay
+fn payout_leaf(arena: &Pubkey, bet: &Pubkey, bettor: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[
+        arena.as_ref(),
+        bet.as_ref(),
+        bettor.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Verify a Merkle proof built with sorted-pair SHA-256 hashing
+fn verify_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        let (left, right) = if node <= *sibling {
+            (node, *sibling)
+        } else {
+            (*sibling, node)
+        };
+        hashv(&[&left, &right]).to_bytes()
+    });
+    computed == root
+}
+
+/// Check a refund-mode claim, mark the bet settled and return its principal
+fn settle_refund(arena: &mut Arena, bet: &mut Bet) -> Result<u64> {
+    require!(arena.refund_mode(), ArenaError::NotInRefundMode);
+    require!(!arena.is_paused(), ArenaError::ArenaPaused);
+    require!(!bet.claimed, ArenaError::AlreadyClaimed);
+    bet.claimed = true;
+    // A live bet's entry fee was earned; an unconfirmed bet never paid one
+    if !bet.confirmed {
+        arena.unconfirmed_escrow -= bet.gross_amount;
+        return Ok(bet.gross_amount);
+    }
+    // Live stakes share an overturned resolver's bond in proportion to their size
+    let bond_share = (bet.amount as u128 * arena.forfeited_bond as u128)
+        .checked_div(arena.total_bet_volume as u128)
+        .unwrap_or(0) as u64;
+    arena.total_refunded += bet.amount + bond_share;
+    Ok(bet.amount + bond_share)
+}
+
+/// Bonus for a correct `predicted_placement`: the winner places first (podium
+/// agents take their podium place), other agents the placement recorded at
+/// death. It is paid from withdrawable fees, capped at what is available, so it
+/// never dilutes the pool.
+fn placement_bonus(arena: &Arena, bet: &Bet, death: Option<&AgentDeath>, payout: u64) -> u64 {
+    if bet.predicted_placement == 0 {
+        return 0;
+    }
+    let placement = if let Some(place) = arena.podium_place(&bet.agent_id) {
+        Some(place as u32 + 1)
+    } else if bet.agent_id == arena.winner {
+        Some(1)
+    } else {
+        death.map(|death| death.placement)
+    };
+    if placement != Some(bet.predicted_placement) {
+        return 0;
+    }
+    bps_of(payout, arena.config.placement_bonus_bps).min(arena.withdrawable_fees)
+}
+
+/// Bonus for backing an agent that completed more than `productive_services`
+/// services, counted at death or, for a survivor, at claim time. Like the
+/// placement bonus it is paid from withdrawable fees, capped at what is left.
+fn productivity_bonus(
+    arena: &Arena,
+    death: Option<&AgentDeath>,
+    agent: Option<&Agent>,
+    payout: u64,
+) -> u64 {
+    let services = death
+        .map(|death| death.services_completed)
+        .or(agent.map(|agent| agent.services_completed));
+    if services.is_none_or(|services| services <= arena.config.productive_services) {
+        return 0;
+    }
+    bps_of(payout, arena.config.productivity_bonus_bps).min(arena.withdrawable_fees)
+}
+
+/// Parimutuel payout: the bet's share of its side's winning stake applied to that
+/// side's pot after fees, plus any operator seed for the survive market. With a
+/// podium, the survive pot is first sliced by the agent's place.
+fn compute_payout(arena: &Arena, pool: &AgentPool, bet: &Bet) -> Result<u64> {
+    let distributable = agent_pot(arena, bet.bet_side, &bet.agent_id);
+    // The product of two u64s always fits in u128; only the quotient is narrowed
+    let payout = (bet.amount as u128 * distributable as u128)
+        .checked_div(pool.side_staked(bet.bet_side) as u128)
+        .ok_or_else(|| error!(ArenaError::MathOverflow))?;
+    u64::try_from(payout).map_err(|_| error!(ArenaError::PayoutOverflow))
+}
+
+/// Winnings still owed to a pool's backers; zero unless the arena resolved to a payout
+fn unclaimed_winnings(arena: &Arena, pool: &AgentPool) -> u64 {
+    if !arena.resolved() || arena.refund_mode() {
+        return 0;
+    }
+    let owed: u64 = [BetSide::Survive, BetSide::Die]
+        .into_iter()
+        .filter(|&side| arena.wins(side, &pool.agent_id) && pool.side_staked(side) > 0)
+        .map(|side| agent_pot(arena, side, &pool.agent_id))
+        .sum();
+    owed.saturating_sub(pool.claimed)
+}
+
+/// What an agent's winning backers on `side` share: the side's pot after fees,
+/// plus the seed for the survive market, sliced by place on a podium
+fn agent_pot(arena: &Arena, side: BetSide, agent_id: &str) -> u64 {
+    let side_volume = arena.side_volume(side);
+    let mut distributable = side_volume - bps_of(side_volume, arena.config.fee_bps);
+    if side == BetSide::Survive {
+        distributable += arena.seeded_amount;
+        // On a podium each place's pool shares only that place's slice of the pot
+        if let Some(place) = arena.podium_place(agent_id) {
+            let (share, backed) = arena.podium_share(place);
+            let slice = distributable as u128 * share as u128 / backed as u128;
+            distributable = slice as u64;
+        }
+    }
+    distributable
+}
+
+/// What a settled arena still owes bettors. Refunds owe every live stake plus a
+/// forfeited bond, less what was refunded. A resolved arena owes every winning
+/// pot less what has been claimed: the survive pot is always backed once
+/// resolved, the die pot only if someone backed the declared first death.
+fn outstanding_liabilities(arena: &Arena) -> u64 {
+    if arena.refund_mode() {
+        return (arena.total_bet_volume + arena.forfeited_bond)
+            .saturating_sub(arena.total_refunded);
+    }
+    if !arena.resolved() {
+        return 0;
+    }
+    let mut owed = agent_pot(arena, BetSide::Survive, "");
+    if arena.first_dead_backed {
+        owed += agent_pot(arena, BetSide::Die, &arena.first_dead);
+    }
+    owed.saturating_sub(arena.total_claimed)
+}
+
+/// Whether every claim, refund, fee and bond is out of the vault. Payouts round
+/// down, so up to a lamport per bet (and per podium slice) may stay owed forever.
+fn is_settled(arena: &Arena) -> bool {
+    let dust = arena.total_bets + arena.podium.len() as u64;
+    arena.withdrawable_fees == 0
+        && arena.declaration_bond == 0
+        && arena.unconfirmed_escrow == 0
+        && outstanding_liabilities(arena) <= dust
+}
+
+/// Unclaimed lamports may be rolled over once the longest of the dispute window,
+/// the keeper grace and `MIN_CLAIM_PERIOD` has passed since resolution
+fn check_claim_period_over(arena: &Arena, timestamp: i64) -> Result<()> {
+    let claim_period = arena
+        .config
+        .dispute_window
+        .max(arena.config.keeper_grace)
+        .max(MIN_CLAIM_PERIOD);
+    require!(
+        timestamp >= arena.resolved_at.saturating_add(claim_period),
+        ArenaError::ClaimPeriodOpen
+    );
+    Ok(())
+}
+
+/// What `rollover` may move out of a vault holding `lamports`: everything above
+/// its rent that is not owed as fees, bond, winnings or unconfirmed escrow
+fn rollover_amount(arena: &Arena, lamports: u64, rent: u64) -> u64 {
+    lamports
+        .saturating_sub(rent)
+        .saturating_sub(arena.withdrawable_fees)
+        .saturating_sub(arena.declaration_bond)
+        .saturating_sub(outstanding_liabilities(arena))
+        .saturating_sub(arena.unconfirmed_escrow)
+}
+
+/// Mark the arena resolved, enforcing who may resolve, the minimum duration and
+/// booking the fee. A configured oracle replaces the authority as resolver. If
+/// nobody backed the winner, or the pool is below `min_pool_to_payout`, the pot
+/// is not distributed: the arena falls back to refund mode and no fee is taken.
+fn resolve_arena(
+    arena: &mut Arena,
+    resolver: Pubkey,
+    winner_backed: bool,
+    timestamp: i64,
+) -> Result<()> {
+    let expected = arena.oracle.unwrap_or(arena.authority);
+    require_keys_eq!(resolver, expected, ArenaError::Unauthorized);
+    require!(!arena.resolved(), ArenaError::ArenaResolved);
+    require!(!arena.refund_mode(), ArenaError::ArenaRefundOnly);
+    require!(
+        timestamp >= arena.started_at.saturating_add(arena.config.min_duration),
+        ArenaError::ArenaTooYoung
+    );
+
+    arena.resolved_at = timestamp;
+    let pool_too_small = arena.total_bet_volume < arena.config.min_pool_to_payout;
+    if !winner_backed || pool_too_small {
+        arena.winner_had_no_backers = !winner_backed;
+        arena.pool_below_minimum = pool_too_small;
+        return arena.transition(ArenaState::RefundMode);
+    }
+    arena.transition(ArenaState::Resolved)?;
+
+    let fee_bps = arena.config.fee_bps;
+    let fee = bps_of(arena.side_volume(BetSide::Survive), fee_bps)
+        + bps_of(arena.side_volume(BetSide::Die), fee_bps);
+    arena.resolution_fee = fee;
+    arena.withdrawable_fees += fee;
+    Ok(())
+}
+
+/// Credit the declared winner's career with the win and the survive stake backing
+/// it, and record it as the career `claim_winnings` credits with payouts
+fn credit_winner_career(
+    arena: &mut Arena,
+    career: &mut Account<AgentCareer>,
+    winner_pool: &AccountInfo,
+) -> Result<()> {
+    career.wins += 1;
+    if let Some(pool) = load_pool(winner_pool)? {
+        career.total_backed += pool.side_staked(BetSide::Survive);
+    }
+    arena.winner_career = Some(career.key());
+    Ok(())
+}
+
+/// Escrow the configured declaration bond from the resolver. It is returned by
+/// `release_bond`, or forfeited to refunds if the result is overturned.
+fn post_declaration_bond<'info>(
+    arena: &mut Arena,
+    resolver: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+) -> Result<()> {
+    let bond = arena.config.declaration_bond;
+    if bond == 0 {
+        return Ok(());
+    }
+    escrow_in(arena, resolver, vault, bond)?;
+    arena.declaration_bond = bond;
+    arena.bonded_by = resolver.key();
+    Ok(())
+}
+
+/// A resolved result can still be disputed and overturned until its dispute
+/// window closes, so claims and fee withdrawals wait for it to become final
+fn check_result_final(arena: &Arena, timestamp: i64) -> Result<()> {
+    require!(!arena.under_review, ArenaError::ResultUnderReview);
+    if arena.resolved() {
+        require!(
+            timestamp
+                >= arena
+                    .resolved_at
+                    .saturating_add(arena.config.dispute_window),
+            ArenaError::DisputeWindowOpen
+        );
+    }
+    Ok(())
+}
+
+/// Refuse to resolve a SOL arena whose vault cannot cover what it owes: every
+/// live stake, the operator seed and uncollected fees. A shortfall means an
+/// accounting bug or a drained vault, and paying out would short late claimants.
+fn check_reserves(arena: &Arena, vault: &AccountInfo) -> Result<()> {
+    if arena.is_spl() || arena.practice {
+        return Ok(());
+    }
+    let rent = Rent::get()?.minimum_balance(vault.data_len());
+    let reserves = vault.lamports().saturating_sub(rent);
+    let owed = arena.total_bet_volume
+        + arena.seeded_amount
+        + arena.withdrawable_fees
+        + arena.declaration_bond;
+    require!(reserves >= owed, ArenaError::InsufficientReserves);
+    Ok(())
+}
+
+/// Store the SOL/USD rate reported at resolution, for dual-denominated results.
+/// Reporting only: no funds are converted. A price of 0 records no rate.
+fn record_settlement_price(arena: &mut Arena, sol_usd_price: u64) -> Result<()> {
+    require!(
+        sol_usd_price == 0 || !arena.is_spl(),
+        ArenaError::WrongBetCurrency
+    );
+    arena.sol_usd_price = sol_usd_price;
+    Ok(())
+}
+
+/// The final snapshot `emit_final_result` publishes for a resolved arena
+fn arena_result(arena: &Account<Arena>) -> ArenaResult {
+    ArenaResult {
+        arena: arena.key(),
+        winner: arena.winner.clone(),
+        first_dead: arena.first_dead.clone(),
+        total_bet_volume: arena.total_bet_volume,
+        survive_pool: arena.side_volume(BetSide::Survive),
+        die_pool: arena.side_volume(BetSide::Die),
+        fee_taken: arena.resolution_fee,
+        total_bets: arena.total_bets,
+        unique_bettors: arena.unique_bettors,
+        gini_coefficient: arena.gini_coefficient,
+        resolved_at: arena.resolved_at,
+        sol_usd_price: arena.sol_usd_price,
+        total_bet_volume_usd: lamports_to_usd(arena.total_bet_volume, arena.sol_usd_price),
+        fee_taken_usd: lamports_to_usd(arena.resolution_fee, arena.sol_usd_price),
+    }
+}
+
+/// The `VolumeUpdated` for `transaction`, with `arena`'s totals once it is counted
+fn volume_updated(key: Pubkey, arena: &Arena, transaction: &Transaction) -> VolumeUpdated {
+    VolumeUpdated {
+        arena: key,
+        transaction_id: transaction.transaction_id.clone(),
+        amount: transaction.amount,
+        total_transactions: arena.total_transactions,
+        total_volume: arena.total_volume,
+    }
+}
+
+/// The `BetIncreased` a top-up of `delta` staked lamports publishes
+fn bet_increased(arena: Pubkey, bet: &Bet, pool: &AgentPool, delta: u64) -> BetIncreased {
+    BetIncreased {
+        arena,
+        bettor: bet.bettor,
+        agent_id: bet.agent_id.clone(),
+        delta,
+        new_amount: bet.amount,
+        pool_total: pool.total_staked,
+    }
+}
+
+/// The `BetCancelled` a cancellation refunding `refunded` lamports publishes
+fn bet_cancelled(arena: Pubkey, bet: &Bet, pool: &AgentPool, refunded: u64) -> BetCancelled {
+    BetCancelled {
+        arena,
+        bettor: bet.bettor,
+        agent_id: bet.agent_id.clone(),
+        refunded,
+        pool_total: pool.total_staked,
+    }
+}
+
+/// The `Checkpoint` of `arena`'s counters as of its latest `checkpoint_id`
+fn arena_checkpoint(key: Pubkey, arena: &Arena, block_height: u64, timestamp: i64) -> Checkpoint {
+    Checkpoint {
+        arena: key,
+        checkpoint_id: arena.checkpoint_id,
+        schema_version: ARENA_SCHEMA_VERSION,
+        block_height,
+        total_transactions: arena.total_transactions,
+        total_volume: arena.total_volume,
+        alive_agents: arena.alive_agents,
+        dead_agents: arena.dead_agents,
+        total_bets: arena.total_bets,
+        total_bet_volume: arena.total_bet_volume,
+        timestamp,
+    }
+}
+
+/// Value lamports in micro-USD at a price quoted in micro-USD per SOL
+fn lamports_to_usd(lamports: u64, sol_usd_price: u64) -> u64 {
+    let usd = lamports as u128 * sol_usd_price as u128 / LAMPORTS_PER_SOL as u128;
+    u64::try_from(usd).unwrap_or(u64::MAX)
+}
+
+/// Notional fee rate for a service type: its `ServiceFeeConfig` if one exists,
+/// otherwise the arena-wide `tx_fee_bps`
+fn service_fee_bps(service_fee: &AccountInfo, default_bps: u16) -> Result<u16> {
+    if service_fee.data_is_empty() {
+        return Ok(default_bps);
+    }
+    let config = ServiceFeeConfig::try_deserialize(&mut &service_fee.try_borrow_data()?[..])?;
+    Ok(config.fee_bps)
+}
+
+fn load_bet(info: &AccountInfo) -> Result<Option<Bet>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    let bet = Bet::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    Ok(Some(bet))
+}
+
+/// Read an agent's pool through an unchecked PDA that may not have been created
+fn load_pool(info: &AccountInfo) -> Result<Option<AgentPool>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    let pool = AgentPool::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    Ok(Some(pool))
+}
+
+/// Read a death record through an unchecked PDA that may not have been created
+fn load_death(info: &AccountInfo) -> Result<Option<AgentDeath>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    let death = AgentDeath::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    Ok(Some(death))
+}
+
+/// Read an agent record through an unchecked PDA that may not have been created
+fn load_agent(info: &AccountInfo) -> Result<Option<Agent>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    let agent = Agent::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    Ok(Some(agent))
+}
+
+/// Whether auto-declaration candidate `a` (balance, first bet time, tie-break key)
+/// beats `b`: higher balance, then earlier first bet, then smaller key
+fn outranks(a: (u64, i64, &[u8]), b: (u64, i64, &[u8])) -> bool {
+    use std::cmp::Reverse;
+    (a.0, Reverse(a.1), Reverse(a.2)) > (b.0, Reverse(b.1), Reverse(b.2))
+}
+
+/// Last-resort ordering key for an agent: its id hashed with the arena's revealed
+/// randomness, so no id is favoured, or the raw id while nothing is revealed
+fn tiebreak_key(arena: &Arena, agent_id: &str) -> Vec<u8> {
+    match arena.randomness {
+        Some(seed) => hashv(&[&seed, agent_id.as_bytes()]).to_bytes().to_vec(),
+        None => agent_id.as_bytes().to_vec(),
+    }
+}
+
+/// Randomness was committed but not revealed, so a tie-break cannot use it
+fn randomness_withheld(arena: &Arena) -> bool {
+    arena.randomness_commitment.is_some() && arena.randomness.is_none()
+}
+
+/// Whether any stake backs the winner to survive
+fn winner_backed(winner_pool: &AccountInfo) -> Result<bool> {
+    Ok(load_pool(winner_pool)?.is_some_and(|pool| pool.side_staked(BetSide::Survive) > 0))
+}
+
+/// Record an agent's first appearance, counting it towards `total_agents` once
+fn mark_agent_seen(seen: &mut AgentSeen, arena: &mut Account<Arena>, agent_id: &str) {
+    if seen.arena != Pubkey::default() {
+        return;
+    }
+    seen.arena = arena.key();
+    seen.agent_id = agent_id.to_string();
+    arena.total_agents += 1;
+}
+
+/// Basis-point share of an amount, rounded down
+fn bps_of(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+/// Validate and install an M-of-N signer set; an empty set disables it
+fn apply_signer_set(arena: &mut Arena, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+    require!(signers.len() <= MAX_SIGNERS, ArenaError::InvalidSignerSet);
+    require!(
+        threshold as usize <= signers.len() && (threshold > 0 || signers.is_empty()),
+        ArenaError::InvalidSignerSet
+    );
+    for (i, signer) in signers.iter().enumerate() {
+        require!(!signers[..i].contains(signer), ArenaError::InvalidSignerSet);
+    }
+    arena.signers = signers;
+    arena.threshold = threshold;
+    Ok(())
+}
+
+/// Pay out all withdrawable fees, returning the amount
+fn withdraw_all_fees<'info>(
+    arena: &mut Arena,
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    timestamp: i64,
+) -> Result<u64> {
+    require!(arena.fee_recipients.is_empty(), ArenaError::FeeSplitActive);
+    check_result_final(arena, timestamp)?;
+    let amount = arena.withdrawable_fees;
+    require!(amount > 0, ArenaError::NoFeesToWithdraw);
+
+    pay_out(arena, vault, to, amount)?;
+    arena.withdrawable_fees = 0;
+    Ok(amount)
+}
+
+/// Split all withdrawable lamport fees across the fee recipients, passed in
+/// configured order; the last one also receives rounding dust
+fn distribute_all_fees<'info>(
+    arena: &mut Arena,
+    vault: &AccountInfo<'info>,
+    recipient_infos: &[AccountInfo<'info>],
+    timestamp: i64,
+) -> Result<u64> {
+    require!(!arena.is_spl(), ArenaError::WrongBetCurrency);
+    require!(
+        !arena.fee_recipients.is_empty(),
+        ArenaError::InvalidFeeSplit
+    );
+    check_result_final(arena, timestamp)?;
+    let amount = arena.withdrawable_fees;
+    require!(amount > 0, ArenaError::NoFeesToWithdraw);
+
+    let recipients = arena.fee_recipients.clone();
+    require!(
+        recipient_infos.len() >= recipients.len(),
+        ArenaError::InvalidFeeSplit
+    );
+    let mut paid = 0;
+    for (i, (recipient, info)) in recipients.iter().zip(recipient_infos).enumerate() {
+        require_keys_eq!(info.key(), recipient.recipient, ArenaError::InvalidFeeSplit);
+        let share = if i + 1 == recipients.len() {
+            amount - paid
+        } else {
+            bps_of(amount, recipient.bps)
+        };
+        pay_out(arena, vault, info, share)?;
+        paid += share;
+    }
+    arena.withdrawable_fees = 0;
+    Ok(amount)
+}
+
+/// Enter a payout, rejecting nested re-entry. The flag is written back to the
+/// account immediately so a CPI that calls into this program observes it; the
+/// caller clears `processing` once its transfers are done.
+fn begin_payout(arena: &mut Account<Arena>) -> Result<()> {
+    require!(!arena.processing, ArenaError::Reentrancy);
+    arena.processing = true;
+    arena.exit(&crate::ID)
+}
+
+/// Pay escrowed lamports out of an arena's vault
+fn pay_from_vault<'info>(
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    **vault.try_borrow_mut_lamports()? -= amount;
+    **to.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// Move lamports into an arena's vault; practice arenas only record the amount
+fn escrow_in<'info>(
+    arena: &Arena,
+    from: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if arena.practice {
+        return Ok(());
+    }
+    transfer_lamports(from, vault, amount)
+}
+
+/// Pay lamports out of an arena's vault; practice arenas' payouts are no-ops
+fn pay_out<'info>(
+    arena: &Arena,
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if arena.practice {
+        return Ok(());
+    }
+    pay_from_vault(vault, to, amount)
+}
+
+/// Resize a program account to `new_len`, topping its rent up from `payer`
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    new_len: usize,
+) -> Result<()> {
+    let rent_due = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if rent_due > 0 {
+        transfer_lamports(payer, account, rent_due)?;
+    }
+    account.resize(new_len)?;
+    Ok(())
+}
+
+/// Transfer lamports from a signing wallet via the system program
+fn transfer_lamports<'info>(
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    // Unit tests have no runtime to invoke; move the lamports as the system
+    // program would
+    #[cfg(test)]
+    {
+        if !from.is_signer {
+            return Err(ProgramError::MissingRequiredSignature.into());
+        }
+        let remaining = from
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **from.try_borrow_mut_lamports()? = remaining;
+        **to.try_borrow_mut_lamports()? += amount;
+        Ok(())
+    }
+    #[cfg(not(test))]
+    {
+        let ix =
+            anchor_lang::solana_program::system_instruction::transfer(from.key, to.key, amount);
+        anchor_lang::solana_program::program::invoke(&ix, &[from.clone(), to.clone()])?;
+        Ok(())
+    }
+}
+
+/// Unit tests have no runtime to invoke, so token transfers move the balances
+/// as the token program would
+#[cfg(test)]
+fn transfer_checked<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::token::spl_token::state::{Account as TokenState, Mint as MintState};
+
+    let TransferChecked {
+        from,
+        mint,
+        to,
+        authority,
+    } = ctx.accounts;
+    if !authority.is_signer && ctx.signer_seeds.is_empty() {
+        return Err(ProgramError::MissingRequiredSignature.into());
+    }
+    if MintState::unpack(&mint.try_borrow_data()?)?.decimals != decimals {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let mut source = TokenState::unpack(&from.try_borrow_data()?)?;
+    let mut destination = TokenState::unpack(&to.try_borrow_data()?)?;
+    if source.owner != authority.key() {
+        return Err(ProgramError::IllegalOwner.into());
+    }
+    source.amount = source
+        .amount
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    destination.amount += amount;
+    TokenState::pack(source, &mut from.try_borrow_mut_data()?)?;
+    TokenState::pack(destination, &mut to.try_borrow_mut_data()?)?;
+    Ok(())
+}
+
+// Account Structures
+
+#[derive(Accounts)]
+#[instruction(arena_id: String)]
+pub struct InitializeArena<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Arena::INIT_SPACE,
+        seeds = [b"arena", arena_id.as_bytes()],
+        bump
+    )]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Vault::INIT_SPACE,
+        seeds = [b"vault", arena.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_arena_id: String)]
+pub struct CloneArena<'info> {
+    #[account(has_one = authority)]
+    pub source: Account<'info, Arena>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Arena::INIT_SPACE,
+        seeds = [b"arena", new_arena_id.as_bytes()],
+        bump
+    )]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Vault::INIT_SPACE,
+        seeds = [b"vault", arena.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetTags<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitVault<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Vault::INIT_SPACE,
+        seeds = [b"vault", arena.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReallocArena<'info> {
+    /// CHECK: may predate the current layout; owner, discriminator and authority
+    /// are verified in the handler
+    #[account(mut, owner = crate::ID)]
+    pub arena: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct ReallocAgent<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    /// CHECK: may predate the current layout; the discriminator is verified in the
+    /// handler
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SelfExclude<'info> {
+    #[account(
+        init_if_needed,
+        payer = wallet,
+        space = 8 + SelfExclusion::INIT_SPACE,
+        seeds = [b"self-exclusion", wallet.key().as_ref()],
+        bump
+    )]
+    pub exclusion: Account<'info, SelfExclusion>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct BlacklistBettor<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Blacklisted::INIT_SPACE,
+        seeds = [b"blacklist", arena.key().as_ref(), wallet.as_ref()],
+        bump
+    )]
+    pub entry: Account<'info, Blacklisted>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct UnblacklistBettor<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        mut,
+        close = authority,
+        has_one = arena,
+        seeds = [b"blacklist", arena.key().as_ref(), wallet.as_ref()],
+        bump
+    )]
+    pub entry: Account<'info, Blacklisted>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoster<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct RegisterAgent<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Agent::INIT_SPACE,
+        seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: Account<'info, Agent>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AgentSeen::INIT_SPACE,
+        seeds = [b"seen", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub seen: Account<'info, AgentSeen>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct SponsorAgent<'info> {
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        has_one = arena,
+        seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: Account<'info, Agent>,
+
+    #[account(
+        init_if_needed,
+        payer = sponsor,
+        space = 8 + AgentVault::INIT_SPACE,
+        seeds = [b"agent-vault", agent.key().as_ref()],
+        bump
+    )]
+    pub agent_vault: Account<'info, AgentVault>,
+
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct WithdrawSponsorship<'info> {
+    #[account(has_one = authority @ ArenaError::Unauthorized)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        has_one = arena,
+        seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: Account<'info, Agent>,
+
+    #[account(
+        mut,
+        has_one = agent,
+        seeds = [b"agent-vault", agent.key().as_ref()],
+        bump
+    )]
+    pub agent_vault: Account<'info, AgentVault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct LogAgentVersion<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        mut,
+        has_one = arena,
+        seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: Account<'info, Agent>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(keep_id: String, merge_id: String)]
+pub struct MergeAgents<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        mut,
+        has_one = arena,
+        seeds = [b"agent", arena.key().as_ref(), keep_id.as_bytes()],
+        bump
+    )]
+    pub keep: Account<'info, Agent>,
+
+    #[account(
+        mut,
+        has_one = arena,
+        close = authority,
+        seeds = [b"agent", arena.key().as_ref(), merge_id.as_bytes()],
+        bump
+    )]
+    pub merged: Account<'info, Agent>,
+
+    /// CHECK: the merged agent's pool, which may not exist; read only to reject active bets
+    #[account(seeds = [b"pool", arena.key().as_ref(), merge_id.as_bytes()], bump)]
+    pub merge_pool: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String, epoch: u64)]
+pub struct SnapshotAgentBalance<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        has_one = arena,
+        seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: Account<'info, Agent>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AgentBalanceSnapshot::INIT_SPACE,
+        seeds = [b"snapshot", agent.key().as_ref(), &epoch.to_le_bytes()],
+        bump
+    )]
+    pub snapshot: Account<'info, AgentBalanceSnapshot>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(identity: Pubkey)]
+pub struct InitCareer<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AgentCareer::INIT_SPACE,
+        seeds = [b"career", identity.as_ref()],
+        bump
+    )]
+    pub career: Account<'info, AgentCareer>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    transaction_id: String,
+    from_agent: String,
+    to_agent: String,
+    amount: u64,
+    service_type: String,
+    hour_index: i64
+)]
+pub struct LogTransaction<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Transaction::INIT_SPACE,
+        seeds = [b"transaction", transaction_id.as_bytes()],
+        bump
+    )]
+    pub transaction: Account<'info, Transaction>,
+
+    #[account(mut, has_one = authority @ ArenaError::Unauthorized)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AgentSeen::INIT_SPACE,
+        seeds = [b"seen", arena.key().as_ref(), from_agent.as_bytes()],
+        bump
+    )]
+    pub from_seen: Account<'info, AgentSeen>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AgentSeen::INIT_SPACE,
+        seeds = [b"seen", arena.key().as_ref(), to_agent.as_bytes()],
+        bump
+    )]
+    pub to_seen: Account<'info, AgentSeen>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + VolumeBucket::INIT_SPACE,
+        seeds = [b"bucket", arena.key().as_ref(), &hour_index.to_le_bytes()],
+        bump
+    )]
+    pub bucket: Account<'info, VolumeBucket>,
+
+    /// CHECK: the service type's fee rate, which may not exist; read in the handler
+    #[account(seeds = [b"service-fee", arena.key().as_ref(), service_type.as_bytes()], bump)]
+    pub service_fee: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ServiceStats::INIT_SPACE,
+        seeds = [b"service-stats", arena.key().as_ref(), service_type.as_bytes()],
+        bump
+    )]
+    pub service_stats: Account<'info, ServiceStats>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Registered record of the sending agent, credited with the transaction
+    #[account(
+        mut,
+        seeds = [b"agent", arena.key().as_ref(), from_agent.as_bytes()],
+        bump
+    )]
+    pub from_agent_account: Option<Account<'info, Agent>>,
+
+    /// Registered record of the receiving agent, credited with a completed service
+    #[account(
+        mut,
+        seeds = [b"agent", arena.key().as_ref(), to_agent.as_bytes()],
+        bump
+    )]
+    pub to_agent_account: Option<Account<'info, Agent>>,
+}
+
+#[derive(Accounts)]
+#[instruction(service_type: String)]
+pub struct SetServiceFee<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ServiceFeeConfig::INIT_SPACE,
+        seeds = [b"service-fee", arena.key().as_ref(), service_type.as_bytes()],
+        bump
+    )]
+    pub service_fee: Account<'info, ServiceFeeConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct LogDeath<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AgentDeath::INIT_SPACE,
+        seeds = [b"death", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub death: Option<Account<'info, AgentDeath>>,
+
+    #[account(mut, has_one = authority @ ArenaError::Unauthorized)]
+    pub arena: Account<'info, Arena>,
+
+    /// The dying agent's bet pool, created if nobody has bet yet so it can be frozen
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + AgentPool::INIT_SPACE,
+        seeds = [b"pool", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub pool: Account<'info, AgentPool>,
+
+    /// Registered agent record; only agents still alive in this arena can die
+    #[account(
+        mut,
+        has_one = arena,
+        seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: Account<'info, Agent>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Cross-arena career of the dying agent, credited with a loss
+    #[account(mut, seeds = [b"career", agent.identity.as_ref()], bump)]
+    pub career: Option<Account<'info, AgentCareer>>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct AmendDeath<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        mut,
+        has_one = arena,
+        seeds = [b"death", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub death: Account<'info, AgentDeath>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateStats<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct PlaceBet<'info> {
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", bettor.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + AgentPool::INIT_SPACE,
+        seeds = [b"pool", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub pool: Account<'info, AgentPool>,
+
+    /// Registered agent being backed; required when the arena only allows registered agents
+    #[account(seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()], bump)]
+    pub agent: Option<Account<'info, Agent>>,
+
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    /// CHECK: the bettor's blacklist marker; must not exist
+    #[account(seeds = [b"blacklist", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub blacklisted: UncheckedAccount<'info>,
+
+    /// CHECK: the bettor's self-exclusion, which may not exist; read in the handler
+    #[account(seeds = [b"self-exclusion", bettor.key().as_ref()], bump)]
+    pub self_exclusion: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct PlaceBetSpl<'info> {
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", bettor.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut, has_one = bet_mint)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + AgentPool::INIT_SPACE,
+        seeds = [b"pool", arena.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub pool: Account<'info, AgentPool>,
+
+    /// Registered agent being backed; required when the arena only allows registered agents
+    #[account(seeds = [b"agent", arena.key().as_ref(), agent_id.as_bytes()], bump)]
+    pub agent: Option<Account<'info, Agent>>,
+
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    /// CHECK: the bettor's blacklist marker; must not exist
+    #[account(seeds = [b"blacklist", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub blacklisted: UncheckedAccount<'info>,
+
+    /// CHECK: the bettor's self-exclusion, which may not exist; read in the handler
+    #[account(seeds = [b"self-exclusion", bettor.key().as_ref()], bump)]
+    pub self_exclusion: UncheckedAccount<'info>,
+
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, token::mint = bet_mint, token::authority = bettor)]
+    pub bettor_token: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token-vault", arena.key().as_ref()], bump)]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct IncreaseBet<'info> {
+    #[account(mut, has_one = bettor, has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()],
+        bump
+    )]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    /// CHECK: the bettor's blacklist marker; must not exist
+    #[account(seeds = [b"blacklist", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub blacklisted: UncheckedAccount<'info>,
+
+    /// CHECK: the bettor's self-exclusion, which may not exist; read in the handler
+    #[account(seeds = [b"self-exclusion", bettor.key().as_ref()], bump)]
+    pub self_exclusion: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmBet<'info> {
+    #[account(mut, has_one = bettor, has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        mut,
+        seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()],
+        bump
+    )]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepUnconfirmedBet<'info> {
+    #[account(mut, has_one = bettor, has_one = arena, close = bettor)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: refund destination, bound to the bet by `has_one`
+    #[account(mut)]
+    pub bettor: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepUnconfirmedBetSpl<'info> {
+    #[account(mut, has_one = bettor, has_one = arena, close = bettor)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(
+        mut,
+        has_one = bet_mint,
+        seeds = [b"arena", arena.arena_id.as_bytes()],
+        bump
+    )]
+    pub arena: Account<'info, Arena>,
+
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token-vault", arena.key().as_ref()], bump)]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = bet_mint, token::authority = bettor)]
+    pub bettor_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: rent destination, bound to the bet by `has_one`
+    #[account(mut)]
+    pub bettor: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SweepExpiredBets<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBet<'info> {
+    #[account(mut, has_one = bettor, has_one = arena, close = bettor)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()],
+        bump
+    )]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_owner: Pubkey)]
+pub struct TransferBet<'info> {
+    /// Keeps its address, which stays seeded by the original bettor's key.
+    /// Anything deriving a bet from a wallet must check `bet.bettor` too.
+    #[account(mut, has_one = bettor, has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    /// The backed agent's pool; a frozen pool's positions no longer trade
+    #[account(seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(mut, seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub from_profile: Account<'info, UserProfile>,
+
+    /// The new owner's profile, created if they have not bet in this arena
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"profile", arena.key().as_ref(), new_owner.as_ref()],
+        bump
+    )]
+    pub to_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    /// Required when a price is charged; must be the new owner
+    #[account(mut)]
+    pub buyer: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    /// Closed once paid, returning its rent to the bettor
+    #[account(mut, has_one = bettor, has_one = arena, close = bettor)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut, seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(mut, seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    /// CHECK: death record of the backed agent, which may not exist; read in the
+    /// handler to honour a placement prediction
+    #[account(seeds = [b"death", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub death: UncheckedAccount<'info>,
+
+    /// CHECK: record of the backed agent, which may not exist; read in the handler
+    /// for its services count, unless a death record takes precedence
+    #[account(seeds = [b"agent", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub agent: UncheckedAccount<'info>,
+
+    /// Career of the declared winner, credited with what its backers are paid
+    #[account(
+        mut,
+        constraint = arena.winner_career == Some(career.key()) @ ArenaError::Unauthorized
+    )]
+    pub career: Option<Account<'info, AgentCareer>>,
+}
+
+#[derive(Accounts)]
+#[instruction(bettor: Pubkey)]
+pub struct AuthorizePayout<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(has_one = arena, constraint = bet.bettor == bettor @ ArenaError::Unauthorized)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PayoutAuthorization::INIT_SPACE,
+        seeds = [b"payout-auth", bet.key().as_ref()],
+        bump
+    )]
+    pub authorization: Account<'info, PayoutAuthorization>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAuthorizedPayout<'info> {
+    #[account(
+        mut,
+        has_one = arena,
+        has_one = bet,
+        has_one = bettor,
+        seeds = [b"payout-auth", bet.key().as_ref()],
+        bump,
+        close = authority
+    )]
+    pub authorization: Account<'info, PayoutAuthorization>,
+
+    #[account(mut, has_one = bettor, has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut, seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(mut, seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub profile: Account<'info, UserProfile>,
+
+    /// CHECK: payout destination, bound to the bet by `has_one`
+    #[account(mut)]
+    pub bettor: UncheckedAccount<'info>,
+
+    /// CHECK: receives the authorization's rent, bound to the arena by `has_one`
+    #[account(mut)]
+    pub authority: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct KeeperSettle<'info> {
+    /// Closed once paid, returning its rent to the bettor
+    #[account(mut, has_one = bettor, has_one = arena, close = bettor)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut, seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(mut, seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub profile: Account<'info, UserProfile>,
+
+    /// CHECK: payout destination, bound to the bet by `has_one`
+    #[account(mut)]
+    pub bettor: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWinningsSpl<'info> {
+    #[account(mut, has_one = bettor, has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(
+        mut,
+        has_one = bet_mint,
+        seeds = [b"arena", arena.arena_id.as_bytes()],
+        bump
+    )]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(mut, seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub profile: Account<'info, UserProfile>,
+
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token-vault", arena.key().as_ref()], bump)]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = bet_mint, token::authority = bettor)]
+    pub bettor_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub bettor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RefundBet<'info> {
+    /// Closed once refunded, returning its rent to the bettor
+    #[account(mut, has_one = bettor, has_one = arena, close = bettor)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundBetSpl<'info> {
+    #[account(mut, has_one = bettor, has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    #[account(
+        mut,
+        has_one = bet_mint,
+        seeds = [b"arena", arena.arena_id.as_bytes()],
+        bump
+    )]
+    pub arena: Account<'info, Arena>,
+
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token-vault", arena.key().as_ref()], bump)]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = bet_mint, token::authority = bettor)]
+    pub bettor_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub bettor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayoutRoot<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithProof<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    /// The winning bet the proof pays out, marked claimed
+    #[account(mut, has_one = bettor, has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    /// One receipt per bet, so a proof can only be redeemed once
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + ProofClaim::INIT_SPACE,
+        seeds = [b"proof-claim", arena.key().as_ref(), bet.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, ProofClaim>,
+
+    #[account(mut, seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecomputeVolume<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VolumeBetween<'info> {
+    pub arena: Account<'info, Arena>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct MaxLiability<'info> {
+    pub arena: Account<'info, Arena>,
+
+    /// CHECK: the candidate winner's pool, which may not exist
+    #[account(seeds = [b"pool", arena.key().as_ref(), agent_id.as_bytes()], bump)]
+    pub pool: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct ClosePool<'info> {
+    #[account(has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        mut,
+        has_one = arena,
+        seeds = [b"pool", arena.key().as_ref(), agent_id.as_bytes()],
+        bump,
+        close = authority
+    )]
+    pub pool: Account<'info, AgentPool>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PreviewClaim<'info> {
+    #[account(has_one = arena)]
+    pub bet: Account<'info, Bet>,
+
+    pub arena: Account<'info, Arena>,
+
+    #[account(seeds = [b"pool", arena.key().as_ref(), bet.agent_id.as_bytes()], bump)]
+    pub pool: Account<'info, AgentPool>,
+}
+
+#[derive(Accounts)]
+pub struct ReadArenaConfig<'info> {
+    pub arena: Account<'info, Arena>,
+}
+
+#[derive(Accounts)]
+pub struct MultiArenaSummary {}
+
+#[derive(Accounts)]
+#[instruction(bettor: Pubkey)]
+pub struct MyPositions<'info> {
+    pub arena: Account<'info, Arena>,
+
+    #[account(seeds = [b"profile", arena.key().as_ref(), bettor.as_ref()], bump)]
+    pub profile: Account<'info, UserProfile>,
+
+    /// CHECK: the bettor's bet on the survive winner, which may not exist
+    #[account(seeds = [b"bet", bettor.as_ref(), arena.winner.as_bytes()], bump)]
+    pub winner_bet: UncheckedAccount<'info>,
+
+    /// CHECK: the bettor's bet on the first agent to die, which may not exist
+    #[account(seeds = [b"bet", bettor.as_ref(), arena.first_dead.as_bytes()], bump)]
+    pub first_dead_bet: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SeedPool<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureArena<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(winner: String)]
+pub struct DeclareWinner<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    /// The arena's oracle if one is configured, otherwise its authority; pays the
+    /// declaration bond
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    /// CHECK: the winner's pool, which may not exist; read only to detect an unbacked winner
+    #[account(seeds = [b"pool", arena.key().as_ref(), winner.as_bytes()], bump)]
+    pub winner_pool: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Registered record of the winning agent, naming the career to credit
+    #[account(seeds = [b"agent", arena.key().as_ref(), winner.as_bytes()], bump)]
+    pub winner_agent: Option<Account<'info, Agent>>,
+
+    /// Cross-arena career of the winning agent, credited with a win. Careers are
+    /// only created at `[b"career", identity]`, so matching the winner's identity
+    /// pins this to that PDA.
+    #[account(
+        mut,
+        constraint = winner_agent
+            .as_ref()
+            .is_some_and(|agent| agent.identity == career.identity) @ ArenaError::Unauthorized
+    )]
+    pub career: Option<Account<'info, AgentCareer>>,
+}
+
+#[derive(Accounts)]
+pub struct AutoDeclareWinner<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    /// The arena's oracle if one is configured, otherwise its authority; pays the
+    /// declaration bond
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayoutTiers<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeclarePodium<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    /// The arena's oracle if one is configured, otherwise its authority; pays the
+    /// declaration bond
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBetting<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetBettingDeadline<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitRandomness<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealRandomness<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+}
+
+#[derive(Accounts)]
+pub struct SetOracle<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EnterRefundMode<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoidArena<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFlag<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FileDispute<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    /// Only wallets that bet in this arena may dispute it
+    #[account(seeds = [b"profile", arena.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Dispute::INIT_SPACE,
+        seeds = [b"dispute", arena.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DismissDisputes<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OverturnResult<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseBond<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: only receives lamports; must be the resolver that posted the bond
+    #[account(mut, address = arena.bonded_by)]
+    pub resolver: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(mut, has_one = authority @ ArenaError::Unauthorized)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFeesSpl<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        has_one = bet_mint,
+        seeds = [b"arena", arena.arena_id.as_bytes()],
+        bump
+    )]
+    pub arena: Account<'info, Arena>,
+
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token-vault", arena.key().as_ref()], bump)]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = bet_mint)]
+    pub authority_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetSigners<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeRecipients<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeFees<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAction<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + PendingAction::INIT_SPACE,
+        seeds = [b"pending", arena.key().as_ref(), &arena.next_action_id.to_le_bytes()],
+        bump
+    )]
+    pub pending_action: Account<'info, PendingAction>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAction<'info> {
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena)]
+    pub pending_action: Account<'info, PendingAction>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    #[account(mut)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut, has_one = arena)]
+    pub pending_action: Account<'info, PendingAction>,
+
+    /// CHECK: must match the recipient recorded in a fee withdrawal; unused by
+    /// other actions
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPractice<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetBetMint<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+
+    /// Arena-owned escrow for token bets
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"token-vault", arena.key().as_ref()],
+        bump,
+        token::mint = bet_mint,
+        token::authority = arena,
+        token::token_program = token_program
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmitFinalResult<'info> {
+    pub arena: Account<'info, Arena>,
+}
+
+#[derive(Accounts)]
+pub struct Rollover<'info> {
+    #[account(mut, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = next_arena.key() != arena.key() @ ArenaError::InvalidRollover
+    )]
+    pub next_arena: Account<'info, Arena>,
+
+    #[account(
+        mut,
+        constraint = next_vault.arena == next_arena.key() @ ArenaError::InvalidRollover,
+        seeds = [b"vault", next_arena.key().as_ref()],
+        bump
+    )]
+    pub next_vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseArena<'info> {
+    #[account(mut, has_one = authority, close = authority)]
+    pub arena: Account<'info, Arena>,
+
+    #[account(mut, has_one = arena, seeds = [b"vault", arena.key().as_ref()], bump, close = authority)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ProtocolStats::INIT_SPACE,
+        seeds = [b"protocol-stats"],
+        bump
+    )]
+    pub protocol_stats: Account<'info, ProtocolStats>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(feature = "test-clock")]
+#[derive(Accounts)]
+pub struct SetTestClock<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TestClock::INIT_SPACE,
+        seeds = [b"test-clock"],
+        bump
+    )]
+    pub test_clock: Account<'info, TestClock>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Account Data Structures
+
+#[account]
+#[derive(InitSpace)]
+pub struct Arena {
+    #[max_len(50)]
+    pub arena_id: String,
+    pub authority: Pubkey,
+    pub total_transactions: u64,
+    pub total_agents: u32,
+    pub alive_agents: u32,
+    pub dead_agents: u32,
+    pub total_volume: u64,
+    pub avg_balance: u64,
+    pub gini_coefficient: u16,
+    pub started_at: i64,
+    pub total_bets: u64,
+    pub total_bet_volume: u64,
+    pub config: ArenaConfig,
+    #[max_len(50)]
+    pub winner: String,
+    /// Lifecycle stage; only changed through `Arena::transition`
+    pub state: ArenaState,
+    /// Stage to return to when a `Paused` arena is resumed
+    pub paused_from: ArenaState,
+    pub resolved_at: i64,
+    pub round: u32,
+    pub trivial_deaths: u32,
+    pub guardian: Pubkey,
+    /// Feature toggles, one bit per `FLAG_*` index
+    pub flags: u64,
+    pub resolution_fee: u64,
+    pub withdrawable_fees: u64,
+    /// SPL mint bets are denominated in; default pubkey means native SOL
+    pub bet_mint: Pubkey,
+    pub bet_mint_decimals: u8,
+    /// Portion of `total_bet_volume` staked in the die market
+    pub die_bet_volume: u64,
+    #[max_len(50)]
+    pub first_dead: String,
+    pub median_balance: u64,
+    pub unique_bettors: u64,
+    pub health_score: u8,
+    /// `total_transactions` at the last `compute_health`, for the activity window
+    pub health_tx_checkpoint: u64,
+    /// M-of-N signer set for critical actions; empty means authority-only
+    #[max_len(5)]
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub next_action_id: u64,
+    /// Sum of registered alive agents' last known balances
+    pub total_balance_sum: u64,
+    /// Running total of `tx_fee_bps` applied to logged volume (no lamports held)
+    pub accumulated_tx_fees: u64,
+    /// Operator jackpot added to the survive market's pot, separate from bet volume
+    pub seeded_amount: u64,
+    /// When set, only this key may declare results
+    pub oracle: Option<Pubkey>,
+    /// Set for the duration of a payout; always false between instructions
+    pub processing: bool,
+    /// Partners splitting lamport fees via `distribute_fees`; empty pays the authority
+    #[max_len(5)]
+    pub fee_recipients: Vec<FeeRecipient>,
+    /// Official agent ids competing in this arena, for clients and `FLAG_ROSTER_ONLY`
+    #[max_len(32, 32)]
+    pub roster: Vec<String>,
+    /// Merkle root of `(arena, bet, bettor, amount)` payout leaves, once committed
+    pub payout_root: Option<[u8; 32]>,
+    /// Resolved to an agent nobody backed, which put the arena in refund mode
+    pub winner_had_no_backers: bool,
+    /// Operator-defined code explaining the void
+    pub void_reason: u8,
+    #[max_len(64)]
+    pub void_note: String,
+    /// Unix time after which bets are rejected; 0 means no deadline
+    pub betting_closes_at: i64,
+    /// Anti-sniping extensions applied to the current deadline
+    pub extensions_used: u32,
+    /// Authority proposed by `propose_authority`, awaiting `accept_authority`
+    pub pending_authority: Option<Pubkey>,
+    pub transfer_proposed_at: i64,
+    /// Deaths logged so far, stored or tallied, used to assign placements
+    pub deaths_logged: u32,
+    /// SOL/USD rate in micro-USD per SOL recorded at resolution; 0 if none
+    pub sol_usd_price: u64,
+    /// Resolved with less than `min_pool_to_payout` staked, forcing refund mode
+    pub pool_below_minimum: bool,
+    /// Share of the survive pot in bps for each podium place, 1st first; empty
+    /// means winner-takes-all
+    #[max_len(5)]
+    pub payout_tiers: Vec<u16>,
+    /// Agents in finishing order, recorded by `declare_podium`, or the sharing
+    /// agents by descending weight from `declare_proportional`
+    #[max_len(8, 32)]
+    pub podium: Vec<String>,
+    /// Sum of `payout_tiers` over podium places somebody backed; unbacked places'
+    /// shares are spread across the rest
+    pub podium_backed_bps: u16,
+    /// Free-form discovery labels, e.g. "pvp" or "high-stakes"
+    #[max_len(8, 24)]
+    pub tags: Vec<String>,
+    /// Hit `max_transactions`; logging is closed and the result can be declared
+    pub ready_to_resolve: bool,
+    /// Disputes filed against the result
+    pub dispute_count: u32,
+    /// Disputes reached `dispute_threshold`; claims wait for `dismiss_disputes`
+    pub under_review: bool,
+    /// Bond held in the vault for the declared result, until released or forfeited
+    pub declaration_bond: u64,
+    /// Resolver who posted `declaration_bond` and receives it back
+    pub bonded_by: Pubkey,
+    /// Bond forfeited by an overturned result, shared pro rata across refunds
+    pub forfeited_bond: u64,
+    /// Id of the last `Checkpoint` emitted; increases by one per checkpoint
+    pub checkpoint_id: u64,
+    /// Career credited at `declare_winner`, the only one `claim_winnings` updates
+    pub winner_career: Option<Pubkey>,
+    /// Per-podium-agent weights from `declare_proportional`; empty means the
+    /// podium is split by `payout_tiers`
+    #[max_len(8)]
+    pub share_weights: Vec<u64>,
+    /// Sum of `share_weights` over agents somebody backed
+    pub backed_weight: u64,
+    /// Demo arena: bets, seeds and payouts are recorded but no lamports move
+    pub practice: bool,
+    /// Jackpot rolled in from earlier arenas, included in `seeded_amount`
+    pub rolled_in_amount: u64,
+    /// Service types seen in logged transactions, for economy breadth
+    pub distinct_service_types: u32,
+    /// SHA-256 of the randomness preimage, set by `commit_randomness`
+    pub randomness_commitment: Option<[u8; 32]>,
+    /// Revealed preimage matching `randomness_commitment`, used for tie-breaks
+    pub randomness: Option<[u8; 32]>,
+    /// Winnings paid out of the pots, for what the vault still owes winners
+    pub total_claimed: u64,
+    /// Lamports of bets escrowed while awaiting `confirm_bet`, outside the pools
+    pub unconfirmed_escrow: u64,
+    /// Whether anyone bet on `first_dead` dying, i.e. the die pot is owed
+    pub first_dead_backed: bool,
+    /// Principal and bond shares returned in refund mode
+    pub total_refunded: u64,
+}
+
+/// Leading fields of `Arena`, stable across schema versions
+#[derive(AnchorDeserialize)]
+struct ArenaHeader {
+    arena_id: String,
+    authority: Pubkey,
+}
+
+impl Arena {
+    /// Move to a new lifecycle stage, rejecting transitions the lifecycle forbids.
+    /// Any live stage may pause; a paused arena may only resume where it was.
+    fn transition(&mut self, to: ArenaState) -> Result<()> {
+        use ArenaState::*;
+        let allowed = match (self.state, to) {
+            (Paused, to) => to == self.paused_from,
+            (from, Paused) => from != Closed,
+            (Open, BettingClosed | Resolved | RefundMode | Voided) => true,
+            (BettingClosed, Open | Resolved | RefundMode | Voided) => true,
+            (RefundMode, Voided | Closed) => true,
+            (Resolved, Voided) => self.under_review,
+            (Resolved | Voided, Closed) => true,
+            _ => false,
+        };
+        require!(allowed, ArenaError::InvalidStateTransition);
+        if to == Paused {
+            self.paused_from = self.state;
+        }
+        self.state = to;
+        Ok(())
+    }
+
+    /// The lifecycle stage, looking through a pause
+    fn stage(&self) -> ArenaState {
+        if self.state == ArenaState::Paused {
+            self.paused_from
+        } else {
+            self.state
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state == ArenaState::Paused
+    }
+
+    /// A result was declared, including one that fell back to refunds
+    fn resolved(&self) -> bool {
+        match self.stage() {
+            ArenaState::Resolved => true,
+            ArenaState::RefundMode => self.winner_had_no_backers || self.pool_below_minimum,
+            _ => false,
+        }
+    }
+
+    /// Only principal refunds and reads are allowed
+    fn refund_mode(&self) -> bool {
+        matches!(self.stage(), ArenaState::RefundMode | ArenaState::Voided)
+    }
+
+    /// Voided for cause; implies `refund_mode`
+    fn voided(&self) -> bool {
+        self.stage() == ArenaState::Voided
+    }
+
+    fn flag(&self, flag: u8) -> bool {
+        self.flags & (1 << flag) != 0
+    }
+
+    fn set_flag(&mut self, flag: u8, value: bool) {
+        if value {
+            self.flags |= 1 << flag;
+        } else {
+            self.flags &= !(1 << flag);
+        }
+    }
+
+    fn is_spl(&self) -> bool {
+        self.bet_mint != Pubkey::default()
+    }
+
+    fn add_bet_volume(&mut self, side: BetSide, amount: u64) {
+        self.total_bet_volume += amount;
+        if side == BetSide::Die {
+            self.die_bet_volume += amount;
+        }
+    }
+
+    fn remove_bet_volume(&mut self, side: BetSide, amount: u64) {
+        self.total_bet_volume -= amount;
+        if side == BetSide::Die {
+            self.die_bet_volume -= amount;
+        }
+    }
+
+    fn side_volume(&self, side: BetSide) -> u64 {
+        match side {
+            BetSide::Survive => self.total_bet_volume - self.die_bet_volume,
+            BetSide::Die => self.die_bet_volume,
+        }
+    }
+
+    fn recompute_avg_balance(&mut self) {
+        self.avg_balance = self
+            .total_balance_sum
+            .checked_div(self.alive_agents as u64)
+            .unwrap_or(0);
+    }
+
+    fn is_winning_bet(&self, bet: &Bet) -> bool {
+        self.wins(bet.bet_side, &bet.agent_id)
+    }
+
+    /// Whether backing `agent_id` on `side` pays out under the declared result
+    fn wins(&self, side: BetSide, agent_id: &str) -> bool {
+        if side == BetSide::Survive && !self.podium.is_empty() {
+            self.podium_place(agent_id).is_some()
+        } else {
+            agent_id == self.winning_agent(side)
+        }
+    }
+
+    fn podium_place(&self, agent_id: &str) -> Option<usize> {
+        self.podium.iter().position(|id| id == agent_id)
+    }
+
+    /// A podium place's share of the survive pot and the backed total it is
+    /// divided by: proportional weights if declared, otherwise the payout tiers
+    fn podium_share(&self, place: usize) -> (u64, u64) {
+        if self.share_weights.is_empty() {
+            (
+                self.payout_tiers[place] as u64,
+                self.podium_backed_bps as u64,
+            )
+        } else {
+            (self.share_weights[place], self.backed_weight)
+        }
+    }
+
+    fn winning_agent(&self, side: BetSide) -> &str {
+        match side {
+            BetSide::Survive => &self.winner,
+            BetSide::Die => &self.first_dead,
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Transaction {
+    #[max_len(50)]
+    pub transaction_id: String,
+    #[max_len(50)]
+    pub from_agent: String,
+    #[max_len(50)]
+    pub to_agent: String,
+    pub amount: u64,
+    #[max_len(20)]
+    pub service_type: String,
+    pub timestamp: i64,
+    pub arena: Pubkey,
+    /// Optional annotation, e.g. what the payment was for; empty if none
+    #[max_len(100)]
+    pub memo: String,
+    /// Reference to the matching payment outside Solana, e.g. an Ethereum tx hash
+    pub external_ref: Option<[u8; 64]>,
+    /// The `VolumeBucket` counting this transaction, within one hour of
+    /// `timestamp / BUCKET_SECONDS`
+    pub hour_index: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AgentDeath {
+    #[max_len(50)]
+    pub agent_id: String,
+    #[max_len(50)]
+    pub agent_name: String,
+    pub final_balance: u64,
+    pub services_completed: u32,
+    pub timestamp: i64,
+    pub arena: Pubkey,
+    /// Finishing position, counted down from `total_agents` as agents die
+    pub placement: u32,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Bet {
+    pub bettor: Pubkey,
+    #[max_len(50)]
+    pub agent_id: String,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub arena: Pubkey,
+    pub claimed: bool,
+    pub bet_side: BetSide,
+    /// Total paid in, including any entry fee; `amount` is the staked remainder
+    pub gross_amount: u64,
+    /// Predicted finishing position of the agent; 0 means no prediction
+    pub predicted_placement: u32,
+    /// False while a large bet awaits `confirm_bet`; unconfirmed stakes are not in the pool
+    pub confirmed: bool,
+}
+
+/// Receipt for a payout redeemed with `claim_with_proof`
+#[account]
+#[derive(InitSpace)]
+pub struct ProofClaim {
+    pub arena: Pubkey,
+    pub bet: Pubkey,
+    pub bettor: Pubkey,
+    pub amount: u64,
+}
+
+/// A payout approved by the authority, executable by anyone via
+/// `execute_authorized_payout`
+#[account]
+#[derive(InitSpace)]
+pub struct PayoutAuthorization {
+    pub arena: Pubkey,
+    pub bet: Pubkey,
+    pub bettor: Pubkey,
+    pub amount: u64,
+}
+
+/// Where an arena is in its lifecycle. `Open` takes bets; `BettingClosed` is
+/// awaiting a result; `Resolved` pays winners; `RefundMode` and `Voided` return
+/// principal; `Closed` is final. `Paused` halts betting and claims in any stage.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum ArenaState {
+    #[default]
+    Open,
+    Paused,
+    BettingClosed,
+    Resolved,
+    Voided,
+    RefundMode,
+    Closed,
+}
+
+/// Which market a bet is in: the agent surviving (winning) or dying first
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum BetSide {
+    #[default]
+    Survive,
+    Die,
+}
+
+/// A critical action awaiting approval from the arena's signer set
+#[account]
+#[derive(InitSpace)]
+pub struct PendingAction {
+    pub arena: Pubkey,
+    pub action_id: u64,
+    pub action: ArenaAction,
+    #[max_len(5)]
+    pub approvals: Vec<Pubkey>,
+    pub executed: bool,
+}
+
+/// An agent's weight in a `declare_proportional` split
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AgentShare {
+    pub agent_id: String,
+    pub weight: u64,
+}
+
+/// One partner's share of the arena's lamport fees
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct FeeRecipient {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+/// Actions gated behind the M-of-N signer set
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub enum ArenaAction {
+    WithdrawFees {
+        recipient: Pubkey,
+    },
+    /// Replace the signer set; an empty set hands control back to the authority
+    SetSigners {
+        #[max_len(MAX_SIGNERS)]
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    },
+    /// Split withdrawable fees across the configured fee recipients
+    DistributeFees,
+}
+
+/// Per-bettor aggregate within an arena; its creation counts a unique bettor
+#[account]
+#[derive(InitSpace)]
+pub struct UserProfile {
+    pub arena: Pubkey,
+    pub bettor: Pubkey,
+    pub bet_count: u64,
+    pub total_staked: u64,
+    pub last_bet_at: i64,
+    /// Winnings paid out to the bettor, in the arena's bet currency
+    pub total_won: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Agent {
+    pub arena: Pubkey,
+    #[max_len(50)]
+    pub agent_id: String,
+    #[max_len(50)]
+    pub name: String,
+    pub balance: u64,
+    pub registered_at: i64,
+    #[max_len(32)]
+    pub version: String,
+    pub model_hash: [u8; 32],
+    pub dead: bool,
+    /// Transactions logged with this agent as `from_agent`
+    pub tx_count: u64,
+    /// Transactions logged with this agent as `to_agent`, i.e. services it was paid for
+    pub services_completed: u32,
+    /// Cross-arena identity whose `AgentCareer` this agent's results count toward
+    pub identity: Pubkey,
+}
+
+/// Escrow for sponsorship of one agent's bankroll, kept apart from the arena vault
+#[account]
+#[derive(InitSpace)]
+pub struct AgentVault {
+    pub agent: Pubkey,
+    /// Lifetime lamports contributed by sponsors
+    pub sponsored: u64,
+}
+
+/// An agent's balance at one epoch, for per-agent time series
+#[account]
+#[derive(InitSpace)]
+pub struct AgentBalanceSnapshot {
+    pub agent: Pubkey,
+    pub epoch: u64,
+    pub balance: u64,
+    pub taken_at: i64,
+}
+
+/// Long-term record for an agent identity competing across many arenas
+#[account]
+#[derive(InitSpace)]
+pub struct AgentCareer {
+    pub identity: Pubkey,
+    pub wins: u32,
+    pub losses: u32,
+    /// Sum of final balances reported when the agent died
+    pub total_earnings: u64,
+    /// Survive-market stake on the agent across settled arenas
+    pub total_backed: u64,
+    /// Winnings claimed by the agent's survive backers; over `total_backed` it
+    /// gives the backing ROI
+    pub total_paid_to_backers: u64,
+}
+
+/// Holds an arena's SOL escrow: stakes, seeded liquidity and uncollected fees.
+/// The arena account itself keeps only its rent.
+#[account]
+#[derive(InitSpace)]
+pub struct Vault {
+    pub arena: Pubkey,
+}
+
+/// One bettor's dispute of an arena's result
+#[account]
+#[derive(InitSpace)]
+pub struct Dispute {
+    pub arena: Pubkey,
+    pub bettor: Pubkey,
+    pub filed_at: i64,
+}
+
+/// A wallet's voluntary, protocol-wide break from betting
+#[account]
+#[derive(InitSpace)]
+pub struct SelfExclusion {
+    pub wallet: Pubkey,
+    /// Unix time from which the wallet may bet again
+    pub until: i64,
+}
+
+/// Marks a wallet barred from betting in an arena
+#[account]
+#[derive(InitSpace)]
+pub struct Blacklisted {
+    pub arena: Pubkey,
+    pub wallet: Pubkey,
+}
+
+/// First-seen marker that keeps `Arena.total_agents` counted on-chain
+#[account]
+#[derive(InitSpace)]
+pub struct AgentSeen {
+    pub arena: Pubkey,
+    #[max_len(50)]
+    pub agent_id: String,
+}
+
+/// Lifetime totals across every closed arena, for protocol-level dashboards
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolStats {
+    pub arenas_closed: u64,
+    pub total_volume: u64,
+    pub total_bet_volume: u64,
+    /// Sum of per-arena unique bettors; a wallet active in two arenas counts twice
+    pub unique_bettors: u64,
+}
+
+/// Notional fee rate for one service type, so e.g. compute can be booked at a
+/// higher rate than storage
+#[account]
+#[derive(InitSpace)]
+pub struct ServiceFeeConfig {
+    pub arena: Pubkey,
+    #[max_len(20)]
+    pub service_type: String,
+    pub fee_bps: u16,
+}
+
+/// Running totals for one service type; its creation counts towards
+/// `Arena.distinct_service_types`
+#[account]
+#[derive(InitSpace)]
+pub struct ServiceStats {
+    pub arena: Pubkey,
+    #[max_len(20)]
+    pub service_type: String,
+    pub transaction_count: u64,
+    pub volume: u64,
+}
+
+/// Transaction volume logged in one `BUCKET_SECONDS` window, for time-series charts
+#[account]
+#[derive(InitSpace)]
+pub struct VolumeBucket {
+    pub arena: Pubkey,
+    /// `timestamp / BUCKET_SECONDS` of the window, as reported by the logger
+    pub hour_index: i64,
+    pub volume: u64,
+    pub transaction_count: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AgentPool {
+    pub arena: Pubkey,
+    #[max_len(50)]
+    pub agent_id: String,
+    pub total_staked: u64,
+    pub bet_count: u64,
+    /// Portion of `total_staked` backing this agent to die first
+    pub die_staked: u64,
+    /// Placement time of the pool's first live bet; 0 until then
+    pub first_bet_at: i64,
+    /// When the agent's death was logged, after which bets are rejected; 0 while alive
+    pub frozen_at: i64,
+    /// Winnings paid so far to this agent's backers, excluding placement bonuses
+    pub claimed: u64,
+}
+
+impl AgentPool {
+    fn add_stake(&mut self, side: BetSide, amount: u64) {
+        self.total_staked += amount;
+        if side == BetSide::Die {
+            self.die_staked += amount;
+        }
+    }
+
+    fn remove_stake(&mut self, side: BetSide, amount: u64) {
+        self.total_staked -= amount;
+        if side == BetSide::Die {
+            self.die_staked -= amount;
+        }
+    }
+
+    fn side_staked(&self, side: BetSide) -> u64 {
+        match side {
+            BetSide::Survive => self.total_staked - self.die_staked,
+            BetSide::Die => self.die_staked,
+        }
+    }
+}
+
+// Configuration
+
+/// Operator-tunable arena rules
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct ArenaConfig {
+    /// Seconds after `started_at` before a winner may be declared
+    pub min_duration: i64,
+    /// Deaths with fewer completed services are tallied instead of stored
+    pub min_services: u32,
+    /// Protocol fee taken from the pot at resolution
+    pub fee_bps: u16,
+    /// Emit non-essential `msg!` logs; high-frequency arenas can turn this off
+    pub verbose: bool,
+    /// Bets must be a multiple of this many lamports; 0 disables the check
+    pub lamport_granularity: u64,
+    /// Minimum seconds between bets from one wallet; 0 disables it
+    pub bet_cooldown: i64,
+    /// Notional fee on logged transaction volume. Bookkeeping only: agents settle
+    /// off-chain, so no lamports move; operators use the total to size pool seeding.
+    pub tx_fee_bps: u16,
+    /// SOL bets of at least this many lamports need `confirm_bet`; 0 disables it
+    pub confirm_threshold: u64,
+    /// Seconds a bettor has to confirm before the bet may be swept and refunded
+    pub confirm_timeout: i64,
+    /// Fee taken from each bet on entry; winnings are computed on the remainder
+    pub entry_fee_bps: u16,
+    /// Bets this many seconds before `betting_closes_at` trigger an extension
+    pub snipe_window: i64,
+    /// Seconds added to the deadline per late bet; 0 disables anti-sniping
+    pub snipe_extension: i64,
+    /// Cap on extensions per deadline
+    pub max_extensions: u32,
+    /// Seconds after resolution before keepers may settle unclaimed bets; never
+    /// less than `MIN_KEEPER_GRACE`
+    pub keeper_grace: i64,
+    /// Share of a keeper-settled payout paid to the keeper, up to
+    /// `MAX_KEEPER_REWARD_BPS`
+    pub keeper_reward_bps: u16,
+    /// Extra payout for a correct placement prediction, funded from fees
+    pub placement_bonus_bps: u16,
+    /// Total stake required at resolution for winnings to unlock; smaller pools refund
+    pub min_pool_to_payout: u64,
+    /// Share of a cancelled bet kept as protocol fees; 0 keeps cancels free
+    pub cancel_fee_bps: u16,
+    /// Logged transactions after which the arena is ready to resolve and stops
+    /// logging; 0 means no cap
+    pub max_transactions: u64,
+    /// Lamports per credit for `place_bet_credits`; 0 disables credit betting
+    pub credit_value: u64,
+    /// Seconds after resolution during which bettors may dispute; 0 disables disputes
+    pub dispute_window: i64,
+    /// Disputes that freeze claims pending authority review
+    pub dispute_threshold: u32,
+    /// Lamports the resolver posts with `declare_winner`, forfeited to refunded
+    /// bettors if the result is overturned; 0 requires no bond
+    pub declaration_bond: u64,
+    /// Largest share a new bet may take of the arena's post-bet pool (stakes plus
+    /// seed); 0 disables the cap
+    pub max_bet_ratio_bps: u16,
+    /// Backed agents completing more services than this earn their winners the
+    /// productivity bonus
+    pub productive_services: u32,
+    /// Extra payout for backing a productive agent, funded from fees
+    pub productivity_bonus_bps: u16,
+    /// Lamports paid into the prize pool to register an agent; 0 makes entry free
+    pub agent_entry_fee: u64,
+}
+
+impl ArenaConfig {
+    fn needs_confirmation(&self, amount: u64) -> bool {
+        self.confirm_threshold > 0 && amount >= self.confirm_threshold
+    }
+
+    /// Portion of an incoming bet that is staked after the entry fee
+    fn stake_after_entry_fee(&self, amount: u64) -> Result<u64> {
+        let staked = amount - bps_of(amount, self.entry_fee_bps);
+        require!(staked > 0, ArenaError::InvalidBetAmount);
+        Ok(staked)
+    }
+}
+
+/// Partial update for `configure_arena`; `None` leaves a field unchanged
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ArenaConfigUpdate {
+    pub min_duration: Option<i64>,
+    pub min_services: Option<u32>,
+    pub fee_bps: Option<u16>,
+    pub verbose: Option<bool>,
+    pub lamport_granularity: Option<u64>,
+    pub bet_cooldown: Option<i64>,
+    pub tx_fee_bps: Option<u16>,
+    pub confirm_threshold: Option<u64>,
+    pub confirm_timeout: Option<i64>,
+    pub entry_fee_bps: Option<u16>,
+    pub snipe_window: Option<i64>,
+    pub snipe_extension: Option<i64>,
+    pub max_extensions: Option<u32>,
+    pub keeper_grace: Option<i64>,
+    pub keeper_reward_bps: Option<u16>,
+    pub placement_bonus_bps: Option<u16>,
+    pub min_pool_to_payout: Option<u64>,
+    pub cancel_fee_bps: Option<u16>,
+    pub max_transactions: Option<u64>,
+    pub credit_value: Option<u64>,
+    pub dispute_window: Option<i64>,
+    pub dispute_threshold: Option<u32>,
+    pub declaration_bond: Option<u64>,
+    pub max_bet_ratio_bps: Option<u16>,
+    pub productive_services: Option<u32>,
+    pub productivity_bonus_bps: Option<u16>,
+    pub agent_entry_fee: Option<u64>,
+}
+
+// Return Data
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimPreview {
+    pub eligible: bool,
+    pub reason_code: u8,
+    pub payout: u64,
+    /// The arena's void reason when `reason_code` is `Voided`
+    pub void_reason: u8,
+}
+
+/// Directory-card view of one arena, returned by `multi_arena_summary`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ArenaSummary {
+    pub arena: Pubkey,
+    pub state: ArenaState,
+    pub total_agents: u32,
+    pub alive_agents: u32,
+    pub total_bets: u64,
+    pub total_bet_volume: u64,
+    pub unique_bettors: u64,
+    pub betting_closes_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BettorPosition {
+    pub bet_count: u64,
+    pub total_staked: u64,
+    pub total_won: u64,
+    pub has_unclaimed: bool,
+}
+
+// Events
+
+/// Final snapshot of a resolved arena, for off-chain archivers
+#[event]
+pub struct ArenaResult {
+    pub arena: Pubkey,
+    pub winner: String,
+    pub first_dead: String,
+    pub total_bet_volume: u64,
+    pub survive_pool: u64,
+    pub die_pool: u64,
+    pub fee_taken: u64,
+    pub total_bets: u64,
+    pub unique_bettors: u64,
+    pub gini_coefficient: u16,
+    pub resolved_at: i64,
+    /// Micro-USD per SOL at resolution; the `_usd` fields are 0 when it is unset
+    pub sol_usd_price: u64,
+    pub total_bet_volume_usd: u64,
+    pub fee_taken_usd: u64,
+}
+
+/// Emitted when a bet is topped up; `delta` is the stake added after entry fees
+#[event]
+pub struct BetIncreased {
+    pub arena: Pubkey,
+    pub bettor: Pubkey,
+    pub agent_id: String,
+    pub delta: u64,
+    pub new_amount: u64,
+    /// The agent pool's total stake after the change
+    pub pool_total: u64,
+}
+
+/// Emitted when a bet is withdrawn before betting closes
+#[event]
+pub struct BetCancelled {
+    pub arena: Pubkey,
+    pub bettor: Pubkey,
+    pub agent_id: String,
+    pub refunded: u64,
+    /// The agent pool's total stake after the change
+    pub pool_total: u64,
+}
+
+/// Emitted when an arena is voided, so bettors can see why
+#[event]
+pub struct ArenaVoided {
+    pub arena: Pubkey,
+    pub void_reason: u8,
+    pub void_note: String,
+    pub voided_at: i64,
+}
+
+/// Emitted per logged transaction with the arena totals after it, so dashboards
+/// need not refetch the arena
+#[event]
+pub struct VolumeUpdated {
+    pub arena: Pubkey,
+    pub transaction_id: String,
+    pub amount: u64,
+    pub total_transactions: u64,
+    pub total_volume: u64,
+}
+
+/// Emitted when a death record is corrected, with the values it replaced
+#[event]
+pub struct AmendedDeath {
+    pub arena: Pubkey,
+    pub agent_id: String,
+    pub previous_balance: u64,
+    pub final_balance: u64,
+    pub previous_services: u32,
+    pub services_completed: u32,
+    pub amended_at: i64,
+}
+
+/// Indexer resume point; `schema_version` tells it how to decode arena accounts
+#[event]
+pub struct Checkpoint {
+    pub arena: Pubkey,
+    pub checkpoint_id: u64,
+    pub schema_version: u8,
+    pub block_height: u64,
+    pub total_transactions: u64,
+    pub total_volume: u64,
+    pub alive_agents: u32,
+    pub dead_agents: u32,
+    pub total_bets: u64,
+    pub total_bet_volume: u64,
+    pub timestamp: i64,
+}
+
+// Errors
+
+#[error_code]
+pub enum ArenaError {
+    #[msg("Arena has already been resolved")]
+    ArenaResolved,
+    #[msg("Arena has not reached its minimum duration")]
+    ArenaTooYoung,
+    #[msg("Invalid arena configuration")]
+    InvalidConfig,
+    #[msg("Bet amount must be greater than zero")]
+    InvalidBetAmount,
+    #[msg("Arena has not been resolved")]
+    ArenaNotResolved,
+    #[msg("Bet has already been claimed")]
+    AlreadyClaimed,
+    #[msg("Bet is not on the winning agent")]
+    NotWinningBet,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Agent is below the arena's min_services; omit the death account")]
+    DeathBelowMinServices,
+    #[msg("Death account is required for this agent")]
+    DeathAccountRequired,
+    #[msg("Arena is paused")]
+    ArenaPaused,
+    #[msg("Signer is not allowed to perform this action")]
+    Unauthorized,
+    #[msg("No fees available to withdraw")]
+    NoFeesToWithdraw,
+    #[msg("Bets have already been placed in this arena")]
+    BetsAlreadyPlaced,
+    #[msg("Decimals do not match the mint")]
+    MintDecimalsMismatch,
+    #[msg("No balances provided")]
+    NoBalances,
+    #[msg("Too many balances provided")]
+    TooManyBalances,
+    #[msg("A priced transfer must be signed by the new owner")]
+    BuyerMismatch,
+    #[msg("Bet amount is not a multiple of the arena's lamport granularity")]
+    InvalidBetGranularity,
+    #[msg("Bettor must wait for the cooldown before betting again")]
+    BetCooldown,
+    #[msg("This action requires approval from the signer set")]
+    MultisigRequired,
+    #[msg("Invalid signer set or threshold")]
+    InvalidSignerSet,
+    #[msg("Signer has already approved this action")]
+    AlreadyApproved,
+    #[msg("Action has already been executed")]
+    ActionAlreadyExecuted,
+    #[msg("Action has not reached the approval threshold")]
+    NotEnoughApprovals,
+    #[msg("Version must be 1-32 bytes")]
+    InvalidVersion,
+    #[msg("Agent is not registered in this arena")]
+    UnknownAgent,
+    #[msg("Agent has already been logged as dead")]
+    AgentAlreadyDead,
+    #[msg("No alive agents to compute over")]
+    NoAliveAgents,
+    #[msg("Instruction does not match the arena's bet currency")]
+    WrongBetCurrency,
+    #[msg("Identifiers must be 1-32 ASCII letters, digits, '-' or '_'")]
+    InvalidIdentifier,
+    #[msg("Cannot merge an agent into itself")]
+    InvalidMerge,
+    #[msg("Agent has active bets")]
+    AgentHasBets,
+    #[msg("Bet is awaiting confirmation")]
+    BetNotConfirmed,
+    #[msg("Bet has already been confirmed")]
+    BetAlreadyConfirmed,
+    #[msg("Confirmation window has closed")]
+    ConfirmationWindowClosed,
+    #[msg("Confirmation window is still open")]
+    ConfirmationWindowOpen,
+    #[msg("Hour index does not match the transaction timestamp")]
+    InvalidHourIndex,
+    #[msg("Arena is refund-only")]
+    ArenaRefundOnly,
+    #[msg("Arena is not in refund mode")]
+    NotInRefundMode,
+    #[msg("Payout already in progress")]
+    Reentrancy,
+    #[msg("Fee split must have at most 5 recipients with shares totalling 10000 bps")]
+    InvalidFeeSplit,
+    #[msg("Fees are split among recipients; use distribute_fees")]
+    FeeSplitActive,
+    #[msg("Transaction id was already logged with different data")]
+    DuplicateTransaction,
+    #[msg("Roster must have at most 32 unique agent ids")]
+    InvalidRoster,
+    #[msg("Agent is not on the arena roster")]
+    NotOnRoster,
+    #[msg("Payout root has already been set")]
+    PayoutRootAlreadySet,
+    #[msg("No payout root has been set")]
+    PayoutRootMissing,
+    #[msg("Payout proof does not match the root")]
+    InvalidPayoutProof,
+    #[msg("Payouts are settled by proof; use claim_with_proof")]
+    ProofClaimRequired,
+    #[msg("Arena has been voided")]
+    ArenaVoided,
+    #[msg("Void note must be at most 64 bytes")]
+    InvalidVoidNote,
+    #[msg("Account is not an arena")]
+    NotAnArena,
+    #[msg("Arena already has the current size")]
+    ArenaUpToDate,
+    #[msg("Betting has closed")]
+    BettingClosed,
+    #[msg("Buckets must belong to the arena, lie in range and be in ascending order")]
+    InvalidBucketRange,
+    #[msg("Authority transfer is still timelocked")]
+    AuthorityTimelocked,
+    #[msg("Keeper grace period has not elapsed")]
+    KeeperGracePeriod,
+    #[msg("Payout does not fit in a u64")]
+    PayoutOverflow,
+    #[msg("Unknown feature flag")]
+    InvalidFlag,
+    #[msg("Transaction amounts do not sum to the arena's total volume")]
+    VolumeMismatch,
+    #[msg("Bettor is blacklisted in this arena")]
+    BettorBlacklisted,
+    #[msg("Payout tiers must be non-zero bps summing to 10000")]
+    InvalidPayoutTiers,
+    #[msg("Podium must rank one distinct agent per payout tier, with its pool")]
+    InvalidPodium,
+    #[msg("Memo exceeds the maximum length")]
+    MemoTooLong,
+    #[msg("Arena still holds unclaimed funds or fees")]
+    ArenaNotSettled,
+    #[msg("Agent counts contradict the arena's tracked totals")]
+    InconsistentStats,
+    #[msg("Authorized payout exceeds the bet's entitlement")]
+    PayoutExceedsEntitlement,
+    #[msg("Tags must be distinct, non-empty and within the count and length limits")]
+    InvalidTags,
+    #[msg("Existing bet is in another arena or on the other side")]
+    BetMismatch,
+    #[msg("Sponsorship amount must be positive")]
+    InvalidSponsorAmount,
+    #[msg("Arena reached its transaction cap and is ready to resolve")]
+    TransactionCapReached,
+    #[msg("Batch too large: at most 8 entries (MAX_BATCH_LEN) per instruction")]
+    BatchTooLarge,
+    #[msg("Batch accounts are missing or do not match")]
+    InvalidBatch,
+    #[msg("This arena does not accept credit bets")]
+    CreditsDisabled,
+    #[msg("Wallet is self-excluded from betting")]
+    SelfExcluded,
+    #[msg("Self-exclusion must end in the future and may only be extended")]
+    InvalidExclusion,
+    #[msg("Agent has been eliminated; its pool accepts no more bets")]
+    PoolFrozen,
+    #[msg("Vault holds less than the arena owes")]
+    InsufficientReserves,
+    #[msg("The arena's lifecycle does not allow this state change")]
+    InvalidStateTransition,
+    #[msg("Result is under review after disputes")]
+    ResultUnderReview,
+    #[msg("Disputes are disabled or the dispute window has closed")]
+    DisputeWindowClosed,
+    #[msg("Arena is not under review")]
+    NotUnderReview,
+    #[msg("No declaration bond is held")]
+    NoBond,
+    #[msg("Declaration bond is locked until the dispute window passes")]
+    BondLocked,
+    #[msg("Pool still owes winnings to its backers")]
+    PoolHasUnclaimedBets,
+    #[msg("Bet exceeds the arena's maximum share of the pool")]
+    BetTooLargeForPool,
+    #[msg("Account is not an agent record")]
+    NotAnAgent,
+    #[msg("Agent record already has the current size")]
+    AgentUpToDate,
+    #[msg("Entry fee does not match the arena's agent entry fee")]
+    InvalidEntryFee,
+    #[msg("Shares must name distinct surviving agents by descending, non-zero weight")]
+    InvalidShares,
+    #[msg("Claims stay open until the dispute window and keeper grace pass")]
+    ClaimPeriodOpen,
+    #[msg("Nothing is left in the vault to roll over")]
+    NothingToRollOver,
+    #[msg("Rollover needs a different arena and its own vault")]
+    InvalidRollover,
+    #[msg("Agent pool would exceed the bettor's max_pool_after")]
+    PoolMovedTooFar,
+    #[msg("Randomness has already been committed")]
+    RandomnessAlreadyCommitted,
+    #[msg("Randomness has already been revealed")]
+    RandomnessAlreadyRevealed,
+    #[msg("No randomness commitment to reveal")]
+    RandomnessNotCommitted,
+    #[msg("Preimage does not match the randomness commitment")]
+    InvalidPreimage,
+    #[msg("Result is not final until the dispute window closes")]
+    DisputeWindowOpen,
+    #[msg("Arena already holds a seed, escrowed bets or fees")]
+    ArenaHoldsFunds,
+    #[msg("Bet is already owned by that account")]
+    SelfTransfer,
+    #[msg("Authorized payout must equal the bet's entitlement")]
+    PayoutNotEntitlement,
+    #[msg("Agent balance already snapshotted for this epoch")]
+    SnapshotExists,
+}
+
+#[cfg(any(test, feature = "test-clock"))]
+#[account]
+#[derive(InitSpace)]
+pub struct TestClock {
+    pub unix_timestamp: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_lang::{Bumps, InstructionData};
+    use anchor_spl::token::spl_token::{
+        self,
+        state::{Account as TokenState, AccountState, Mint as MintState},
+    };
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    /// An account whose bytes are all zero: empty strings and vectors, `None`,
+    /// first enum variants and `false`
+    fn blank<T: AnchorDeserialize>(space: usize) -> T {
+        T::deserialize(&mut &vec![0u8; space][..]).unwrap()
+    }
+
+    fn blank_arena() -> Arena {
+        blank(Arena::INIT_SPACE)
+    }
+
+    /// Run `f` with a `TestClock` at `unix_timestamp` as its remaining accounts
+    fn with_clock<R>(unix_timestamp: i64, owner: Pubkey, f: impl FnOnce(&[AccountInfo]) -> R) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = Vec::new();
+        TestClock { unix_timestamp }
+            .try_serialize(&mut data)
+            .unwrap();
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        f(&[info])
+    }
+
+    fn at(unix_timestamp: i64) -> i64 {
+        with_clock(unix_timestamp, crate::ID, |accounts| now(accounts).unwrap())
+    }
+
+    #[test]
+    fn now_reads_injected_clock() {
+        assert_eq!(at(1_700_000_000), 1_700_000_000);
+    }
+
+    #[test]
+    fn now_ignores_clock_owned_by_another_program() {
+        // Off-chain the sysvar fallback is unavailable, so the foreign clock is not read
+        with_clock(42, Pubkey::new_unique(), |accounts| {
+            assert!(now(accounts).is_err());
+        });
+    }
+
+    #[test]
+    fn betting_deadline_uses_injected_clock() {
+        let mut arena = blank_arena();
+        arena.betting_closes_at = 1_000;
+
+        assert!(check_betting_open(&arena, at(999)).is_ok());
+        assert_eq!(
+            check_betting_open(&arena, at(1_000)).unwrap_err(),
+            ArenaError::BettingClosed.into()
+        );
+    }
+
+    #[test]
+    fn zero_deadline_never_closes_betting() {
+        let arena = blank_arena();
+        assert!(check_betting_open(&arena, at(i64::MAX)).is_ok());
+    }
+
+    #[test]
+    fn closed_stage_rejects_bets_before_deadline() {
+        let mut arena = blank_arena();
+        arena.betting_closes_at = 1_000;
+        arena.state = ArenaState::BettingClosed;
+
+        assert_eq!(
+            check_betting_open(&arena, at(10)).unwrap_err(),
+            ArenaError::BettingClosed.into()
+        );
+    }
+
+    /// An arena resolved at t=1000 to `winner`, with a 100s dispute window
+    fn resolved_arena(winner: &str) -> Arena {
+        let mut arena = blank_arena();
+        arena.state = ArenaState::Resolved;
+        arena.resolved_at = 1_000;
+        arena.config.dispute_window = 100;
+        arena.winner = winner.to_string();
+        arena
+    }
+
+    #[test]
+    fn result_is_not_final_inside_dispute_window() {
+        let arena = resolved_arena("alpha");
+
+        assert_eq!(
+            check_result_final(&arena, at(1_099)).unwrap_err(),
+            ArenaError::DisputeWindowOpen.into()
+        );
+        assert!(check_result_final(&arena, at(1_100)).is_ok());
+    }
+
+    #[test]
+    fn result_under_review_is_not_final() {
+        let mut arena = resolved_arena("alpha");
+        arena.under_review = true;
+
+        assert_eq!(
+            check_result_final(&arena, at(5_000)).unwrap_err(),
+            ArenaError::ResultUnderReview.into()
+        );
+    }
+
+    #[test]
+    fn fee_withdrawal_waits_for_dispute_window() {
+        let mut arena = resolved_arena("alpha");
+        arena.withdrawable_fees = 10;
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![];
+        let owner = Pubkey::new_unique();
+        let vault = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+
+        assert_eq!(
+            withdraw_all_fees(&mut arena, &vault, &vault, at(1_050)).unwrap_err(),
+            ArenaError::DisputeWindowOpen.into()
+        );
+        assert_eq!(arena.withdrawable_fees, 10);
+    }
+
+    /// A pool of `staked` lamports backing `agent_id` to survive
+    fn survive_pool(agent_id: &str, staked: u64) -> AgentPool {
+        let mut pool: AgentPool = blank(AgentPool::INIT_SPACE);
+        pool.agent_id = agent_id.to_string();
+        pool.add_stake(BetSide::Survive, staked);
+        pool
+    }
+
+    fn survive_bet(agent_id: &str, amount: u64) -> Bet {
+        let mut bet: Bet = blank(Bet::INIT_SPACE);
+        bet.agent_id = agent_id.to_string();
+        bet.amount = amount;
+        bet.gross_amount = amount;
+        bet.confirmed = true;
+        bet
+    }
+
+    #[test]
+    fn claim_pays_share_once_dispute_window_closes() {
+        let mut arena = resolved_arena("alpha");
+        arena.add_bet_volume(BetSide::Survive, 1_000);
+        let mut pool = survive_pool("alpha", 400);
+        let mut bet = survive_bet("alpha", 100);
+
+        assert_eq!(
+            settle_claim(&mut arena, &mut pool, &mut bet, at(1_050)).unwrap_err(),
+            ArenaError::DisputeWindowOpen.into()
+        );
+        assert!(!bet.claimed);
+
+        assert_eq!(
+            settle_claim(&mut arena, &mut pool, &mut bet, at(1_100)).unwrap(),
+            250
+        );
+        assert!(bet.claimed);
+        assert_eq!(pool.claimed, 250);
+        assert_eq!(arena.total_claimed, 250);
+
+        assert_eq!(
+            settle_claim(&mut arena, &mut pool, &mut bet, at(1_200)).unwrap_err(),
+            ArenaError::AlreadyClaimed.into()
+        );
+    }
+
+    #[test]
+    fn claim_rejects_losing_and_refund_mode_bets() {
+        let mut arena = resolved_arena("alpha");
+        arena.add_bet_volume(BetSide::Survive, 1_000);
+        let mut pool = survive_pool("beta", 600);
+        let mut bet = survive_bet("beta", 100);
+        assert_eq!(
+            settle_claim(&mut arena, &mut pool, &mut bet, at(2_000)).unwrap_err(),
+            ArenaError::NotWinningBet.into()
+        );
+
+        arena.state = ArenaState::Voided;
+        let mut pool = survive_pool("alpha", 400);
+        let mut bet = survive_bet("alpha", 100);
+        assert_eq!(
+            settle_claim(&mut arena, &mut pool, &mut bet, at(2_000)).unwrap_err(),
+            ArenaError::ArenaRefundOnly.into()
+        );
+    }
+
+    #[test]
+    fn payout_leaf_is_bound_to_one_bet() {
+        let (arena, bettor) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (bet, other_bet) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let leaf = payout_leaf(&arena, &bet, &bettor, 500);
+        let sibling = payout_leaf(&arena, &other_bet, &bettor, 500);
+        let (left, right) = if leaf <= sibling {
+            (leaf, sibling)
+        } else {
+            (sibling, leaf)
+        };
+        let root = hashv(&[&left, &right]).to_bytes();
+
+        assert!(verify_proof(leaf, &[sibling], root));
+        assert!(verify_proof(sibling, &[leaf], root));
+        // The same proof cannot redeem the amount against a different bet
+        let forged = payout_leaf(&arena, &Pubkey::new_unique(), &bettor, 500);
+        assert!(!verify_proof(forged, &[sibling], root));
+    }
+
+    #[test]
+    fn rollover_claim_period_has_a_floor() {
+        let mut arena = resolved_arena("alpha");
+        let floor = arena.resolved_at + MIN_CLAIM_PERIOD;
+        // The 100s dispute window alone would have opened rollover long ago
+        assert_eq!(
+            check_claim_period_over(&arena, at(floor - 1)).unwrap_err(),
+            ArenaError::ClaimPeriodOpen.into()
+        );
+        assert!(check_claim_period_over(&arena, at(floor)).is_ok());
+
+        arena.config.keeper_grace = MIN_CLAIM_PERIOD * 2;
+        assert!(check_claim_period_over(&arena, at(floor)).is_err());
+    }
+
+    #[test]
+    fn rollover_leaves_owed_lamports_behind() {
+        let rent = 1_000;
+        let mut arena = resolved_arena("alpha");
+        arena.add_bet_volume(BetSide::Survive, 1_000);
+        arena.total_claimed = 400;
+        arena.withdrawable_fees = 10;
+        arena.declaration_bond = 5;
+        arena.unconfirmed_escrow = 20;
+        // Unclaimed winnings, fees, bond and escrow, plus 50 nobody is owed
+        let lamports = rent + 600 + 10 + 5 + 20 + 50;
+
+        assert_eq!(outstanding_liabilities(&arena), 600);
+        assert_eq!(rollover_amount(&arena, lamports, rent), 50);
+        assert_eq!(rollover_amount(&arena, lamports - 50, rent), 0);
+    }
+
+    #[test]
+    fn unbacked_die_pot_is_not_owed() {
+        let mut arena = resolved_arena("alpha");
+        arena.add_bet_volume(BetSide::Survive, 1_000);
+        arena.add_bet_volume(BetSide::Die, 300);
+        arena.first_dead = "beta".to_string();
+        assert_eq!(outstanding_liabilities(&arena), 1_000);
+
+        arena.first_dead_backed = true;
+        assert_eq!(outstanding_liabilities(&arena), 1_300);
+    }
+
+    #[test]
+    fn refunded_arena_settles_with_seed_left_over() {
+        let mut arena = blank_arena();
+        arena.state = ArenaState::RefundMode;
+        arena.add_bet_volume(BetSide::Survive, 500);
+        arena.total_bets = 2;
+        arena.seeded_amount = 300;
+
+        let mut bet = survive_bet("alpha", 200);
+        assert_eq!(settle_refund(&mut arena, &mut bet).unwrap(), 200);
+        assert!(!is_settled(&arena));
+
+        let mut bet = survive_bet("beta", 300);
+        assert_eq!(settle_refund(&mut arena, &mut bet).unwrap(), 300);
+        // The seed is owed to nobody, so it is swept when the arena closes
+        assert!(is_settled(&arena));
+    }
+
+    #[test]
+    fn rounding_dust_does_not_block_settlement() {
+        let mut arena = resolved_arena("alpha");
+        arena.add_bet_volume(BetSide::Survive, 1_000);
+        arena.total_bets = 3;
+        arena.total_claimed = 998;
+        assert!(is_settled(&arena));
+
+        arena.unconfirmed_escrow = 1;
+        assert!(!is_settled(&arena));
+    }
+
+    #[test]
+    fn ties_break_on_first_bet_then_key() {
+        assert!(outranks((10, 5, b"b"), (10, 6, b"a")));
+        assert!(outranks((10, 5, b"a"), (10, 5, b"b")));
+        assert!(outranks((11, i64::MAX, b"z"), (10, 0, b"a")));
+    }
+
+    #[test]
+    fn revealed_randomness_reorders_tie_breaks() {
+        let mut arena = blank_arena();
+        assert!(!randomness_withheld(&arena));
+        assert_eq!(tiebreak_key(&arena, "alpha"), b"alpha".to_vec());
+
+        let preimage = [7u8; 32];
+        arena.randomness_commitment = Some(hashv(&[&preimage]).to_bytes());
+        assert!(randomness_withheld(&arena));
+
+        arena.randomness = Some(preimage);
+        assert!(!randomness_withheld(&arena));
+        assert_eq!(
+            tiebreak_key(&arena, "alpha"),
+            hashv(&[&preimage, b"alpha"]).to_bytes().to_vec()
+        );
+    }
+
+    // Instruction harness
+    //
+    // Runs handlers against in-memory accounts laid out the way the runtime
+    // passes them. Nothing can be invoked off-chain, so `transfer_lamports` moves
+    // balances directly and accounts behind a plain `init` are built up front and
+    // handed to `Chain::call`, which skips account validation.
+
+    /// Serves `Rent::default()` for the rent sysvar
+    struct RentStub;
+
+    impl solana_sysvar::program_stubs::SyscallStubs for RentStub {
+        fn sol_get_rent_sysvar(&self, _var_addr: *mut u8) -> u64 {
+            // `Rent::get` starts from the default and keeps it on success
+            0
+        }
+    }
+
+    /// An account key preceded by the original data length `resize` checks
+    #[repr(C)]
+    struct KeySlot {
+        original_data_len: u32,
+        key: Pubkey,
+    }
+
+    /// A leaked account in the runtime's layout: data is prefixed by its length
+    /// and followed by the spare bytes a realloc may grow into
+    fn leak_account(
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: &[u8],
+        executable: bool,
+    ) -> AccountInfo<'static> {
+        let slot = Box::leak(Box::new(KeySlot {
+            original_data_len: data.len() as u32,
+            key,
+        }));
+        let words = 1 + (data.len() + MAX_PERMITTED_DATA_INCREASE).div_ceil(8);
+        let buffer = Box::leak(vec![0u64; words].into_boxed_slice()).as_mut_ptr();
+        // SAFETY: the buffer is leaked and large enough for the length and data
+        let bytes = unsafe {
+            *buffer = data.len() as u64;
+            std::slice::from_raw_parts_mut(buffer.add(1).cast::<u8>(), data.len())
+        };
+        bytes.copy_from_slice(data);
+        AccountInfo::new(
+            &slot.key,
+            false,
+            false,
+            Box::leak(Box::new(lamports)),
+            bytes,
+            Box::leak(Box::new(owner)),
+            executable,
+            0,
+        )
+    }
+
+    fn pda(seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &crate::ID).0
+    }
+
+    /// The error the entrypoint returns for `error`
+    fn code(error: impl Into<anchor_lang::error::Error>) -> ProgramError {
+        error.into().into()
+    }
+
+    /// In-memory accounts, and the `TestClock` passed to every instruction
+    struct Chain {
+        accounts: RefCell<HashMap<Pubkey, AccountInfo<'static>>>,
+        /// Accounts `ensure` created for the next instruction
+        created: RefCell<Vec<Pubkey>>,
+        clock: Pubkey,
+    }
+
+    impl Chain {
+        fn new() -> Self {
+            static STUBS: std::sync::Once = std::sync::Once::new();
+            STUBS.call_once(|| {
+                solana_sysvar::program_stubs::set_syscall_stubs(Box::new(RentStub));
+            });
+            let chain = Chain {
+                accounts: RefCell::default(),
+                created: RefCell::default(),
+                clock: Pubkey::new_unique(),
+            };
+            // The program itself stands in for omitted optional accounts
+            for program in [System::id(), crate::ID] {
+                chain.insert(leak_account(program, Pubkey::default(), 1, &[], true));
+            }
+            chain.warp(0);
+            chain
+        }
+
+        fn insert(&self, info: AccountInfo<'static>) {
+            self.accounts.borrow_mut().insert(*info.key, info);
+        }
+
+        /// Set the time following instructions read through `now`
+        fn warp(&self, unix_timestamp: i64) {
+            self.put(self.clock, &TestClock { unix_timestamp });
+        }
+
+        /// A new system wallet holding `lamports`
+        fn wallet(&self, lamports: u64) -> Pubkey {
+            let key = Pubkey::new_unique();
+            self.insert(leak_account(key, System::id(), lamports, &[], false));
+            key
+        }
+
+        /// The account at `key`, created as an empty system account if missing
+        fn info(&self, key: Pubkey) -> AccountInfo<'static> {
+            self.accounts
+                .borrow_mut()
+                .entry(key)
+                .or_insert_with(|| leak_account(key, System::id(), 0, &[], false))
+                .clone()
+        }
+
+        fn info_as(&self, key: Pubkey, is_signer: bool, is_writable: bool) -> AccountInfo<'static> {
+            AccountInfo {
+                is_signer,
+                is_writable,
+                ..self.info(key)
+            }
+        }
+
+        fn lamports(&self, key: Pubkey) -> u64 {
+            self.info(key).lamports()
+        }
+
+        /// Whether `key` holds a live program account
+        fn exists(&self, key: Pubkey) -> bool {
+            *self.info(key).owner == crate::ID
+        }
+
+        fn get<T: AccountDeserialize>(&self, key: Pubkey) -> T {
+            T::try_deserialize(&mut &self.info(key).data.borrow()[..]).unwrap()
+        }
+
+        /// Store `state` at `key`, sized as its `init` would be, keeping any
+        /// lamports already there
+        fn put<T: AccountSerialize + Space>(&self, key: Pubkey, state: &T) {
+            let mut data = Vec::new();
+            state.try_serialize(&mut data).unwrap();
+            data.resize(8 + T::INIT_SPACE, 0);
+            let lamports = Rent::default()
+                .minimum_balance(data.len())
+                .max(self.lamports(key));
+            self.insert(leak_account(key, crate::ID, lamports, &data, false));
+        }
+
+        /// Create `key` as a zeroed, rent-exempt `T` unless it exists, as an
+        /// `init` or first `init_if_needed` would before the handler runs
+        fn ensure<T: Discriminator + Space>(&self, key: Pubkey) -> Pubkey {
+            if !self.exists(key) {
+                let mut data = T::DISCRIMINATOR.to_vec();
+                data.resize(8 + T::INIT_SPACE, 0);
+                let lamports = Rent::default().minimum_balance(data.len());
+                self.insert(leak_account(key, crate::ID, lamports, &data, false));
+                self.created.borrow_mut().push(key);
+            }
+            key
+        }
+
+        fn leak_info(&self, key: Pubkey, is_signer: bool) -> &'static AccountInfo<'static> {
+            Box::leak(Box::new(self.info_as(key, is_signer, true)))
+        }
+
+        /// `T` at `key` for a hand-built accounts struct, created by `ensure`
+        fn account<T>(&self, key: Pubkey) -> Account<'static, T>
+        where
+            T: AccountSerialize + AccountDeserialize + Owner + Discriminator + Space + Clone,
+        {
+            self.ensure::<T>(key);
+            Account::try_from(self.leak_info(key, false)).unwrap()
+        }
+
+        fn signer(&self, key: Pubkey) -> Signer<'static> {
+            Signer::try_from(self.leak_info(key, true)).unwrap()
+        }
+
+        fn system_program(&self) -> Program<'static, System> {
+            Program::try_from(self.leak_info(System::id(), false)).unwrap()
+        }
+
+        /// Instruction accounts, then `extra` remaining accounts and the clock
+        fn infos(
+            &self,
+            accounts: impl ToAccountMetas,
+            extra: &[Pubkey],
+        ) -> &'static [AccountInfo<'static>] {
+            let extra = extra.iter().map(|&key| AccountMeta::new(key, false));
+            let clock = AccountMeta::new_readonly(self.clock, false);
+            let infos: Vec<_> = accounts
+                .to_account_metas(None)
+                .into_iter()
+                .chain(extra)
+                .chain([clock])
+                .map(|meta| self.info_as(meta.pubkey, meta.is_signer, meta.is_writable))
+                .collect();
+            Box::leak(infos.into_boxed_slice())
+        }
+
+        /// Run an instruction through the program's entrypoint
+        fn send(
+            &self,
+            accounts: impl ToAccountMetas,
+            ix: impl InstructionData,
+        ) -> std::result::Result<(), ProgramError> {
+            self.send_with(accounts, ix, &[])
+        }
+
+        /// `send` with `extra` remaining accounts ahead of the clock
+        fn send_with(
+            &self,
+            accounts: impl ToAccountMetas,
+            ix: impl InstructionData,
+            extra: &[Pubkey],
+        ) -> std::result::Result<(), ProgramError> {
+            let infos = self.infos(accounts, extra);
+            self.atomic(|| crate::entry(&crate::ID, infos, &ix.data()))
+        }
+
+        /// Run `f` as one instruction: if it fails its writes are undone and the
+        /// accounts `ensure` created for it removed, as the runtime would
+        fn atomic<R, E>(
+            &self,
+            f: impl FnOnce() -> std::result::Result<R, E>,
+        ) -> std::result::Result<R, E> {
+            let snapshot: Vec<_> = self
+                .accounts
+                .borrow()
+                .values()
+                .map(|info| {
+                    let data = info.data.borrow().to_vec();
+                    (info.clone(), info.lamports(), *info.owner, data)
+                })
+                .collect();
+            let result = f();
+            let created = self.created.take();
+            if result.is_err() {
+                for (info, lamports, owner, data) in snapshot {
+                    **info.lamports.borrow_mut() = lamports;
+                    info.assign(&owner);
+                    info.resize(data.len()).unwrap();
+                    info.data.borrow_mut().copy_from_slice(&data);
+                }
+                for key in created {
+                    self.accounts.borrow_mut().remove(&key);
+                }
+            }
+            result
+        }
+
+        /// Run `handler` on hand-built `accounts` and persist them, for
+        /// instructions whose plain `init` would need a CPI
+        fn call<T, R>(
+            &self,
+            mut accounts: T,
+            bumps: T::Bumps,
+            handler: impl FnOnce(Context<'_, '_, 'static, 'static, T>) -> Result<R>,
+        ) -> Result<R>
+        where
+            T: Bumps + Accounts<'static, T::Bumps> + AccountsExit<'static>,
+        {
+            let remaining = self.infos(Vec::<AccountMeta>::new(), &[]);
+            self.atomic(|| {
+                let result = handler(Context::new(&crate::ID, &mut accounts, remaining, bumps))?;
+                accounts.exit(&crate::ID)?;
+                Ok(result)
+            })
+        }
+    }
+
+    /// An arena created by `initialize_arena`, with the wallet administering it
+    struct TestArena {
+        key: Pubkey,
+        vault: Pubkey,
+        authority: Pubkey,
+    }
+
+    impl TestArena {
+        fn pda(&self, prefix: &[u8], id: &str) -> Pubkey {
+            pda(&[prefix, self.key.as_ref(), id.as_bytes()])
+        }
+
+        fn pool(&self, agent_id: &str) -> Pubkey {
+            self.pda(b"pool", agent_id)
+        }
+
+        fn agent(&self, agent_id: &str) -> Pubkey {
+            self.pda(b"agent", agent_id)
+        }
+
+        fn profile(&self, bettor: Pubkey) -> Pubkey {
+            pda(&[b"profile", self.key.as_ref(), bettor.as_ref()])
+        }
+
+        fn state(&self, c: &Chain) -> Arena {
+            c.get(self.key)
+        }
+
+        fn claim_accounts(&self, bettor: Pubkey, agent_id: &str) -> crate::accounts::ClaimWinnings {
+            crate::accounts::ClaimWinnings {
+                bet: bet_key(bettor, agent_id),
+                arena: self.key,
+                vault: self.vault,
+                pool: self.pool(agent_id),
+                profile: self.profile(bettor),
+                bettor,
+                death: self.pda(b"death", agent_id),
+                agent: self.agent(agent_id),
+                career: None,
+            }
+        }
+    }
+
+    /// A plain alpha -> beta payment for `Chain::log_transaction`
+    fn payment(
+        transaction_id: &str,
+        amount: u64,
+        hour_index: i64,
+    ) -> crate::instruction::LogTransaction {
+        crate::instruction::LogTransaction {
+            transaction_id: transaction_id.to_string(),
+            from_agent: "alpha".to_string(),
+            to_agent: "beta".to_string(),
+            amount,
+            service_type: "compute".to_string(),
+            hour_index,
+            memo: None,
+            external_ref: None,
+        }
+    }
+
+    fn bet_key(bettor: Pubkey, agent_id: &str) -> Pubkey {
+        pda(&[b"bet", bettor.as_ref(), agent_id.as_bytes()])
+    }
+
+    /// The career identity `Chain::register` gives `agent_id` in every arena
+    fn identity(agent_id: &str) -> Pubkey {
+        Pubkey::new_from_array(hashv(&[agent_id.as_bytes()]).to_bytes())
+    }
+
+    fn career_key(agent_id: &str) -> Pubkey {
+        pda(&[b"career", identity(agent_id).as_ref()])
+    }
+
+    impl Chain {
+        fn open_arena(&self, arena_id: &str) -> TestArena {
+            let authority = self.wallet(100 * LAMPORTS_PER_SOL);
+            let key = pda(&[b"arena", arena_id.as_bytes()]);
+            let vault = pda(&[b"vault", key.as_ref()]);
+            let accounts = InitializeArena {
+                arena: self.account(key),
+                vault: self.account(vault),
+                authority: self.signer(authority),
+                system_program: self.system_program(),
+            };
+            self.call(accounts, Default::default(), |ctx| {
+                arena_logger::initialize_arena(ctx, arena_id.to_string(), Pubkey::new_unique())
+            })
+            .unwrap();
+            TestArena {
+                key,
+                vault,
+                authority,
+            }
+        }
+
+        fn register(&self, arena: &TestArena, agent_id: &str, balance: u64) -> Result<()> {
+            let accounts = RegisterAgent {
+                arena: self.account(arena.key),
+                agent: self.account(arena.agent(agent_id)),
+                seen: self.account(arena.pda(b"seen", agent_id)),
+                vault: self.account(arena.vault),
+                authority: self.signer(arena.authority),
+                system_program: self.system_program(),
+            };
+            let fee = arena.state(self).config.agent_entry_fee;
+            self.call(accounts, Default::default(), |ctx| {
+                arena_logger::register_agent(
+                    ctx,
+                    agent_id.to_string(),
+                    agent_id.to_uppercase(),
+                    balance,
+                    fee,
+                    identity(agent_id),
+                )
+            })
+        }
+
+        fn configure(
+            &self,
+            arena: &TestArena,
+            update: ArenaConfigUpdate,
+        ) -> std::result::Result<(), ProgramError> {
+            self.send(
+                crate::accounts::ConfigureArena {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::ConfigureArena { update },
+            )
+        }
+
+        fn set_flag(
+            &self,
+            arena: &TestArena,
+            flag: u8,
+            value: bool,
+        ) -> std::result::Result<(), ProgramError> {
+            self.send(
+                crate::accounts::SetFlag {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::SetFlag { flag, value },
+            )
+        }
+
+        fn place_bet_accounts(
+            &self,
+            arena: &TestArena,
+            bettor: Pubkey,
+            agent_id: &str,
+        ) -> crate::accounts::PlaceBet {
+            let agent = arena.agent(agent_id);
+            crate::accounts::PlaceBet {
+                bet: self.ensure::<Bet>(bet_key(bettor, agent_id)),
+                arena: arena.key,
+                vault: arena.vault,
+                pool: self.ensure::<AgentPool>(arena.pool(agent_id)),
+                agent: self.exists(agent).then_some(agent),
+                profile: self.ensure::<UserProfile>(arena.profile(bettor)),
+                blacklisted: pda(&[b"blacklist", arena.key.as_ref(), bettor.as_ref()]),
+                self_exclusion: pda(&[b"self-exclusion", bettor.as_ref()]),
+                bettor,
+                system_program: System::id(),
+            }
+        }
+
+        fn bet(
+            &self,
+            arena: &TestArena,
+            bettor: Pubkey,
+            agent_id: &str,
+            amount: u64,
+            bet_side: BetSide,
+        ) -> std::result::Result<(), ProgramError> {
+            self.send(
+                self.place_bet_accounts(arena, bettor, agent_id),
+                crate::instruction::PlaceBet {
+                    agent_id: agent_id.to_string(),
+                    amount,
+                    bet_side,
+                    predicted_placement: 0,
+                },
+            )
+        }
+
+        /// Accounts for the authority to declare `winner`, crediting no career
+        fn declare_accounts(
+            &self,
+            arena: &TestArena,
+            winner: &str,
+        ) -> crate::accounts::DeclareWinner {
+            let agent = arena.agent(winner);
+            crate::accounts::DeclareWinner {
+                arena: arena.key,
+                vault: arena.vault,
+                resolver: arena.authority,
+                winner_pool: arena.pool(winner),
+                system_program: System::id(),
+                winner_agent: self.exists(agent).then_some(agent),
+                career: None,
+            }
+        }
+
+        fn declare(
+            &self,
+            arena: &TestArena,
+            winner: &str,
+        ) -> std::result::Result<(), ProgramError> {
+            self.send(
+                self.declare_accounts(arena, winner),
+                crate::instruction::DeclareWinner {
+                    agent_id: winner.to_string(),
+                    sol_usd_price: 0,
+                },
+            )
+        }
+
+        fn log_transaction(
+            &self,
+            arena: &TestArena,
+            ix: crate::instruction::LogTransaction,
+        ) -> std::result::Result<(), ProgramError> {
+            let (from, to) = (arena.agent(&ix.from_agent), arena.agent(&ix.to_agent));
+            let accounts = crate::accounts::LogTransaction {
+                transaction: self
+                    .ensure::<Transaction>(pda(&[b"transaction", ix.transaction_id.as_bytes()])),
+                arena: arena.key,
+                from_seen: self.ensure::<AgentSeen>(arena.pda(b"seen", &ix.from_agent)),
+                to_seen: self.ensure::<AgentSeen>(arena.pda(b"seen", &ix.to_agent)),
+                bucket: self.ensure::<VolumeBucket>(pda(&[
+                    b"bucket",
+                    arena.key.as_ref(),
+                    &ix.hour_index.to_le_bytes(),
+                ])),
+                service_fee: arena.pda(b"service-fee", &ix.service_type),
+                service_stats: self
+                    .ensure::<ServiceStats>(arena.pda(b"service-stats", &ix.service_type)),
+                authority: arena.authority,
+                system_program: System::id(),
+                from_agent_account: self.exists(from).then_some(from),
+                to_agent_account: self.exists(to).then_some(to),
+            };
+            self.send(accounts, ix)
+        }
+
+        /// A new token mint with `decimals`
+        fn mint(&self, decimals: u8) -> Pubkey {
+            self.insert(leak_account(spl_token::ID, Pubkey::default(), 1, &[], true));
+            let mint = Pubkey::new_unique();
+            let mut data = [0; MintState::LEN];
+            MintState::pack(
+                MintState {
+                    decimals,
+                    is_initialized: true,
+                    ..Default::default()
+                },
+                &mut data,
+            )
+            .unwrap();
+            self.insert(leak_account(mint, spl_token::ID, 1, &data, false));
+            mint
+        }
+
+        /// `set_bet_mint`, with the token vault its `init` would create
+        fn set_bet_mint(&self, arena: &TestArena, mint: Pubkey, decimals: u8) -> Result<()> {
+            let token_vault = pda(&[b"token-vault", arena.key.as_ref()]);
+            self.token_account(token_vault, mint, arena.key, 0);
+            let accounts = SetBetMint {
+                arena: self.account(arena.key),
+                bet_mint: InterfaceAccount::try_from(self.leak_info(mint, false)).unwrap(),
+                token_vault: InterfaceAccount::try_from(self.leak_info(token_vault, false))
+                    .unwrap(),
+                authority: self.signer(arena.authority),
+                token_program: Interface::try_from(self.leak_info(spl_token::ID, false)).unwrap(),
+                system_program: self.system_program(),
+            };
+            self.call(accounts, Default::default(), |ctx| {
+                arena_logger::set_bet_mint(ctx, decimals)
+            })
+        }
+
+        /// Turn `arena` into a token arena betting a new mint with `decimals`
+        fn use_bet_mint(&self, arena: &TestArena, decimals: u8) -> Pubkey {
+            let mint = self.mint(decimals);
+            self.set_bet_mint(arena, mint, decimals).unwrap();
+            mint
+        }
+
+        /// Create a token account at `key` holding `amount` of `mint`
+        fn token_account(&self, key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+            let mut data = [0; TokenState::LEN];
+            TokenState::pack(
+                TokenState {
+                    mint,
+                    owner,
+                    amount,
+                    state: AccountState::Initialized,
+                    ..Default::default()
+                },
+                &mut data,
+            )
+            .unwrap();
+            self.insert(leak_account(key, spl_token::ID, 1, &data, false));
+            key
+        }
+
+        fn token_balance(&self, key: Pubkey) -> u64 {
+            TokenState::unpack(&self.info(key).try_borrow_data().unwrap())
+                .unwrap()
+                .amount
+        }
+
+        fn preview(&self, arena: &TestArena, bettor: Pubkey, agent_id: &str) -> ClaimPreview {
+            let accounts = PreviewClaim {
+                bet: self.account(bet_key(bettor, agent_id)),
+                arena: self.account(arena.key),
+                pool: self.account(arena.pool(agent_id)),
+            };
+            self.call(accounts, Default::default(), arena_logger::preview_claim)
+                .unwrap()
+        }
+
+        /// Log `agent_id`'s death with a record, as a non-trivial death
+        fn kill(&self, arena: &TestArena, agent_id: &str) -> Result<()> {
+            let accounts = LogDeath {
+                death: Some(self.account(arena.pda(b"death", agent_id))),
+                arena: self.account(arena.key),
+                pool: self.account(arena.pool(agent_id)),
+                agent: self.account(arena.agent(agent_id)),
+                authority: self.signer(arena.authority),
+                system_program: self.system_program(),
+                career: None,
+            };
+            self.call(accounts, Default::default(), |ctx| {
+                arena_logger::log_death(ctx, agent_id.to_string(), agent_id.to_uppercase(), 0, 0)
+            })
+        }
+
+        fn authorize_payout(
+            &self,
+            arena: &TestArena,
+            bettor: Pubkey,
+            agent_id: &str,
+            amount: u64,
+        ) -> Result<()> {
+            let bet = bet_key(bettor, agent_id);
+            let accounts = AuthorizePayout {
+                arena: self.account(arena.key),
+                bet: self.account(bet),
+                pool: self.account(arena.pool(agent_id)),
+                authorization: self.account(pda(&[b"payout-auth", bet.as_ref()])),
+                authority: self.signer(arena.authority),
+                system_program: self.system_program(),
+            };
+            self.call(accounts, Default::default(), |ctx| {
+                arena_logger::authorize_payout(ctx, bettor, amount)
+            })
+        }
+    }
+
+    #[test]
+    fn authorized_payout_pays_the_full_entitlement() {
+        let c = Chain::new();
+        let arena = c.open_arena("authorized");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        let (winner, loser) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, winner, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, loser, "beta", 100, BetSide::Survive).unwrap();
+        c.declare(&arena, "alpha").unwrap();
+
+        // Holding part of the entitlement back would strand it in the vault
+        assert_eq!(
+            c.authorize_payout(&arena, winner, "alpha", 399)
+                .unwrap_err(),
+            ArenaError::PayoutNotEntitlement.into()
+        );
+        c.authorize_payout(&arena, winner, "alpha", 400).unwrap();
+
+        let authorization = pda(&[b"payout-auth", bet_key(winner, "alpha").as_ref()]);
+        let before = c.lamports(winner);
+        c.send(
+            crate::accounts::ExecuteAuthorizedPayout {
+                authorization,
+                bet: bet_key(winner, "alpha"),
+                arena: arena.key,
+                vault: arena.vault,
+                pool: arena.pool("alpha"),
+                profile: arena.profile(winner),
+                bettor: winner,
+                authority: arena.authority,
+            },
+            crate::instruction::ExecuteAuthorizedPayout {},
+        )
+        .unwrap();
+
+        assert_eq!(c.lamports(winner), before + 400);
+        assert!(c.get::<Bet>(bet_key(winner, "alpha")).claimed);
+        let state = arena.state(&c);
+        assert_eq!(state.total_claimed, 400);
+        assert_eq!(state.withdrawable_fees, 0);
+        assert!(!c.exists(authorization));
+    }
+
+    #[test]
+    fn keeper_settles_a_winning_bet_for_a_capped_reward() {
+        let c = Chain::new();
+        let arena = c.open_arena("keeper");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        for update in [
+            ArenaConfigUpdate {
+                keeper_reward_bps: Some(MAX_KEEPER_REWARD_BPS + 1),
+                ..Default::default()
+            },
+            ArenaConfigUpdate {
+                keeper_grace: Some(0),
+                ..Default::default()
+            },
+        ] {
+            assert_eq!(
+                c.configure(&arena, update).unwrap_err(),
+                code(ArenaError::InvalidConfig)
+            );
+        }
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                keeper_reward_bps: Some(MAX_KEEPER_REWARD_BPS),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (winner, loser) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, winner, "alpha", 600, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, loser, "beta", 400, BetSide::Survive).unwrap();
+        c.declare(&arena, "alpha").unwrap();
+
+        let keeper = c.wallet(LAMPORTS_PER_SOL);
+        let bet = bet_key(winner, "alpha");
+        let settle = || {
+            c.send(
+                crate::accounts::KeeperSettle {
+                    bet,
+                    arena: arena.key,
+                    vault: arena.vault,
+                    pool: arena.pool("alpha"),
+                    profile: arena.profile(winner),
+                    bettor: winner,
+                    keeper,
+                },
+                crate::instruction::KeeperSettle {},
+            )
+        };
+        // The unconfigured grace still gives the winner a day to claim in full
+        c.warp(MIN_KEEPER_GRACE - 1);
+        assert_eq!(settle().unwrap_err(), code(ArenaError::KeeperGracePeriod));
+
+        c.warp(MIN_KEEPER_GRACE);
+        let bet_rent = c.lamports(bet);
+        let (winner_before, keeper_before) = (c.lamports(winner), c.lamports(keeper));
+        settle().unwrap();
+
+        assert_eq!(c.lamports(keeper), keeper_before + 50);
+        assert_eq!(c.lamports(winner), winner_before + 950 + bet_rent);
+        assert!(!c.exists(bet));
+        assert_eq!(c.get::<UserProfile>(arena.profile(winner)).total_won, 950);
+    }
+
+    #[test]
+    fn career_accumulates_results_across_arenas() {
+        let c = Chain::new();
+        let accounts = InitCareer {
+            career: c.account(career_key("alpha")),
+            payer: c.signer(c.wallet(LAMPORTS_PER_SOL)),
+            system_program: c.system_program(),
+        };
+        c.call(accounts, Default::default(), |ctx| {
+            arena_logger::init_career(ctx, identity("alpha"))
+        })
+        .unwrap();
+
+        // A plain win, with the career credited on claim
+        let first = c.open_arena("career-1");
+        c.register(&first, "alpha", 100).unwrap();
+        c.register(&first, "beta", 100).unwrap();
+        let (backer, rival) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&first, backer, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        c.bet(&first, rival, "beta", 100, BetSide::Survive).unwrap();
+        // Only the winner's own career can be credited
+        let mut declare = c.declare_accounts(&first, "beta");
+        declare.career = Some(career_key("alpha"));
+        assert_eq!(
+            c.send(
+                declare,
+                crate::instruction::DeclareWinner {
+                    agent_id: "beta".to_string(),
+                    sol_usd_price: 0,
+                },
+            )
+            .unwrap_err(),
+            code(ArenaError::Unauthorized)
+        );
+        let mut declare = c.declare_accounts(&first, "alpha");
+        declare.career = Some(career_key("alpha"));
+        c.send(
+            declare,
+            crate::instruction::DeclareWinner {
+                agent_id: "alpha".to_string(),
+                sol_usd_price: 0,
+            },
+        )
+        .unwrap();
+        let mut claim = first.claim_accounts(backer, "alpha");
+        claim.career = Some(career_key("alpha"));
+        c.send(claim, crate::instruction::ClaimWinnings {}).unwrap();
+
+        // A two-sided result credits the survivor's career the same way
+        let second = c.open_arena("career-2");
+        c.register(&second, "alpha", 100).unwrap();
+        c.register(&second, "gamma", 100).unwrap();
+        c.bet(&second, backer, "alpha", 200, BetSide::Survive)
+            .unwrap();
+        c.bet(&second, rival, "gamma", 50, BetSide::Die).unwrap();
+        let mut declare = c.declare_accounts(&second, "alpha");
+        declare.career = Some(career_key("alpha"));
+        c.send_with(
+            declare,
+            crate::instruction::ResolveTwoSided {
+                surviving_agent: "alpha".to_string(),
+                first_dead_agent: "gamma".to_string(),
+                sol_usd_price: 0,
+            },
+            &[second.pool("gamma")],
+        )
+        .unwrap();
+        assert_eq!(second.state(&c).winner_career, Some(career_key("alpha")));
+
+        let career: AgentCareer = c.get(career_key("alpha"));
+        assert_eq!(career.wins, 2);
+        assert_eq!(career.losses, 0);
+        assert_eq!(career.total_backed, 500);
+        assert_eq!(career.total_paid_to_backers, 400);
+    }
+
+    #[test]
+    fn repeat_place_bet_tops_up_one_position() {
+        let c = Chain::new();
+        let arena = c.open_arena("top-up");
+        c.register(&arena, "alpha", 100).unwrap();
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, bettor, "alpha", 200, BetSide::Survive)
+            .unwrap();
+        // The position cannot switch markets
+        assert_eq!(
+            c.bet(&arena, bettor, "alpha", 100, BetSide::Die)
+                .unwrap_err(),
+            code(ArenaError::BetMismatch)
+        );
+
+        let bet: Bet = c.get(bet_key(bettor, "alpha"));
+        assert_eq!((bet.amount, bet.gross_amount), (500, 500));
+        let pool: AgentPool = c.get(arena.pool("alpha"));
+        assert_eq!((pool.total_staked, pool.bet_count), (500, 1));
+        let profile: UserProfile = c.get(arena.profile(bettor));
+        assert_eq!((profile.total_staked, profile.bet_count), (500, 1));
+        let state = arena.state(&c);
+        assert_eq!((state.total_bet_volume, state.total_bets), (500, 1));
+        assert_eq!(state.unique_bettors, 1);
+        assert_eq!(
+            c.lamports(arena.vault),
+            Rent::default().minimum_balance(8 + Vault::INIT_SPACE) + 500
+        );
+    }
+
+    #[test]
+    fn only_the_new_owner_claims_a_transferred_bet() {
+        let c = Chain::new();
+        let arena = c.open_arena("transfer");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        let (seller, buyer, rival) = (
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+        );
+        c.bet(&arena, seller, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, rival, "beta", 100, BetSide::Survive).unwrap();
+
+        let bet = bet_key(seller, "alpha");
+        let transfer = || {
+            c.send(
+                crate::accounts::TransferBet {
+                    bet,
+                    arena: arena.key,
+                    pool: arena.pool("alpha"),
+                    from_profile: arena.profile(seller),
+                    to_profile: c.ensure::<UserProfile>(arena.profile(buyer)),
+                    bettor: seller,
+                    buyer: Some(buyer),
+                    system_program: System::id(),
+                },
+                crate::instruction::TransferBet {
+                    new_owner: buyer,
+                    price: 50,
+                },
+            )
+        };
+        let toggle = |open: bool| {
+            let accounts = crate::accounts::CloseBetting {
+                arena: arena.key,
+                authority: arena.authority,
+            };
+            if open {
+                c.send(
+                    accounts,
+                    crate::instruction::ReopenBetting { new_deadline: 0 },
+                )
+            } else {
+                c.send(accounts, crate::instruction::CloseBetting {})
+            }
+        };
+        // Positions stop trading with the market
+        toggle(false).unwrap();
+        assert_eq!(transfer().unwrap_err(), code(ArenaError::BettingClosed));
+        toggle(true).unwrap();
+        let seller_before = c.lamports(seller);
+        transfer().unwrap();
+        assert_eq!(c.lamports(seller), seller_before + 50);
+        assert_eq!(c.get::<Bet>(bet).bettor, buyer);
+
+        // The seller can neither top the sold position up nor claim it
+        assert_eq!(
+            c.bet(&arena, seller, "alpha", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::BetMismatch)
+        );
+        c.declare(&arena, "alpha").unwrap();
+        assert_eq!(
+            c.send(
+                arena.claim_accounts(seller, "alpha"),
+                crate::instruction::ClaimWinnings {}
+            )
+            .unwrap_err(),
+            code(anchor_lang::error::ErrorCode::ConstraintHasOne)
+        );
+
+        let mut claim = arena.claim_accounts(buyer, "alpha");
+        claim.bet = bet;
+        let (buyer_before, bet_rent) = (c.lamports(buyer), c.lamports(bet));
+        c.send(claim, crate::instruction::ClaimWinnings {}).unwrap();
+        assert_eq!(c.lamports(buyer), buyer_before + 400 + bet_rent);
+        assert_eq!(c.get::<UserProfile>(arena.profile(buyer)).total_won, 400);
+        assert_eq!(c.get::<UserProfile>(arena.profile(seller)).total_staked, 0);
+    }
+
+    #[test]
+    fn deaths_are_recorded_per_arena() {
+        let c = Chain::new();
+        let (first, second) = (c.open_arena("deaths-1"), c.open_arena("deaths-2"));
+        for arena in [&first, &second] {
+            c.register(arena, "alpha", 100).unwrap();
+            c.register(arena, "beta", 100).unwrap();
+        }
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&first, bettor, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        // The same agent id dies in both arenas without the records colliding
+        c.warp(60);
+        c.kill(&first, "alpha").unwrap();
+        c.kill(&second, "alpha").unwrap();
+        for arena in [&first, &second] {
+            let death: AgentDeath = c.get(arena.pda(b"death", "alpha"));
+            assert_eq!((death.arena, death.placement), (arena.key, 2));
+        }
+        assert_eq!(
+            c.kill(&first, "alpha").unwrap_err(),
+            ArenaError::AgentAlreadyDead.into()
+        );
+
+        // No new stake backs the dead agent, nor tops up an existing position
+        assert_eq!(
+            c.bet(&first, bettor, "alpha", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::PoolFrozen)
+        );
+        assert_eq!(
+            c.bet(
+                &second,
+                c.wallet(LAMPORTS_PER_SOL),
+                "alpha",
+                100,
+                BetSide::Survive
+            )
+            .unwrap_err(),
+            code(ArenaError::PoolFrozen)
+        );
+        c.bet(&first, bettor, "beta", 100, BetSide::Survive)
+            .unwrap();
+        assert_eq!(c.get::<AgentPool>(first.pool("alpha")).total_staked, 300);
+    }
+
+    #[test]
+    fn placement_bonus_pays_only_correct_predictions() {
+        let c = Chain::new();
+        let arena = c.open_arena("placement");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                fee_bps: Some(1_000),
+                placement_bonus_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (right, wrong, loser) = (
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+        );
+        for (bettor, predicted_placement) in [(right, 1), (wrong, 2)] {
+            c.send(
+                c.place_bet_accounts(&arena, bettor, "alpha"),
+                crate::instruction::PlaceBet {
+                    agent_id: "alpha".to_string(),
+                    amount: 300,
+                    bet_side: BetSide::Survive,
+                    predicted_placement,
+                },
+            )
+            .unwrap();
+        }
+        c.bet(&arena, loser, "beta", 400, BetSide::Survive).unwrap();
+        c.declare(&arena, "alpha").unwrap();
+        let fees = arena.state(&c).withdrawable_fees;
+
+        let mut won = Vec::new();
+        for bettor in [right, wrong] {
+            c.send(
+                arena.claim_accounts(bettor, "alpha"),
+                crate::instruction::ClaimWinnings {},
+            )
+            .unwrap();
+            won.push(c.get::<UserProfile>(arena.profile(bettor)).total_won);
+        }
+        // The winner places first; the bonus comes out of fees, not the pool
+        assert_eq!(won, [495, 450]);
+        assert_eq!(arena.state(&c).withdrawable_fees, fees - 45);
+    }
+
+    #[test]
+    fn signer_set_approves_fee_payouts() {
+        let c = Chain::new();
+        let signers = [
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+        ];
+        let (partner, treasury) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        let setup = |arena_id: &str, recipients: Vec<FeeRecipient>| {
+            let arena = c.open_arena(arena_id);
+            c.register(&arena, "alpha", 100).unwrap();
+            c.register(&arena, "beta", 100).unwrap();
+            c.configure(
+                &arena,
+                ArenaConfigUpdate {
+                    fee_bps: Some(1_000),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                "alpha",
+                600,
+                BetSide::Survive,
+            )
+            .unwrap();
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                "beta",
+                400,
+                BetSide::Survive,
+            )
+            .unwrap();
+            c.declare(&arena, "alpha").unwrap();
+            c.send(
+                crate::accounts::SetFeeRecipients {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::SetFeeRecipients { recipients },
+            )
+            .unwrap();
+            c.send(
+                crate::accounts::SetSigners {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::SetSigners {
+                    signers: signers.to_vec(),
+                    threshold: 2,
+                },
+            )
+            .unwrap();
+            arena
+        };
+        // Proposes `action` as the first signer and returns its pending account
+        let propose = |arena: &TestArena, action: ArenaAction| {
+            let pending = pda(&[b"pending", arena.key.as_ref(), &0u64.to_le_bytes()]);
+            let accounts = ProposeAction {
+                arena: c.account(arena.key),
+                pending_action: c.account(pending),
+                proposer: c.signer(signers[0]),
+                system_program: c.system_program(),
+            };
+            c.call(accounts, Default::default(), |ctx| {
+                arena_logger::propose_action(ctx, action)
+            })
+            .unwrap();
+            pending
+        };
+        let approve = |arena: &TestArena, pending, approver| {
+            c.send(
+                crate::accounts::ApproveAction {
+                    arena: arena.key,
+                    pending_action: pending,
+                    approver,
+                },
+                crate::instruction::ApproveAction {},
+            )
+        };
+        let execute = |arena: &TestArena, pending, destination, recipients: &[Pubkey]| {
+            c.send_with(
+                crate::accounts::ExecuteAction {
+                    arena: arena.key,
+                    vault: arena.vault,
+                    pending_action: pending,
+                    destination,
+                },
+                crate::instruction::ExecuteAction {},
+                recipients,
+            )
+        };
+
+        // A 2-of-3 withdrawal to the treasury
+        let arena = setup("multisig-withdraw", Vec::new());
+        assert_eq!(
+            c.send(
+                crate::accounts::WithdrawFees {
+                    arena: arena.key,
+                    vault: arena.vault,
+                    authority: arena.authority,
+                },
+                crate::instruction::WithdrawFees {},
+            )
+            .unwrap_err(),
+            code(ArenaError::MultisigRequired)
+        );
+        let pending = propose(
+            &arena,
+            ArenaAction::WithdrawFees {
+                recipient: treasury,
+            },
+        );
+        assert_eq!(
+            execute(&arena, pending, treasury, &[]).unwrap_err(),
+            code(ArenaError::NotEnoughApprovals)
+        );
+        approve(&arena, pending, signers[2]).unwrap();
+        let before = c.lamports(treasury);
+        execute(&arena, pending, treasury, &[]).unwrap();
+        assert_eq!(c.lamports(treasury), before + 100);
+        assert!(c.get::<PendingAction>(pending).executed);
+
+        // A fee split can no longer be paid out without the signer set either
+        let arena = setup(
+            "multisig-split",
+            vec![
+                FeeRecipient {
+                    recipient: partner,
+                    bps: 7_000,
+                },
+                FeeRecipient {
+                    recipient: treasury,
+                    bps: 3_000,
+                },
+            ],
+        );
+        assert_eq!(
+            c.send_with(
+                crate::accounts::DistributeFees {
+                    arena: arena.key,
+                    vault: arena.vault,
+                },
+                crate::instruction::DistributeFees {},
+                &[partner, treasury],
+            )
+            .unwrap_err(),
+            code(ArenaError::MultisigRequired)
+        );
+        let pending = propose(&arena, ArenaAction::DistributeFees);
+        approve(&arena, pending, signers[1]).unwrap();
+        let before = (c.lamports(partner), c.lamports(treasury));
+        execute(&arena, pending, partner, &[partner, treasury]).unwrap();
+        assert_eq!(
+            (c.lamports(partner), c.lamports(treasury)),
+            (before.0 + 70, before.1 + 30)
+        );
+        assert_eq!(arena.state(&c).withdrawable_fees, 0);
+    }
+
+    #[test]
+    fn sponsorship_stays_out_of_balances_and_pools() {
+        let c = Chain::new();
+        let arena = c.open_arena("sponsor");
+        c.register(&arena, "alpha", 100).unwrap();
+        let agent_vault = pda(&[b"agent-vault", arena.agent("alpha").as_ref()]);
+        c.send(
+            crate::accounts::SponsorAgent {
+                arena: arena.key,
+                agent: arena.agent("alpha"),
+                agent_vault: c.ensure::<AgentVault>(agent_vault),
+                sponsor: c.wallet(LAMPORTS_PER_SOL),
+                system_program: System::id(),
+            },
+            crate::instruction::SponsorAgent {
+                agent_id: "alpha".to_string(),
+                amount: 500,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(c.get::<AgentVault>(agent_vault).sponsored, 500);
+        assert_eq!(c.get::<Agent>(arena.agent("alpha")).balance, 100);
+        let state = arena.state(&c);
+        assert_eq!((state.total_balance_sum, state.total_bet_volume), (100, 0));
+        assert!(!c.exists(arena.pool("alpha")));
+
+        let withdraw = |authority, amount| {
+            c.send(
+                crate::accounts::WithdrawSponsorship {
+                    arena: arena.key,
+                    agent: arena.agent("alpha"),
+                    agent_vault,
+                    authority,
+                },
+                crate::instruction::WithdrawSponsorship {
+                    agent_id: "alpha".to_string(),
+                    amount,
+                },
+            )
+        };
+        assert_eq!(
+            withdraw(c.wallet(LAMPORTS_PER_SOL), 500).unwrap_err(),
+            code(ArenaError::Unauthorized)
+        );
+        // The vault's rent is not sponsorship
+        assert_eq!(
+            withdraw(arena.authority, 501).unwrap_err(),
+            code(ArenaError::InvalidSponsorAmount)
+        );
+        let before = c.lamports(arena.authority);
+        withdraw(arena.authority, 500).unwrap();
+        assert_eq!(c.lamports(arena.authority), before + 500);
+        assert_eq!(
+            c.lamports(agent_vault),
+            Rent::default().minimum_balance(8 + AgentVault::INIT_SPACE)
+        );
+    }
+
+    #[test]
+    fn transactions_fill_hourly_buckets() {
+        let c = Chain::new();
+        let arena = c.open_arena("buckets");
+        let bucket = |hour_index: i64| {
+            c.get::<VolumeBucket>(pda(&[
+                b"bucket",
+                arena.key.as_ref(),
+                &hour_index.to_le_bytes(),
+            ]))
+        };
+        c.warp(5 * BUCKET_SECONDS + 10);
+        c.log_transaction(&arena, payment("tx-1", 100, 5)).unwrap();
+        c.warp(6 * BUCKET_SECONDS - 10);
+        c.log_transaction(&arena, payment("tx-2", 200, 5)).unwrap();
+        // A logger whose clock has already ticked over is a bucket ahead
+        c.log_transaction(&arena, payment("tx-3", 400, 6)).unwrap();
+        assert_eq!(
+            c.log_transaction(&arena, payment("tx-4", 800, 7))
+                .unwrap_err(),
+            code(ArenaError::InvalidHourIndex)
+        );
+
+        let (first, second) = (bucket(5), bucket(6));
+        assert_eq!(
+            (first.hour_index, first.volume, first.transaction_count),
+            (5, 300, 2)
+        );
+        assert_eq!(
+            (second.hour_index, second.volume, second.transaction_count),
+            (6, 400, 1)
+        );
+        assert!(!c.exists(pda(&[b"bucket", arena.key.as_ref(), &7i64.to_le_bytes()])));
+        assert_eq!(arena.state(&c).total_volume, 700);
+    }
+
+    #[test]
+    fn relogging_a_transaction_is_idempotent() {
+        let c = Chain::new();
+        let arena = c.open_arena("idempotent");
+        c.log_transaction(&arena, payment("tx-1", 100, 0)).unwrap();
+        let before = c.lamports(arena.authority);
+        c.log_transaction(&arena, payment("tx-1", 100, 0)).unwrap();
+        assert_eq!(c.lamports(arena.authority), before);
+
+        // Retried in the next hour, a re-log would open a bucket the original never used
+        for conflicting in [payment("tx-1", 101, 0), payment("tx-1", 100, 1)] {
+            assert_eq!(
+                c.log_transaction(&arena, conflicting).unwrap_err(),
+                code(ArenaError::DuplicateTransaction)
+            );
+        }
+        assert!(!c.exists(pda(&[b"bucket", arena.key.as_ref(), &1i64.to_le_bytes()])));
+
+        let state = arena.state(&c);
+        assert_eq!((state.total_transactions, state.total_volume), (1, 100));
+        let bucket: VolumeBucket =
+            c.get(pda(&[b"bucket", arena.key.as_ref(), &0i64.to_le_bytes()]));
+        assert_eq!((bucket.volume, bucket.transaction_count), (100, 1));
+    }
+
+    #[test]
+    fn merging_agents_combines_their_totals() {
+        let c = Chain::new();
+        let arena = c.open_arena("merge");
+        for (agent_id, balance) in [("alpha", 100), ("beta", 300), ("gamma", 200)] {
+            c.register(&arena, agent_id, balance).unwrap();
+        }
+        c.log_transaction(&arena, payment("tx-1", 50, 0)).unwrap();
+        c.log_transaction(&arena, payment("tx-2", 50, 0)).unwrap();
+        let merge = |keep_id: &str, merge_id: &str| {
+            c.send(
+                crate::accounts::MergeAgents {
+                    arena: arena.key,
+                    keep: arena.agent(keep_id),
+                    merged: arena.agent(merge_id),
+                    merge_pool: arena.pool(merge_id),
+                    authority: arena.authority,
+                },
+                crate::instruction::MergeAgents {
+                    keep_id: keep_id.to_string(),
+                    merge_id: merge_id.to_string(),
+                },
+            )
+        };
+        merge("beta", "alpha").unwrap();
+
+        let kept: Agent = c.get(arena.agent("beta"));
+        assert_eq!(
+            (kept.balance, kept.tx_count, kept.services_completed),
+            (400, 2, 2)
+        );
+        assert!(!c.exists(arena.agent("alpha")));
+        let state = arena.state(&c);
+        assert_eq!((state.alive_agents, state.total_balance_sum), (2, 600));
+        assert_eq!(state.avg_balance, 300);
+
+        // A dead agent's balance already left the alive sum
+        c.warp(60);
+        c.kill(&arena, "gamma").unwrap();
+        assert_eq!(
+            merge("beta", "gamma").unwrap_err(),
+            code(ArenaError::AgentAlreadyDead)
+        );
+        assert_eq!(arena.state(&c).avg_balance, 400);
+    }
+
+    #[test]
+    fn token_bets_follow_the_sol_rules_through_to_the_claim() {
+        let c = Chain::new();
+        let arena = c.open_arena("tokens");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                confirm_threshold: Some(500),
+                confirm_timeout: Some(60),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mint = c.use_bet_mint(&arena, 6);
+        let token_vault = pda(&[b"token-vault", arena.key.as_ref()]);
+        let holder = |lamports| {
+            let wallet = c.wallet(lamports);
+            (
+                wallet,
+                c.token_account(Pubkey::new_unique(), mint, wallet, 1_000),
+            )
+        };
+        let (winner, winner_token) = holder(LAMPORTS_PER_SOL);
+        let (whale, whale_token) = holder(LAMPORTS_PER_SOL);
+        let (loser, loser_token) = holder(LAMPORTS_PER_SOL);
+        let bet = |bettor, bettor_token, agent_id: &str, amount| {
+            let agent = arena.agent(agent_id);
+            c.send(
+                crate::accounts::PlaceBetSpl {
+                    bet: c.ensure::<Bet>(bet_key(bettor, agent_id)),
+                    arena: arena.key,
+                    pool: c.ensure::<AgentPool>(arena.pool(agent_id)),
+                    agent: c.exists(agent).then_some(agent),
+                    profile: c.ensure::<UserProfile>(arena.profile(bettor)),
+                    blacklisted: pda(&[b"blacklist", arena.key.as_ref(), bettor.as_ref()]),
+                    self_exclusion: pda(&[b"self-exclusion", bettor.as_ref()]),
+                    bet_mint: mint,
+                    bettor_token,
+                    token_vault,
+                    bettor,
+                    token_program: spl_token::ID,
+                    system_program: System::id(),
+                },
+                crate::instruction::PlaceBetSpl {
+                    agent_id: agent_id.to_string(),
+                    amount,
+                    bet_side: BetSide::Survive,
+                    predicted_placement: 1,
+                },
+            )
+        };
+
+        // A small bet tops up like a SOL bet and keeps its placement prediction
+        bet(winner, winner_token, "alpha", 300).unwrap();
+        bet(winner, winner_token, "alpha", 300).unwrap();
+        let position: Bet = c.get(bet_key(winner, "alpha"));
+        assert_eq!((position.amount, position.predicted_placement), (600, 1));
+        assert!(position.confirmed);
+        assert_eq!(c.get::<AgentPool>(arena.pool("alpha")).bet_count, 1);
+
+        // A large one waits for confirmation and is swept back once it lapses
+        bet(whale, whale_token, "alpha", 800).unwrap();
+        assert!(!c.get::<Bet>(bet_key(whale, "alpha")).confirmed);
+        assert_eq!(arena.state(&c).unconfirmed_escrow, 800);
+        c.warp(60);
+        c.send(
+            crate::accounts::SweepUnconfirmedBetSpl {
+                bet: bet_key(whale, "alpha"),
+                arena: arena.key,
+                bet_mint: mint,
+                token_vault,
+                bettor_token: whale_token,
+                bettor: whale,
+                token_program: spl_token::ID,
+            },
+            crate::instruction::SweepUnconfirmedBetSpl {},
+        )
+        .unwrap();
+        assert_eq!(c.token_balance(whale_token), 1_000);
+        assert!(!c.exists(bet_key(whale, "alpha")));
+
+        bet(loser, loser_token, "beta", 400).unwrap();
+        assert_eq!(c.token_balance(token_vault), 1_000);
+        c.declare(&arena, "alpha").unwrap();
+        c.send(
+            crate::accounts::ClaimWinningsSpl {
+                bet: bet_key(winner, "alpha"),
+                arena: arena.key,
+                pool: arena.pool("alpha"),
+                profile: arena.profile(winner),
+                bet_mint: mint,
+                token_vault,
+                bettor_token: winner_token,
+                bettor: winner,
+                token_program: spl_token::ID,
+            },
+            crate::instruction::ClaimWinningsSpl {},
+        )
+        .unwrap();
+        assert_eq!(c.token_balance(winner_token), 1_400);
+        assert_eq!(c.token_balance(token_vault), 0);
+        let state = arena.state(&c);
+        assert_eq!((state.total_claimed, state.unconfirmed_escrow), (1_000, 0));
+    }
+
+    #[test]
+    fn declaring_before_min_duration_is_rejected() {
+        let c = Chain::new();
+        c.warp(1_000);
+        let arena = c.open_arena("young");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                min_duration: Some(3_600),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            100,
+            BetSide::Survive,
+        )
+        .unwrap();
+
+        c.warp(1_000 + 3_599);
+        assert_eq!(
+            c.declare(&arena, "alpha").unwrap_err(),
+            code(ArenaError::ArenaTooYoung)
+        );
+        c.warp(1_000 + 3_600);
+        c.declare(&arena, "alpha").unwrap();
+        assert_eq!(arena.state(&c).winner, "alpha");
+    }
+
+    #[test]
+    fn pool_counts_positions_not_stakes() {
+        let c = Chain::new();
+        let arena = c.open_arena("bet-count");
+        c.register(&arena, "alpha", 100).unwrap();
+        let (first, second) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, first, "alpha", 100, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, second, "alpha", 200, BetSide::Survive)
+            .unwrap();
+        c.send(
+            crate::accounts::IncreaseBet {
+                bet: bet_key(first, "alpha"),
+                arena: arena.key,
+                vault: arena.vault,
+                pool: arena.pool("alpha"),
+                profile: arena.profile(first),
+                blacklisted: pda(&[b"blacklist", arena.key.as_ref(), first.as_ref()]),
+                self_exclusion: pda(&[b"self-exclusion", first.as_ref()]),
+                bettor: first,
+                system_program: System::id(),
+            },
+            crate::instruction::IncreaseBet {
+                amount: 50,
+                max_pool_after: 0,
+            },
+        )
+        .unwrap();
+
+        let pool: AgentPool = c.get(arena.pool("alpha"));
+        assert_eq!((pool.bet_count, pool.total_staked), (2, 350));
+    }
+
+    #[test]
+    fn cloned_arena_keeps_the_rules_but_not_the_history() {
+        let c = Chain::new();
+        let source = c.open_arena("season-1");
+        c.register(&source, "alpha", 100).unwrap();
+        c.configure(
+            &source,
+            ArenaConfigUpdate {
+                fee_bps: Some(250),
+                min_duration: Some(600),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        c.bet(
+            &source,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            100,
+            BetSide::Survive,
+        )
+        .unwrap();
+
+        c.warp(5_000);
+        let key = pda(&[b"arena", b"season-2"]);
+        let vault = pda(&[b"vault", key.as_ref()]);
+        let accounts = CloneArena {
+            source: c.account(source.key),
+            arena: c.account(key),
+            vault: c.account(vault),
+            authority: c.signer(source.authority),
+            system_program: c.system_program(),
+        };
+        c.call(accounts, Default::default(), |ctx| {
+            arena_logger::clone_arena(ctx, "season-2".to_string(), 2)
+        })
+        .unwrap();
+
+        let clone: Arena = c.get(key);
+        assert_eq!(
+            (clone.config.fee_bps, clone.config.min_duration),
+            (250, 600)
+        );
+        assert_eq!((clone.arena_id.as_str(), clone.round), ("season-2", 2));
+        assert_eq!((clone.started_at, clone.state), (5_000, ArenaState::Open));
+        assert_eq!((clone.total_agents, clone.total_bets), (0, 0));
+        assert_eq!((clone.total_bet_volume, clone.withdrawable_fees), (0, 0));
+        assert_eq!(c.get::<Vault>(vault).arena, key);
+    }
+
+    #[test]
+    fn preview_matches_what_a_claim_would_pay() {
+        let c = Chain::new();
+        let arena = c.open_arena("preview");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        let (winner, loser) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, winner, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, loser, "beta", 100, BetSide::Survive).unwrap();
+        let early = c.preview(&arena, winner, "alpha");
+        assert_eq!(early.reason_code, ClaimReason::NotResolved as u8);
+        c.declare(&arena, "alpha").unwrap();
+
+        let won = c.preview(&arena, winner, "alpha");
+        assert!(won.eligible);
+        assert_eq!(
+            (won.reason_code, won.payout),
+            (ClaimReason::Eligible as u8, 400)
+        );
+        let lost = c.preview(&arena, loser, "beta");
+        assert!(!lost.eligible);
+        assert_eq!(
+            (lost.reason_code, lost.payout),
+            (ClaimReason::LosingBet as u8, 0)
+        );
+
+        let before = c.lamports(winner) + c.lamports(bet_key(winner, "alpha"));
+        c.send(
+            arena.claim_accounts(winner, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.lamports(winner), before + won.payout);
+    }
+
+    #[test]
+    fn trivial_deaths_are_only_tallied() {
+        let c = Chain::new();
+        let arena = c.open_arena("trivial");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                min_services: Some(3),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let log_death = |with_record: bool| {
+            let accounts = LogDeath {
+                death: with_record.then(|| c.account(arena.pda(b"death", "alpha"))),
+                arena: c.account(arena.key),
+                pool: c.account(arena.pool("alpha")),
+                agent: c.account(arena.agent("alpha")),
+                authority: c.signer(arena.authority),
+                system_program: c.system_program(),
+                career: None,
+            };
+            c.call(accounts, Default::default(), |ctx| {
+                arena_logger::log_death(ctx, "alpha".to_string(), "ALPHA".to_string(), 40, 2)
+            })
+        };
+        c.warp(60);
+        // Paying rent for a record of a trivial death is refused
+        assert_eq!(
+            log_death(true).unwrap_err(),
+            ArenaError::DeathBelowMinServices.into()
+        );
+        assert!(!c.exists(arena.pda(b"death", "alpha")));
+        log_death(false).unwrap();
+
+        let state = arena.state(&c);
+        assert_eq!((state.trivial_deaths, state.dead_agents), (1, 1));
+        assert!(c.get::<Agent>(arena.agent("alpha")).dead);
+        assert!(!c.exists(arena.pda(b"death", "alpha")));
+    }
+
+    #[test]
+    fn guardian_can_pause_but_not_withdraw() {
+        let c = Chain::new();
+        let arena = c.open_arena("guardian");
+        let guardian = arena.state(&c).guardian;
+        let set_paused = |signer, paused| {
+            c.send(
+                crate::accounts::SetPaused {
+                    arena: arena.key,
+                    signer,
+                },
+                crate::instruction::SetPaused { paused },
+            )
+        };
+        set_paused(guardian, true).unwrap();
+        assert_eq!(arena.state(&c).state, ArenaState::Paused);
+        // Resuming stays with the authority
+        assert_eq!(
+            set_paused(guardian, false).unwrap_err(),
+            code(ArenaError::Unauthorized)
+        );
+        set_paused(arena.authority, false).unwrap();
+        assert_eq!(arena.state(&c).state, ArenaState::Open);
+
+        assert_eq!(
+            c.send(
+                crate::accounts::WithdrawFees {
+                    arena: arena.key,
+                    vault: arena.vault,
+                    authority: guardian,
+                },
+                crate::instruction::WithdrawFees {},
+            )
+            .unwrap_err(),
+            code(ArenaError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn agents_are_counted_once() {
+        let c = Chain::new();
+        let arena = c.open_arena("seen");
+        c.log_transaction(&arena, payment("tx-1", 10, 0)).unwrap();
+        assert_eq!(arena.state(&c).total_agents, 2);
+        // Registration and later payments find the agents already seen
+        c.register(&arena, "alpha", 100).unwrap();
+        c.log_transaction(&arena, payment("tx-2", 10, 0)).unwrap();
+
+        let state = arena.state(&c);
+        assert_eq!((state.total_agents, state.alive_agents), (2, 1));
+        let seen: AgentSeen = c.get(arena.pda(b"seen", "beta"));
+        assert_eq!((seen.arena, seen.agent_id.as_str()), (arena.key, "beta"));
+    }
+
+    #[test]
+    fn bet_mint_records_its_decimals() {
+        let c = Chain::new();
+        let arena = c.open_arena("usdc");
+        let mint = c.mint(6);
+        assert_eq!(
+            c.set_bet_mint(&arena, mint, 9).unwrap_err(),
+            ArenaError::MintDecimalsMismatch.into()
+        );
+        c.set_bet_mint(&arena, mint, 6).unwrap();
+
+        let state = arena.state(&c);
+        assert_eq!((state.bet_mint, state.bet_mint_decimals), (mint, 6));
+    }
+
+    #[test]
+    fn two_sided_result_pays_each_market_its_own_pot() {
+        let c = Chain::new();
+        let arena = c.open_arena("two-sided");
+        for agent_id in ["alpha", "beta", "gamma"] {
+            c.register(&arena, agent_id, 100).unwrap();
+        }
+        let bettors: Vec<_> = [
+            ("alpha", 300, BetSide::Survive),
+            ("beta", 100, BetSide::Survive),
+            ("gamma", 200, BetSide::Die),
+            ("alpha", 100, BetSide::Die),
+        ]
+        .into_iter()
+        .map(|(agent_id, amount, side)| {
+            let bettor = c.wallet(LAMPORTS_PER_SOL);
+            c.bet(&arena, bettor, agent_id, amount, side).unwrap();
+            (bettor, agent_id)
+        })
+        .collect();
+        c.send_with(
+            c.declare_accounts(&arena, "alpha"),
+            crate::instruction::ResolveTwoSided {
+                surviving_agent: "alpha".to_string(),
+                first_dead_agent: "gamma".to_string(),
+                sol_usd_price: 0,
+            },
+            &[arena.pool("gamma")],
+        )
+        .unwrap();
+
+        // Survival stakes share the survive pot, death stakes the die pot
+        let payouts: Vec<_> = bettors
+            .iter()
+            .map(|&(bettor, agent_id)| c.preview(&arena, bettor, agent_id).payout)
+            .collect();
+        assert_eq!(payouts, [400, 0, 300, 0]);
+        let (survivor_backer, first_dead_backer) = (bettors[0], bettors[2]);
+        for (bettor, agent_id) in [survivor_backer, first_dead_backer] {
+            c.send(
+                arena.claim_accounts(bettor, agent_id),
+                crate::instruction::ClaimWinnings {},
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            c.get::<UserProfile>(arena.profile(first_dead_backer.0))
+                .total_won,
+            300
+        );
+        assert_eq!(
+            c.send(
+                arena.claim_accounts(bettors[3].0, "alpha"),
+                crate::instruction::ClaimWinnings {},
+            )
+            .unwrap_err(),
+            code(ArenaError::NotWinningBet)
+        );
+        assert_eq!(arena.state(&c).total_claimed, 700);
+    }
+
+    #[test]
+    fn median_of_odd_and_even_balance_sets() {
+        let c = Chain::new();
+        let arena = c.open_arena("median");
+        let median = |balances: Vec<u64>| {
+            c.send(
+                crate::accounts::UpdateStats {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::ComputeMedian { balances },
+            )
+            .map(|()| arena.state(&c).median_balance)
+        };
+        assert_eq!(median(vec![30, 10, 20]), Ok(20));
+        assert_eq!(median(vec![40, 10, 30, 20]), Ok(25));
+        // The midpoint of two large balances does not overflow
+        assert_eq!(median(vec![u64::MAX, u64::MAX - 2]), Ok(u64::MAX - 1));
+
+        assert_eq!(median(Vec::new()), Err(code(ArenaError::NoBalances)));
+        assert_eq!(
+            median(vec![1; MAX_MEDIAN_INPUTS + 1]),
+            Err(code(ArenaError::TooManyBalances))
+        );
+        assert_eq!(arena.state(&c).median_balance, u64::MAX - 1);
+    }
+
+    #[test]
+    fn quiet_arenas_skip_formatting_their_logs() {
+        let c = Chain::new();
+        let mut arena = c.open_arena("quiet").state(&c);
+        let formatted = std::cell::Cell::new(0);
+        let argument = || {
+            formatted.set(formatted.get() + 1);
+            "expensive"
+        };
+        arena_msg!(arena, "verbose: {}", argument());
+        assert_eq!(formatted.get(), 1);
+
+        arena.config.verbose = false;
+        arena_msg!(arena, "quiet: {}", argument());
+        assert_eq!(formatted.get(), 1);
+    }
+
+    #[test]
+    fn bets_must_be_whole_multiples_of_the_granularity() {
+        let c = Chain::new();
+        let arena = c.open_arena("granularity");
+        c.register(&arena, "alpha", 100).unwrap();
+        let granularity = |lamports| {
+            c.configure(
+                &arena,
+                ArenaConfigUpdate {
+                    lamport_granularity: Some(lamports),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        granularity(1_000);
+        let (first, second) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        assert_eq!(
+            c.bet(&arena, first, "alpha", 1_500, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::InvalidBetGranularity)
+        );
+        c.bet(&arena, first, "alpha", 2_000, BetSide::Survive)
+            .unwrap();
+
+        granularity(0);
+        c.bet(&arena, second, "alpha", 1_500, BetSide::Survive)
+            .unwrap();
+        assert_eq!(arena.state(&c).total_bet_volume, 3_500);
+    }
+
+    #[test]
+    fn health_weighs_liveness_activity_and_engagement() {
+        assert_eq!(health_score(0, 0, 0, 0), 0);
+        assert_eq!(health_score(3, 4, 50, 25), 30 + 15 + 15);
+        assert_eq!(health_score(4, 4, 1_000, 1_000), 100);
+
+        let c = Chain::new();
+        let arena = c.open_arena("health");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        for _ in 0..2 {
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                "alpha",
+                100,
+                BetSide::Survive,
+            )
+            .unwrap();
+        }
+        for i in 0..10 {
+            c.log_transaction(&arena, payment(&format!("tx-{i}"), 10, 0))
+                .unwrap();
+        }
+        let compute = || {
+            c.send(
+                crate::accounts::UpdateStats {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::ComputeHealth {},
+            )
+            .map(|()| arena.state(&c).health_score)
+        };
+        assert_eq!(compute(), Ok(40 + 3 + 1));
+        // Activity only counts transactions since the last computation
+        assert_eq!(compute(), Ok(40 + 1));
+    }
+
+    #[test]
+    fn repeat_bets_wait_out_the_cooldown() {
+        let c = Chain::new();
+        let arena = c.open_arena("cooldown");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                bet_cooldown: Some(30),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (bettor, other) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.warp(100);
+        c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+            .unwrap();
+        // The cooldown is per wallet, whichever agent the next bet backs
+        c.warp(129);
+        assert_eq!(
+            c.bet(&arena, bettor, "beta", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::BetCooldown)
+        );
+        c.bet(&arena, other, "beta", 100, BetSide::Survive).unwrap();
+        c.warp(130);
+        c.bet(&arena, bettor, "beta", 100, BetSide::Survive)
+            .unwrap();
+        assert_eq!(c.get::<UserProfile>(arena.profile(bettor)).bet_count, 2);
+    }
+
+    #[test]
+    fn final_result_event_summarizes_the_arena() {
+        let c = Chain::new();
+        let arena = c.open_arena("result");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                fee_bps: Some(500),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            600,
+            BetSide::Survive,
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            300,
+            BetSide::Survive,
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            100,
+            BetSide::Die,
+        )
+        .unwrap();
+        let emit = || {
+            c.send(
+                crate::accounts::EmitFinalResult { arena: arena.key },
+                crate::instruction::EmitFinalResult {},
+            )
+        };
+        assert_eq!(emit().unwrap_err(), code(ArenaError::ArenaNotResolved));
+        c.warp(500);
+        c.send(
+            c.declare_accounts(&arena, "alpha"),
+            crate::instruction::DeclareWinner {
+                agent_id: "alpha".to_string(),
+                sol_usd_price: 150_000_000,
+            },
+        )
+        .unwrap();
+        emit().unwrap();
+
+        let result = arena_result(&c.account(arena.key));
+        assert_eq!((result.arena, result.winner.as_str()), (arena.key, "alpha"));
+        assert_eq!(
+            (
+                result.total_bet_volume,
+                result.survive_pool,
+                result.die_pool
+            ),
+            (1_000, 900, 100)
+        );
+        assert_eq!((result.total_bets, result.unique_bettors), (3, 3));
+        assert_eq!((result.fee_taken, result.resolved_at), (50, 500));
+        assert_eq!(result.sol_usd_price, 150_000_000);
+        assert_eq!(
+            (result.total_bet_volume_usd, result.fee_taken_usd),
+            (150, 7)
+        );
+    }
+
+    #[test]
+    fn agent_version_is_recorded_and_bounded() {
+        let c = Chain::new();
+        let arena = c.open_arena("versions");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        let log = |version: String| {
+            c.send(
+                crate::accounts::LogAgentVersion {
+                    arena: arena.key,
+                    agent: arena.agent("alpha"),
+                    authority: arena.authority,
+                },
+                crate::instruction::LogAgentVersion {
+                    agent_id: "alpha".to_string(),
+                    version,
+                    model_hash: [7; 32],
+                },
+            )
+        };
+
+        log("v1.4.2".to_string()).unwrap();
+        let agent: Agent = c.get(arena.agent("alpha"));
+        assert_eq!(
+            (agent.version.as_str(), agent.model_hash),
+            ("v1.4.2", [7; 32])
+        );
+
+        let too_long = "v".repeat(MAX_VERSION_LEN + 1);
+        assert_eq!(log(too_long).unwrap_err(), code(ArenaError::InvalidVersion));
+        assert_eq!(
+            log(String::new()).unwrap_err(),
+            code(ArenaError::InvalidVersion)
+        );
+        let agent: Agent = c.get(arena.agent("alpha"));
+        assert_eq!(agent.version, "v1.4.2");
+    }
+
+    #[test]
+    fn strict_arenas_only_take_bets_on_registered_agents() {
+        let c = Chain::new();
+        let arena = c.open_arena("strict");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "ghost", 100, BetSide::Survive)
+            .unwrap();
+
+        c.set_flag(&arena, FLAG_REQUIRE_REGISTERED_AGENTS, true)
+            .unwrap();
+        let err = c
+            .bet(&arena, bettor, "phantom", 100, BetSide::Survive)
+            .unwrap_err();
+        assert_eq!(err, code(ArenaError::UnknownAgent));
+        assert!(!c.exists(bet_key(bettor, "phantom")));
+        c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+            .unwrap();
+    }
+
+    #[test]
+    fn deaths_rebalance_the_alive_average() {
+        let c = Chain::new();
+        let arena = c.open_arena("average");
+        c.register(&arena, "alpha", 900).unwrap();
+        c.register(&arena, "beta", 300).unwrap();
+        c.register(&arena, "gamma", 600).unwrap();
+        let state = arena.state(&c);
+        assert_eq!((state.alive_agents, state.avg_balance), (3, 600));
+
+        c.warp(60);
+        c.kill(&arena, "alpha").unwrap();
+        let state = arena.state(&c);
+        assert_eq!(state.alive_agents, 2);
+        assert_eq!((state.total_balance_sum, state.avg_balance), (900, 450));
+    }
+
+    #[test]
+    fn transaction_fees_are_tallied_without_moving_lamports() {
+        let c = Chain::new();
+        let arena = c.open_arena("tx-fees");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                tx_fee_bps: Some(250),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let vault = c.lamports(arena.vault);
+        c.log_transaction(&arena, payment("tx-1", 1_000, 0))
+            .unwrap();
+        c.log_transaction(&arena, payment("tx-2", 2_000, 0))
+            .unwrap();
+        c.log_transaction(&arena, payment("tx-3", 399, 0)).unwrap();
+
+        // 25 + 50 + 9 (399 * 2.5% rounds down)
+        assert_eq!(arena.state(&c).accumulated_tx_fees, 84);
+        assert_eq!(c.lamports(arena.vault), vault);
+    }
+
+    #[test]
+    fn stats_over_no_alive_agents_settle_at_zero() {
+        let c = Chain::new();
+        let arena = c.open_arena("wiped-out");
+        let accounts = || crate::accounts::UpdateStats {
+            arena: arena.key,
+            authority: arena.authority,
+        };
+        c.send(
+            accounts(),
+            crate::instruction::UpdateStats {
+                alive_agents: 0,
+                dead_agents: 4,
+                avg_balance: 500,
+                gini_coefficient: 0,
+            },
+        )
+        .unwrap();
+        assert_eq!(arena.state(&c).avg_balance, 0);
+
+        let gini =
+            |balances: Vec<u64>| c.send(accounts(), crate::instruction::ComputeGini { balances });
+        assert_eq!(gini(vec![]).unwrap_err(), code(ArenaError::NoAliveAgents));
+        gini(vec![0, 0, 0]).unwrap();
+        assert_eq!(arena.state(&c).gini_coefficient, 0);
+    }
+
+    #[test]
+    fn malformed_ids_never_seed_an_account() {
+        let c = Chain::new();
+        let arena = c.open_arena("ids");
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        for id in ["", "tx\n1", "agent\u{7}"] {
+            assert_eq!(
+                c.log_transaction(&arena, payment(id, 100, 0)).unwrap_err(),
+                code(ArenaError::InvalidIdentifier)
+            );
+            assert_eq!(
+                c.bet(&arena, bettor, id, 100, BetSide::Survive)
+                    .unwrap_err(),
+                code(ArenaError::InvalidIdentifier)
+            );
+            assert!(!c.exists(bet_key(bettor, id)));
+        }
+        assert_eq!(
+            c.register(&arena, "al pha", 1_000).unwrap_err(),
+            ArenaError::InvalidIdentifier.into()
+        );
+        c.log_transaction(&arena, payment("tx-1_ok", 100, 0))
+            .unwrap();
+    }
+
+    #[test]
+    fn configured_oracle_declares_in_place_of_the_authority() {
+        let c = Chain::new();
+        let arena = c.open_arena("oracle");
+        let oracle = c.wallet(LAMPORTS_PER_SOL);
+        c.send(
+            crate::accounts::SetOracle {
+                arena: arena.key,
+                authority: arena.authority,
+            },
+            crate::instruction::SetOracle {
+                oracle: Some(oracle),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            c.declare(&arena, "alpha").unwrap_err(),
+            code(ArenaError::Unauthorized)
+        );
+        c.send(
+            crate::accounts::DeclareWinner {
+                resolver: oracle,
+                ..c.declare_accounts(&arena, "alpha")
+            },
+            crate::instruction::DeclareWinner {
+                agent_id: "alpha".to_string(),
+                sol_usd_price: 0,
+            },
+        )
+        .unwrap();
+        let state = arena.state(&c);
+        assert!(state.resolved());
+        assert_eq!(state.winner, "alpha");
+    }
+
+    #[test]
+    fn large_bets_count_once_confirmed() {
+        let c = Chain::new();
+        let arena = c.open_arena("confirm");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                confirm_threshold: Some(500),
+                confirm_timeout: Some(60),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "alpha", 800, BetSide::Survive)
+            .unwrap();
+        assert!(!c.get::<Bet>(bet_key(bettor, "alpha")).confirmed);
+        let state = arena.state(&c);
+        assert_eq!((state.unconfirmed_escrow, state.total_bet_volume), (800, 0));
+
+        let confirm = || {
+            c.send(
+                crate::accounts::ConfirmBet {
+                    bet: bet_key(bettor, "alpha"),
+                    arena: arena.key,
+                    pool: arena.pool("alpha"),
+                    profile: arena.profile(bettor),
+                    bettor,
+                },
+                crate::instruction::ConfirmBet {},
+            )
+        };
+        c.warp(59);
+        confirm().unwrap();
+        assert!(c.get::<Bet>(bet_key(bettor, "alpha")).confirmed);
+        let state = arena.state(&c);
+        assert_eq!((state.unconfirmed_escrow, state.total_bet_volume), (0, 800));
+        assert_eq!(c.get::<AgentPool>(arena.pool("alpha")).total_staked, 800);
+        assert_eq!(
+            confirm().unwrap_err(),
+            code(ArenaError::BetAlreadyConfirmed)
+        );
+    }
+
+    #[test]
+    fn stale_unconfirmed_bets_are_swept_back() {
+        let c = Chain::new();
+        let arena = c.open_arena("stale");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                confirm_threshold: Some(500),
+                confirm_timeout: Some(60),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "alpha", 800, BetSide::Survive)
+            .unwrap();
+        let bet = bet_key(bettor, "alpha");
+        let sweep = || {
+            c.send(
+                crate::accounts::SweepUnconfirmedBet {
+                    bet,
+                    arena: arena.key,
+                    vault: arena.vault,
+                    bettor,
+                },
+                crate::instruction::SweepUnconfirmedBet {},
+            )
+        };
+        c.warp(30);
+        assert_eq!(
+            sweep().unwrap_err(),
+            code(ArenaError::ConfirmationWindowOpen)
+        );
+
+        c.warp(60);
+        let confirm = c.send(
+            crate::accounts::ConfirmBet {
+                bet,
+                arena: arena.key,
+                pool: arena.pool("alpha"),
+                profile: arena.profile(bettor),
+                bettor,
+            },
+            crate::instruction::ConfirmBet {},
+        );
+        assert_eq!(
+            confirm.unwrap_err(),
+            code(ArenaError::ConfirmationWindowClosed)
+        );
+        let (before, vault, rent) = (c.lamports(bettor), c.lamports(arena.vault), c.lamports(bet));
+        sweep().unwrap();
+        assert!(!c.exists(bet));
+        assert_eq!(c.lamports(bettor), before + 800 + rent);
+        assert_eq!(c.lamports(arena.vault), vault - 800);
+        assert_eq!(arena.state(&c).unconfirmed_escrow, 0);
+    }
+
+    #[test]
+    fn refund_mode_returns_principal_and_keeps_history() {
+        let c = Chain::new();
+        let arena = c.open_arena("deprecated");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "alpha", 500, BetSide::Survive)
+            .unwrap();
+        c.log_transaction(&arena, payment("tx-1", 100, 0)).unwrap();
+        c.send(
+            crate::accounts::EnterRefundMode {
+                arena: arena.key,
+                authority: arena.authority,
+            },
+            crate::instruction::EnterRefundMode {},
+        )
+        .unwrap();
+
+        let late = c.wallet(LAMPORTS_PER_SOL);
+        assert_eq!(
+            c.bet(&arena, late, "alpha", 500, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::ArenaRefundOnly)
+        );
+        assert_eq!(
+            c.log_transaction(&arena, payment("tx-2", 100, 0))
+                .unwrap_err(),
+            code(ArenaError::ArenaRefundOnly)
+        );
+
+        let bet = bet_key(bettor, "alpha");
+        let (before, rent) = (c.lamports(bettor), c.lamports(bet));
+        c.send(
+            crate::accounts::RefundBet {
+                bet,
+                arena: arena.key,
+                vault: arena.vault,
+                bettor,
+            },
+            crate::instruction::RefundBet {},
+        )
+        .unwrap();
+        assert_eq!(c.lamports(bettor), before + 500 + rent);
+        assert!(!c.exists(bet));
+        let state = arena.state(&c);
+        assert_eq!((state.total_bet_volume, state.total_transactions), (500, 1));
+    }
+
+    #[test]
+    fn claims_run_one_at_a_time() {
+        let c = Chain::new();
+        let arena = c.open_arena("reentrancy");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        let (first, second, third, loser) = (
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+        );
+        for bettor in [first, second, third] {
+            c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+                .unwrap();
+        }
+        c.bet(&arena, loser, "beta", 300, BetSide::Survive).unwrap();
+        c.declare(&arena, "alpha").unwrap();
+        let claim = |bettor| {
+            c.send(
+                arena.claim_accounts(bettor, "alpha"),
+                crate::instruction::ClaimWinnings {},
+            )
+        };
+
+        // Back-to-back claims each clear the guard for the next
+        for bettor in [first, second] {
+            claim(bettor).unwrap();
+            assert!(!arena.state(&c).processing);
+        }
+
+        // A claim entered while another is mid-payout is refused
+        let mut state = arena.state(&c);
+        state.processing = true;
+        c.put(arena.key, &state);
+        assert_eq!(claim(third).unwrap_err(), code(ArenaError::Reentrancy));
+        assert!(c.exists(bet_key(third, "alpha")));
+    }
+
+    #[test]
+    fn fee_split_pays_each_recipient_its_share() {
+        let c = Chain::new();
+        let arena = c.open_arena("fee-split");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                fee_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (partner, treasury) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        let split = |partner_bps, treasury_bps| {
+            c.send(
+                crate::accounts::SetFeeRecipients {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::SetFeeRecipients {
+                    recipients: vec![
+                        FeeRecipient {
+                            recipient: partner,
+                            bps: partner_bps,
+                        },
+                        FeeRecipient {
+                            recipient: treasury,
+                            bps: treasury_bps,
+                        },
+                    ],
+                },
+            )
+        };
+        assert_eq!(
+            split(7_000, 2_999).unwrap_err(),
+            code(ArenaError::InvalidFeeSplit)
+        );
+        assert_eq!(
+            split(7_000, 3_001).unwrap_err(),
+            code(ArenaError::InvalidFeeSplit)
+        );
+        split(7_000, 3_000).unwrap();
+
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            6_000,
+            BetSide::Survive,
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            4_000,
+            BetSide::Survive,
+        )
+        .unwrap();
+        c.declare(&arena, "alpha").unwrap();
+        let (partner_before, treasury_before) = (c.lamports(partner), c.lamports(treasury));
+        c.send_with(
+            crate::accounts::DistributeFees {
+                arena: arena.key,
+                vault: arena.vault,
+            },
+            crate::instruction::DistributeFees {},
+            &[partner, treasury],
+        )
+        .unwrap();
+        assert_eq!(c.lamports(partner), partner_before + 700);
+        assert_eq!(c.lamports(treasury), treasury_before + 300);
+        assert_eq!(arena.state(&c).withdrawable_fees, 0);
+    }
+
+    #[test]
+    fn senders_are_credited_with_their_transactions() {
+        let c = Chain::new();
+        let arena = c.open_arena("activity");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        for tx_id in ["tx-1", "tx-2", "tx-3"] {
+            c.log_transaction(&arena, payment(tx_id, 10, 0)).unwrap();
+        }
+        let tx_count = |agent_id| c.get::<Agent>(arena.agent(agent_id)).tx_count;
+        assert_eq!((tx_count("alpha"), tx_count("beta")), (3, 0));
+    }
+
+    #[test]
+    fn entry_fee_is_taken_before_staking() {
+        let c = Chain::new();
+        let arena = c.open_arena("entry-fee");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                entry_fee_bps: Some(500),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (winner, loser) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, winner, "alpha", 1_000, BetSide::Survive)
+            .unwrap();
+        let bet: Bet = c.get(bet_key(winner, "alpha"));
+        assert_eq!((bet.gross_amount, bet.amount), (1_000, 950));
+        assert_eq!(arena.state(&c).withdrawable_fees, 50);
+        assert_eq!(c.get::<AgentPool>(arena.pool("alpha")).total_staked, 950);
+
+        c.bet(&arena, loser, "beta", 400, BetSide::Survive).unwrap();
+        let state = arena.state(&c);
+        assert_eq!(
+            (state.total_bet_volume, state.withdrawable_fees),
+            (1_330, 70)
+        );
+
+        // Winnings are shared out of the staked pot only
+        c.declare(&arena, "alpha").unwrap();
+        c.send(
+            arena.claim_accounts(winner, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.get::<UserProfile>(arena.profile(winner)).total_won, 1_330);
+    }
+
+    #[test]
+    fn roster_only_arenas_reject_outsiders() {
+        let c = Chain::new();
+        let arena = c.open_arena("roster");
+        let set_roster = |roster: &[&str]| {
+            c.send(
+                crate::accounts::SetRoster {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::SetRoster {
+                    roster: roster.iter().map(|id| id.to_string()).collect(),
+                },
+            )
+        };
+        assert_eq!(
+            set_roster(&["alpha", "alpha"]).unwrap_err(),
+            code(ArenaError::InvalidRoster)
+        );
+        set_roster(&["alpha", "beta"]).unwrap();
+        assert_eq!(arena.state(&c).roster, ["alpha", "beta"]);
+
+        // The roster is advisory until the arena opts in
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "gamma", 100, BetSide::Survive)
+            .unwrap();
+        c.set_flag(&arena, FLAG_ROSTER_ONLY, true).unwrap();
+        assert_eq!(
+            c.bet(&arena, bettor, "delta", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::NotOnRoster)
+        );
+        assert_eq!(
+            c.register(&arena, "delta", 1_000).unwrap_err(),
+            ArenaError::NotOnRoster.into()
+        );
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+            .unwrap();
+    }
+
+    #[test]
+    fn unbacked_winner_turns_the_arena_to_refunds() {
+        let c = Chain::new();
+        let arena = c.open_arena("unbacked");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        let (backer, doubter) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, backer, "beta", 500, BetSide::Survive)
+            .unwrap();
+        // Betting against the winner doesn't back it
+        c.bet(&arena, doubter, "alpha", 200, BetSide::Die).unwrap();
+
+        c.declare(&arena, "alpha").unwrap();
+        let state = arena.state(&c);
+        assert!(state.winner_had_no_backers);
+        assert!(state.refund_mode() && state.resolved());
+
+        for (bettor, agent_id, principal) in [(backer, "beta", 500), (doubter, "alpha", 200)] {
+            let bet = bet_key(bettor, agent_id);
+            let (before, rent) = (c.lamports(bettor), c.lamports(bet));
+            c.send(
+                crate::accounts::RefundBet {
+                    bet,
+                    arena: arena.key,
+                    vault: arena.vault,
+                    bettor,
+                },
+                crate::instruction::RefundBet {},
+            )
+            .unwrap();
+            assert_eq!(c.lamports(bettor), before + principal + rent);
+        }
+    }
+
+    #[test]
+    fn void_reason_is_published_to_bettors() {
+        let c = Chain::new();
+        let arena = c.open_arena("voided");
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+            .unwrap();
+        let void = |void_note: String| {
+            c.send(
+                crate::accounts::VoidArena {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::VoidArena {
+                    void_reason: 3,
+                    void_note,
+                },
+            )
+        };
+        assert_eq!(
+            void("x".repeat(MAX_VOID_NOTE_LEN + 1)).unwrap_err(),
+            code(ArenaError::InvalidVoidNote)
+        );
+        void("feed outage".to_string()).unwrap();
+
+        let state = arena.state(&c);
+        assert!(state.voided());
+        assert_eq!(
+            (state.void_reason, state.void_note.as_str()),
+            (3, "feed outage")
+        );
+        let preview = c.preview(&arena, bettor, "alpha");
+        assert!(!preview.eligible);
+        assert_eq!(
+            (preview.reason_code, preview.void_reason),
+            (ClaimReason::Voided as u8, 3)
+        );
+    }
+
+    #[test]
+    fn realloc_grows_an_old_arena_in_place() {
+        let c = Chain::new();
+        let arena = c.open_arena("legacy");
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            700,
+            BetSide::Survive,
+        )
+        .unwrap();
+        // An account from before the last 64 bytes of fields existed
+        let old_len = 8 + Arena::INIT_SPACE - 64;
+        let data = c.info(arena.key).data.borrow()[..old_len].to_vec();
+        let rent = Rent::default().minimum_balance(old_len);
+        c.insert(leak_account(arena.key, crate::ID, rent, &data, false));
+
+        let realloc = || {
+            c.send(
+                crate::accounts::ReallocArena {
+                    arena: arena.key,
+                    authority: arena.authority,
+                    system_program: System::id(),
+                },
+                crate::instruction::ReallocArena {},
+            )
+        };
+        let paid = c.lamports(arena.authority);
+        realloc().unwrap();
+        assert_eq!(c.info(arena.key).data_len(), 8 + Arena::INIT_SPACE);
+        let new_rent = Rent::default().minimum_balance(8 + Arena::INIT_SPACE);
+        assert_eq!(c.lamports(arena.key), new_rent);
+        assert_eq!(c.lamports(arena.authority), paid - (new_rent - rent));
+        let state = arena.state(&c);
+        assert_eq!(
+            (
+                state.arena_id.as_str(),
+                state.authority,
+                state.total_bet_volume
+            ),
+            ("legacy", arena.authority, 700)
+        );
+
+        assert_eq!(realloc().unwrap_err(), code(ArenaError::ArenaUpToDate));
+    }
+
+    /// An arena closing at 1_000 whose last-minute bets buy two minutes, twice
+    fn sniping_arena(c: &Chain) -> TestArena {
+        let arena = c.open_arena("sniping");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                snipe_window: Some(60),
+                snipe_extension: Some(120),
+                max_extensions: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        c.send(
+            crate::accounts::SetBettingDeadline {
+                arena: arena.key,
+                authority: arena.authority,
+            },
+            crate::instruction::SetBettingDeadline { closes_at: 1_000 },
+        )
+        .unwrap();
+        arena
+    }
+
+    #[test]
+    fn late_bets_push_the_deadline_out() {
+        let c = Chain::new();
+        let arena = sniping_arena(&c);
+        let late_bet = |at| {
+            c.warp(at);
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                "alpha",
+                100,
+                BetSide::Survive,
+            )
+            .unwrap();
+            let state = arena.state(&c);
+            (state.betting_closes_at, state.extensions_used)
+        };
+        assert_eq!(late_bet(900), (1_000, 0));
+        assert_eq!(late_bet(950), (1_120, 1));
+        // Past the original deadline, but inside the extended one
+        assert_eq!(late_bet(1_100), (1_240, 2));
+    }
+
+    #[test]
+    fn deadline_extensions_are_capped() {
+        let c = Chain::new();
+        let arena = sniping_arena(&c);
+        let bet_at = |at| {
+            c.warp(at);
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                "alpha",
+                100,
+                BetSide::Survive,
+            )
+        };
+        for at in [950, 1_100, 1_200] {
+            bet_at(at).unwrap();
+        }
+        let state = arena.state(&c);
+        assert_eq!((state.betting_closes_at, state.extensions_used), (1_240, 2));
+        assert_eq!(bet_at(1_240).unwrap_err(), code(ArenaError::BettingClosed));
+    }
+
+    #[test]
+    fn volume_between_sums_a_bounded_bucket_range() {
+        let c = Chain::new();
+        let arena = c.open_arena("timeframe");
+        for (hour, amount) in [(0, 100), (1, 200), (2, 400), (3, 800)] {
+            c.warp(hour * BUCKET_SECONDS);
+            c.log_transaction(&arena, payment(&format!("tx-{hour}"), amount, hour))
+                .unwrap();
+        }
+        let bucket = |hour: i64| pda(&[b"bucket", arena.key.as_ref(), &hour.to_le_bytes()]);
+        // A read-only view takes the buckets alone as its remaining accounts
+        let volume = |buckets: Vec<Pubkey>, start_hour, end_hour| {
+            let mut accounts = VolumeBetween {
+                arena: c.account(arena.key),
+            };
+            let remaining: Vec<_> = buckets.into_iter().map(|key| c.info(key)).collect();
+            arena_logger::volume_between(
+                Context::new(
+                    &crate::ID,
+                    &mut accounts,
+                    Box::leak(remaining.into_boxed_slice()),
+                    Default::default(),
+                ),
+                start_hour,
+                end_hour,
+            )
+        };
+
+        assert_eq!(
+            volume(vec![bucket(1), bucket(2), bucket(3)], 1, 3).unwrap(),
+            1_400
+        );
+        assert_eq!(
+            volume(vec![bucket(0), bucket(0)], 0, 3).unwrap_err(),
+            ArenaError::InvalidBucketRange.into()
+        );
+        assert_eq!(
+            volume(vec![bucket(0); MAX_BUCKETS_READ + 1], 0, 3).unwrap_err(),
+            ArenaError::InvalidBucketRange.into()
+        );
+    }
+
+    #[test]
+    fn authority_handover_waits_out_the_timelock() {
+        let c = Chain::new();
+        let arena = c.open_arena("handover");
+        let successor = c.wallet(LAMPORTS_PER_SOL);
+        c.warp(1_000);
+        c.send(
+            crate::accounts::ProposeAuthority {
+                arena: arena.key,
+                authority: arena.authority,
+            },
+            crate::instruction::ProposeAuthority {
+                new_authority: Some(successor),
+            },
+        )
+        .unwrap();
+        let accept = |new_authority| {
+            c.send(
+                crate::accounts::AcceptAuthority {
+                    arena: arena.key,
+                    new_authority,
+                },
+                crate::instruction::AcceptAuthority {},
+            )
+        };
+
+        c.warp(1_000 + AUTHORITY_DELAY - 1);
+        assert_eq!(
+            accept(successor).unwrap_err(),
+            code(ArenaError::AuthorityTimelocked)
+        );
+        c.warp(1_000 + AUTHORITY_DELAY);
+        assert_eq!(
+            accept(c.wallet(LAMPORTS_PER_SOL)).unwrap_err(),
+            code(ArenaError::Unauthorized)
+        );
+        accept(successor).unwrap();
+        let state = arena.state(&c);
+        assert_eq!(
+            (state.authority, state.pending_authority),
+            (successor, None)
+        );
+    }
+
+    #[test]
+    fn bet_lifecycle_events_carry_the_new_totals() {
+        use anchor_lang::Event;
+
+        let c = Chain::new();
+        let arena = c.open_arena("order-book");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                cancel_fee_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (backer, quitter) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, backer, "alpha", 400, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, quitter, "alpha", 200, BetSide::Survive)
+            .unwrap();
+
+        c.send(
+            crate::accounts::IncreaseBet {
+                bet: bet_key(backer, "alpha"),
+                arena: arena.key,
+                vault: arena.vault,
+                pool: arena.pool("alpha"),
+                profile: arena.profile(backer),
+                blacklisted: pda(&[b"blacklist", arena.key.as_ref(), backer.as_ref()]),
+                self_exclusion: pda(&[b"self-exclusion", backer.as_ref()]),
+                bettor: backer,
+                system_program: System::id(),
+            },
+            crate::instruction::IncreaseBet {
+                amount: 300,
+                max_pool_after: 0,
+            },
+        )
+        .unwrap();
+        let pool: AgentPool = c.get(arena.pool("alpha"));
+        let event = bet_increased(arena.key, &c.get(bet_key(backer, "alpha")), &pool, 300);
+        // Decode as a subscriber would, from the logged discriminator and payload
+        let data = event.data();
+        assert_eq!(&data[..8], BetIncreased::DISCRIMINATOR);
+        let increased = BetIncreased::try_from_slice(&data[8..]).unwrap();
+        assert_eq!(
+            (increased.bettor, increased.agent_id.as_str()),
+            (backer, "alpha")
+        );
+        assert_eq!(
+            (increased.delta, increased.new_amount, increased.pool_total),
+            (300, 700, 900)
+        );
+
+        let bet: Bet = c.get(bet_key(quitter, "alpha"));
+        let (before, rent) = (c.lamports(quitter), c.lamports(bet_key(quitter, "alpha")));
+        c.send(
+            crate::accounts::CancelBet {
+                bet: bet_key(quitter, "alpha"),
+                arena: arena.key,
+                vault: arena.vault,
+                pool: arena.pool("alpha"),
+                profile: arena.profile(quitter),
+                bettor: quitter,
+            },
+            crate::instruction::CancelBet {},
+        )
+        .unwrap();
+        assert_eq!(c.lamports(quitter), before + 180 + rent);
+        let pool: AgentPool = c.get(arena.pool("alpha"));
+        let data = bet_cancelled(arena.key, &bet, &pool, 180).data();
+        assert_eq!(&data[..8], BetCancelled::DISCRIMINATOR);
+        let cancelled = BetCancelled::try_from_slice(&data[8..]).unwrap();
+        assert_eq!(cancelled.bettor, quitter);
+        assert_eq!((cancelled.refunded, cancelled.pool_total), (180, 700));
+        assert_eq!(arena.state(&c).withdrawable_fees, 20);
+    }
+
+    #[test]
+    fn liability_depends_on_whether_the_winner_was_backed() {
+        let c = Chain::new();
+        let arena = c.open_arena("liability");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                fee_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            600,
+            BetSide::Survive,
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            400,
+            BetSide::Survive,
+        )
+        .unwrap();
+        let liability = |agent_id: &str| {
+            let accounts = MaxLiability {
+                arena: c.account(arena.key),
+                pool: UncheckedAccount::try_from(c.leak_info(arena.pool(agent_id), false)),
+            };
+            c.call(accounts, Default::default(), |ctx| {
+                arena_logger::max_liability(ctx, agent_id.to_string())
+            })
+            .unwrap()
+        };
+
+        // Either backed agent winning pays out the pot less the 10% fee
+        assert_eq!((liability("alpha"), liability("beta")), (900, 900));
+        // An agent nobody backed sends every stake back
+        assert_eq!(liability("gamma"), 1_000);
+    }
+
+    #[test]
+    fn balance_snapshots_are_one_per_epoch() {
+        let c = Chain::new();
+        let arena = c.open_arena("history");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        let agent = arena.agent("alpha");
+        let snapshot_key = |epoch: u64| pda(&[b"snapshot", agent.as_ref(), &epoch.to_le_bytes()]);
+        let snapshot = |epoch| {
+            c.send(
+                crate::accounts::SnapshotAgentBalance {
+                    arena: arena.key,
+                    agent,
+                    snapshot: c.ensure::<AgentBalanceSnapshot>(snapshot_key(epoch)),
+                    authority: arena.authority,
+                    system_program: System::id(),
+                },
+                crate::instruction::SnapshotAgentBalance {
+                    agent_id: "alpha".to_string(),
+                    epoch,
+                },
+            )
+        };
 
-#[derive(Accounts)]
-#[instruction(agent_id: String)]
-pub struct PlaceBet<'info> {
-    #[account(
-        init,
-        payer = bettor,
-        space = 8 + Bet::INIT_SPACE,
-        seeds = [b"bet", bettor.key().as_ref(), agent_id.as_bytes()],
-        bump
-    )]
-    pub bet: Account<'info, Bet>,
+        c.warp(100);
+        snapshot(1).unwrap();
+        let mut state: Agent = c.get(agent);
+        state.balance = 1_750;
+        c.put(agent, &state);
+        c.warp(200);
+        snapshot(2).unwrap();
+        assert_eq!(snapshot(1).unwrap_err(), code(ArenaError::SnapshotExists));
 
-    #[account(mut)]
-    pub arena: Account<'info, Arena>,
+        let history: Vec<_> = [1, 2]
+            .map(|epoch| c.get::<AgentBalanceSnapshot>(snapshot_key(epoch)))
+            .into_iter()
+            .map(|s| (s.agent, s.epoch, s.balance, s.taken_at))
+            .collect();
+        assert_eq!(history, [(agent, 1, 1_000, 100), (agent, 2, 1_750, 200)]);
+    }
 
-    #[account(mut)]
-    pub bettor: Signer<'info>,
+    #[test]
+    fn payouts_multiply_in_u128_and_fail_loudly_on_narrowing() {
+        let mut arena = blank_arena();
+        arena.state = ArenaState::Resolved;
+        arena.winner = "alpha".to_string();
+        arena.total_bet_volume = 10_000_000_000_000_000_000;
+        let mut pool: AgentPool = blank(AgentPool::INIT_SPACE);
+        pool.total_staked = 4_000_000_000_000_000_000;
+        let mut bet: Bet = blank(Bet::INIT_SPACE);
+        bet.agent_id = "alpha".to_string();
+        bet.amount = 1_000_000_000_000_000_000;
 
-    pub system_program: Program<'info, System>,
-}
+        // amount * pot is 10^37, far past u64::MAX, but the share itself fits
+        assert!((bet.amount as u128 * arena.total_bet_volume as u128) > u64::MAX as u128);
+        assert_eq!(
+            compute_payout(&arena, &pool, &bet).unwrap(),
+            2_500_000_000_000_000_000
+        );
 
-// Account Data Structures
+        // A share that cannot be paid in lamports is refused rather than truncated
+        pool.total_staked = 1;
+        assert_eq!(
+            compute_payout(&arena, &pool, &bet).unwrap_err(),
+            ArenaError::PayoutOverflow.into()
+        );
+    }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Arena {
-    #[max_len(50)]
-    pub arena_id: String,
-    pub authority: Pubkey,
-    pub total_transactions: u64,
-    pub total_agents: u32,
-    pub alive_agents: u32,
-    pub dead_agents: u32,
-    pub total_volume: u64,
-    pub avg_balance: u64,
-    pub gini_coefficient: u16,
-    pub started_at: i64,
-    pub total_bets: u64,
-    pub total_bet_volume: u64,
-}
+    #[test]
+    fn flags_toggle_independently() {
+        let c = Chain::new();
+        let arena = c.open_arena("flags");
+        let flags = || {
+            let state = arena.state(&c);
+            (
+                state.flag(FLAG_REQUIRE_REGISTERED_AGENTS),
+                state.flag(FLAG_ROSTER_ONLY),
+            )
+        };
+        c.set_flag(&arena, FLAG_ROSTER_ONLY, true).unwrap();
+        assert_eq!(flags(), (false, true));
+        c.set_flag(&arena, FLAG_REQUIRE_REGISTERED_AGENTS, true)
+            .unwrap();
+        assert_eq!(flags(), (true, true));
+        c.set_flag(&arena, FLAG_ROSTER_ONLY, false).unwrap();
+        assert_eq!(flags(), (true, false));
+        assert_eq!(arena.state(&c).flags, 1 << FLAG_REQUIRE_REGISTERED_AGENTS);
 
-#[account]
-#[derive(InitSpace)]
-pub struct Transaction {
-    #[max_len(50)]
-    pub transaction_id: String,
-    #[max_len(50)]
-    pub from_agent: String,
-    #[max_len(50)]
-    pub to_agent: String,
-    pub amount: u64,
-    #[max_len(20)]
-    pub service_type: String,
-    pub timestamp: i64,
-    pub arena: Pubkey,
-}
+        // The retired pause bit and undefined bits are refused
+        for flag in [0, FLAG_COUNT, 63] {
+            assert_eq!(
+                c.set_flag(&arena, flag, true).unwrap_err(),
+                code(ArenaError::InvalidFlag)
+            );
+        }
+    }
 
-#[account]
-#[derive(InitSpace)]
-pub struct AgentDeath {
-    #[max_len(50)]
-    pub agent_id: String,
-    #[max_len(50)]
-    pub agent_name: String,
-    pub final_balance: u64,
-    pub services_completed: u32,
-    pub timestamp: i64,
-    pub arena: Pubkey,
-}
+    #[test]
+    fn winnings_cannot_be_redirected() {
+        let c = Chain::new();
+        let arena = c.open_arena("redirect");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        let (winner, loser, thief) = (
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+            c.wallet(LAMPORTS_PER_SOL),
+        );
+        c.bet(&arena, winner, "alpha", 500, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, loser, "beta", 500, BetSide::Survive).unwrap();
+        c.declare(&arena, "alpha").unwrap();
+        let stolen = crate::accounts::ClaimWinnings {
+            bettor: thief,
+            profile: arena.profile(thief),
+            ..arena.claim_accounts(winner, "alpha")
+        };
+        c.ensure::<UserProfile>(arena.profile(thief));
+        assert_eq!(
+            c.send(stolen, crate::instruction::ClaimWinnings {})
+                .unwrap_err(),
+            code(anchor_lang::error::ErrorCode::ConstraintHasOne)
+        );
 
-#[account]
-#[derive(InitSpace)]
-pub struct Bet {
-    pub bettor: Pubkey,
-    #[max_len(50)]
-    pub agent_id: String,
-    pub amount: u64,
-    pub timestamp: i64,
-    pub arena: Pubkey,
-    pub claimed: bool,
+        // The handler holds the line even past the account constraints
+        let accounts = ClaimWinnings {
+            bet: c.account(bet_key(winner, "alpha")),
+            arena: c.account(arena.key),
+            vault: c.account(arena.vault),
+            pool: c.account(arena.pool("alpha")),
+            profile: c.account(arena.profile(thief)),
+            bettor: c.signer(thief),
+            death: UncheckedAccount::try_from(c.leak_info(arena.pda(b"death", "alpha"), false)),
+            agent: UncheckedAccount::try_from(c.leak_info(arena.agent("alpha"), false)),
+            career: None,
+        };
+        let before = c.lamports(thief);
+        assert_eq!(
+            c.call(accounts, Default::default(), arena_logger::claim_winnings)
+                .unwrap_err(),
+            ArenaError::Unauthorized.into()
+        );
+        assert_eq!(c.lamports(thief), before);
+
+        c.send(
+            arena.claim_accounts(winner, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.get::<UserProfile>(arena.profile(winner)).total_won, 1_000);
+    }
+
+    #[test]
+    fn settlement_price_values_the_result_in_usd() {
+        let c = Chain::new();
+        let arena = c.open_arena("usd");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                fee_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(10 * LAMPORTS_PER_SOL),
+            "alpha",
+            2 * LAMPORTS_PER_SOL,
+            BetSide::Survive,
+        )
+        .unwrap();
+        c.bet(
+            &arena,
+            c.wallet(10 * LAMPORTS_PER_SOL),
+            "beta",
+            LAMPORTS_PER_SOL,
+            BetSide::Survive,
+        )
+        .unwrap();
+        // $142.50 per SOL, in micro-USD
+        c.send(
+            c.declare_accounts(&arena, "alpha"),
+            crate::instruction::DeclareWinner {
+                agent_id: "alpha".to_string(),
+                sol_usd_price: 142_500_000,
+            },
+        )
+        .unwrap();
+
+        let result = arena_result(&c.account(arena.key));
+        assert_eq!(
+            (result.total_bet_volume, result.fee_taken),
+            (3 * LAMPORTS_PER_SOL, LAMPORTS_PER_SOL * 3 / 10)
+        );
+        assert_eq!(
+            (result.total_bet_volume_usd, result.fee_taken_usd),
+            (427_500_000, 42_750_000)
+        );
+
+        // Token-denominated arenas have no SOL amounts to convert
+        let tokens = c.open_arena("usd-tokens");
+        c.use_bet_mint(&tokens, 6);
+        let mut state = tokens.state(&c);
+        assert_eq!(
+            record_settlement_price(&mut state, 142_500_000).unwrap_err(),
+            ArenaError::WrongBetCurrency.into()
+        );
+    }
+
+    #[test]
+    fn volume_audit_flags_drift() {
+        let c = Chain::new();
+        let arena = c.open_arena("audit");
+        for (tx_id, amount) in [("tx-1", 120), ("tx-2", 80), ("tx-3", 300)] {
+            c.log_transaction(&arena, payment(tx_id, amount, 0))
+                .unwrap();
+        }
+        let audit = |transaction_amounts: Vec<u64>| {
+            c.send(
+                crate::accounts::RecomputeVolume {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::RecomputeVolume {
+                    transaction_amounts,
+                },
+            )
+        };
+        audit(vec![300, 120, 80]).unwrap();
+        assert_eq!(
+            audit(vec![120, 80]).unwrap_err(),
+            code(ArenaError::VolumeMismatch)
+        );
+        assert_eq!(
+            audit(vec![u64::MAX, 1]).unwrap_err(),
+            code(ArenaError::MathOverflow)
+        );
+    }
+
+    #[test]
+    fn blacklisted_wallets_keep_their_bets_but_place_no_more() {
+        let c = Chain::new();
+        let arena = c.open_arena("blacklist");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        let (banned, other) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, banned, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        let entry = pda(&[b"blacklist", arena.key.as_ref(), banned.as_ref()]);
+        let accounts = BlacklistBettor {
+            arena: c.account(arena.key),
+            entry: c.account(entry),
+            authority: c.signer(arena.authority),
+            system_program: c.system_program(),
+        };
+        c.call(accounts, Default::default(), |ctx| {
+            arena_logger::blacklist_bettor(ctx, banned)
+        })
+        .unwrap();
+
+        assert_eq!(
+            c.bet(&arena, banned, "beta", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::BettorBlacklisted)
+        );
+        c.bet(&arena, other, "beta", 100, BetSide::Survive).unwrap();
+
+        c.declare(&arena, "alpha").unwrap();
+        c.send(
+            arena.claim_accounts(banned, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.get::<UserProfile>(arena.profile(banned)).total_won, 400);
+
+        c.send(
+            crate::accounts::UnblacklistBettor {
+                arena: arena.key,
+                entry,
+                authority: arena.authority,
+            },
+            crate::instruction::UnblacklistBettor { wallet: banned },
+        )
+        .unwrap();
+        assert!(!c.exists(entry));
+    }
+
+    #[test]
+    fn winnings_unlock_only_above_the_minimum_pool() {
+        let c = Chain::new();
+        let market = |arena_id: &str, losing_stake| {
+            let arena = c.open_arena(arena_id);
+            c.register(&arena, "alpha", 1_000).unwrap();
+            c.register(&arena, "beta", 1_000).unwrap();
+            c.configure(
+                &arena,
+                ArenaConfigUpdate {
+                    min_pool_to_payout: Some(1_000),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let winner = c.wallet(LAMPORTS_PER_SOL);
+            c.bet(&arena, winner, "alpha", 600, BetSide::Survive)
+                .unwrap();
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                "beta",
+                losing_stake,
+                BetSide::Survive,
+            )
+            .unwrap();
+            c.declare(&arena, "alpha").unwrap();
+            (arena, winner)
+        };
+
+        let (thin, winner) = market("thin-market", 300);
+        let state = thin.state(&c);
+        assert!(state.pool_below_minimum && state.refund_mode());
+        assert_eq!(
+            c.send(
+                thin.claim_accounts(winner, "alpha"),
+                crate::instruction::ClaimWinnings {},
+            )
+            .unwrap_err(),
+            code(ArenaError::ArenaRefundOnly)
+        );
+        let bet = bet_key(winner, "alpha");
+        let (before, rent) = (c.lamports(winner), c.lamports(bet));
+        c.send(
+            crate::accounts::RefundBet {
+                bet,
+                arena: thin.key,
+                vault: thin.vault,
+                bettor: winner,
+            },
+            crate::instruction::RefundBet {},
+        )
+        .unwrap();
+        assert_eq!(c.lamports(winner), before + 600 + rent);
+
+        let (deep, winner) = market("deep-market", 400);
+        assert!(!deep.state(&c).refund_mode());
+        c.send(
+            deep.claim_accounts(winner, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.get::<UserProfile>(deep.profile(winner)).total_won, 1_000);
+    }
+
+    #[test]
+    fn vault_holds_the_escrow_and_the_arena_only_rent() {
+        let c = Chain::new();
+        let arena = c.open_arena("vaulted");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                fee_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let arena_rent = c.lamports(arena.key);
+        let vault = c.lamports(arena.vault);
+        let (winner, loser) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, winner, "alpha", 700, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, loser, "beta", 300, BetSide::Survive).unwrap();
+        assert_eq!(c.lamports(arena.vault), vault + 1_000);
+        assert_eq!(c.lamports(arena.key), arena_rent);
+
+        c.declare(&arena, "alpha").unwrap();
+        c.send(
+            arena.claim_accounts(winner, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.lamports(arena.vault), vault + 100);
+        let before = c.lamports(arena.authority);
+        c.send(
+            crate::accounts::WithdrawFees {
+                arena: arena.key,
+                vault: arena.vault,
+                authority: arena.authority,
+            },
+            crate::instruction::WithdrawFees {},
+        )
+        .unwrap();
+        assert_eq!(c.lamports(arena.authority), before + 100);
+        assert_eq!(c.lamports(arena.vault), vault);
+        assert_eq!(c.lamports(arena.key), arena_rent);
+    }
+
+    #[test]
+    fn podium_pays_each_place_its_tier() {
+        let c = Chain::new();
+        let arena = c.open_arena("podium");
+        c.send(
+            crate::accounts::SetPayoutTiers {
+                arena: arena.key,
+                authority: arena.authority,
+            },
+            crate::instruction::SetPayoutTiers {
+                payout_tiers: vec![5_000, 3_000, 2_000],
+            },
+        )
+        .unwrap();
+        let mut bettors = HashMap::new();
+        for (agent_id, amount) in [
+            ("alpha", 300),
+            ("beta", 200),
+            ("gamma", 100),
+            ("delta", 400),
+        ] {
+            let bettor = c.wallet(LAMPORTS_PER_SOL);
+            c.bet(&arena, bettor, agent_id, amount, BetSide::Survive)
+                .unwrap();
+            bettors.insert(agent_id, bettor);
+        }
+        let ranked = ["alpha", "beta", "gamma"];
+        let pools: Vec<_> = ranked.iter().map(|id| arena.pool(id)).collect();
+        c.send_with(
+            crate::accounts::DeclarePodium {
+                arena: arena.key,
+                vault: arena.vault,
+                resolver: arena.authority,
+                system_program: System::id(),
+            },
+            crate::instruction::DeclarePodium {
+                ranked_agents: ranked.map(String::from).to_vec(),
+                sol_usd_price: 0,
+            },
+            &pools,
+        )
+        .unwrap();
+
+        let claim = |agent_id| {
+            let bettor = bettors[agent_id];
+            c.send(
+                arena.claim_accounts(bettor, agent_id),
+                crate::instruction::ClaimWinnings {},
+            )
+            .map(|()| c.get::<UserProfile>(arena.profile(bettor)).total_won)
+        };
+        // First place shares half the 1_000 pot; third place a fifth
+        assert_eq!(claim("alpha").unwrap(), 500);
+        assert_eq!(claim("gamma").unwrap(), 200);
+        assert_eq!(claim("delta").unwrap_err(), code(ArenaError::NotWinningBet));
+    }
+
+    #[test]
+    fn transaction_memos_are_stored_and_bounded() {
+        let c = Chain::new();
+        let arena = c.open_arena("memos");
+        let tx = |tx_id: &str| pda(&[b"transaction", tx_id.as_bytes()]);
+        c.log_transaction(
+            &arena,
+            crate::instruction::LogTransaction {
+                memo: Some("paid for GPT-4 inference".to_string()),
+                ..payment("tx-memo", 250, 0)
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            c.get::<Transaction>(tx("tx-memo")).memo,
+            "paid for GPT-4 inference"
+        );
+
+        // Callers that predate memos store an empty one
+        c.log_transaction(&arena, payment("tx-plain", 250, 0))
+            .unwrap();
+        assert_eq!(c.get::<Transaction>(tx("tx-plain")).memo, "");
+
+        let err = c
+            .log_transaction(
+                &arena,
+                crate::instruction::LogTransaction {
+                    memo: Some("m".repeat(MAX_MEMO_LEN + 1)),
+                    ..payment("tx-long", 250, 0)
+                },
+            )
+            .unwrap_err();
+        assert_eq!(err, code(ArenaError::MemoTooLong));
+        assert!(!c.exists(tx("tx-long")));
+    }
+
+    #[test]
+    fn cancelling_withholds_the_cancel_fee() {
+        let c = Chain::new();
+        let arena = c.open_arena("cancel-fee");
+        let cancel = |bettor: Pubkey| {
+            let bet = bet_key(bettor, "alpha");
+            let (before, rent) = (c.lamports(bettor), c.lamports(bet));
+            c.send(
+                crate::accounts::CancelBet {
+                    bet,
+                    arena: arena.key,
+                    vault: arena.vault,
+                    pool: arena.pool("alpha"),
+                    profile: arena.profile(bettor),
+                    bettor,
+                },
+                crate::instruction::CancelBet {},
+            )
+            .unwrap();
+            c.lamports(bettor) - before - rent
+        };
+        let (free, taxed) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, free, "alpha", 1_000, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, taxed, "alpha", 1_000, BetSide::Survive)
+            .unwrap();
+
+        assert_eq!(cancel(free), 1_000);
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                cancel_fee_bps: Some(200),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(cancel(taxed), 980);
+        let state = arena.state(&c);
+        assert_eq!((state.withdrawable_fees, state.total_bet_volume), (20, 0));
+    }
+
+    #[test]
+    fn closing_arenas_adds_them_to_protocol_stats() {
+        let c = Chain::new();
+        let stats = pda(&[b"protocol-stats"]);
+        let run = |arena_id: &str, payments: &[u64], stakes: &[u64]| {
+            let arena = c.open_arena(arena_id);
+            for (i, &amount) in payments.iter().enumerate() {
+                let tx_id = format!("{arena_id}-tx-{i}");
+                c.log_transaction(&arena, payment(&tx_id, amount, 0))
+                    .unwrap();
+            }
+            let bettors: Vec<_> = stakes
+                .iter()
+                .map(|&amount| {
+                    let bettor = c.wallet(LAMPORTS_PER_SOL);
+                    c.bet(&arena, bettor, "alpha", amount, BetSide::Survive)
+                        .unwrap();
+                    bettor
+                })
+                .collect();
+            c.declare(&arena, "alpha").unwrap();
+            for bettor in bettors {
+                c.send(
+                    arena.claim_accounts(bettor, "alpha"),
+                    crate::instruction::ClaimWinnings {},
+                )
+                .unwrap();
+            }
+            c.send(
+                crate::accounts::CloseArena {
+                    arena: arena.key,
+                    vault: arena.vault,
+                    protocol_stats: c.ensure::<ProtocolStats>(stats),
+                    authority: arena.authority,
+                    system_program: System::id(),
+                },
+                crate::instruction::CloseArena {},
+            )
+            .unwrap();
+            assert!(!c.exists(arena.key));
+        };
+
+        run("season-1", &[100, 250], &[300, 200]);
+        run("season-2", &[400], &[700]);
+        let totals: ProtocolStats = c.get(stats);
+        assert_eq!(
+            (
+                totals.arenas_closed,
+                totals.total_volume,
+                totals.total_bet_volume,
+                totals.unique_bettors
+            ),
+            (2, 750, 1_200, 3)
+        );
+    }
+
+    #[test]
+    fn stats_updates_must_agree_with_the_chain() {
+        let c = Chain::new();
+        let arena = c.open_arena("consistent");
+        for agent_id in ["alpha", "beta", "gamma"] {
+            c.register(&arena, agent_id, 1_000).unwrap();
+        }
+        c.warp(60);
+        c.kill(&arena, "alpha").unwrap();
+        let update = |alive_agents, dead_agents| {
+            c.send(
+                crate::accounts::UpdateStats {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::UpdateStats {
+                    alive_agents,
+                    dead_agents,
+                    avg_balance: 1_000,
+                    gini_coefficient: 0,
+                },
+            )
+        };
+
+        // A logged death can't be undone
+        assert_eq!(
+            update(3, 0).unwrap_err(),
+            code(ArenaError::InconsistentStats)
+        );
+        // Nor can the split count more agents than were seen
+        assert_eq!(
+            update(2, 2).unwrap_err(),
+            code(ArenaError::InconsistentStats)
+        );
+        update(1, 2).unwrap();
+        // Deaths are permanent once reported
+        assert_eq!(
+            update(2, 1).unwrap_err(),
+            code(ArenaError::InconsistentStats)
+        );
+        let state = arena.state(&c);
+        assert_eq!((state.alive_agents, state.dead_agents), (1, 2));
+    }
+
+    #[test]
+    fn tags_are_stored_and_validated() {
+        let c = Chain::new();
+        let arena = c.open_arena("tagged");
+        let set_tags = |tags: Vec<String>| {
+            c.send(
+                crate::accounts::SetTags {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::SetTags { tags },
+            )
+        };
+        set_tags(vec!["trading".to_string(), "llm-agents".to_string()]).unwrap();
+        assert_eq!(arena.state(&c).tags, ["trading", "llm-agents"]);
+
+        let invalid = [
+            (0..=MAX_TAGS).map(|i| format!("tag-{i}")).collect(),
+            vec!["t".repeat(MAX_TAG_LEN + 1)],
+            vec![String::new()],
+            vec!["trading".to_string(), "trading".to_string()],
+        ];
+        for tags in invalid {
+            assert_eq!(set_tags(tags).unwrap_err(), code(ArenaError::InvalidTags));
+        }
+        assert_eq!(arena.state(&c).tags, ["trading", "llm-agents"]);
+    }
+
+    #[test]
+    fn transaction_cap_closes_logging() {
+        let c = Chain::new();
+        let arena = c.open_arena("bounded");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                max_transactions: Some(3),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        for tx_id in ["tx-1", "tx-2"] {
+            c.log_transaction(&arena, payment(tx_id, 10, 0)).unwrap();
+        }
+        assert!(!arena.state(&c).ready_to_resolve);
+        c.log_transaction(&arena, payment("tx-3", 10, 0)).unwrap();
+        let state = arena.state(&c);
+        assert!(state.ready_to_resolve);
+        assert_eq!(state.total_transactions, 3);
+
+        assert_eq!(
+            c.log_transaction(&arena, payment("tx-4", 10, 0))
+                .unwrap_err(),
+            code(ArenaError::TransactionCapReached)
+        );
+        // A new cap must lie beyond what was already logged
+        assert_eq!(
+            c.configure(
+                &arena,
+                ArenaConfigUpdate {
+                    max_transactions: Some(3),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err(),
+            code(ArenaError::InvalidConfig)
+        );
+    }
+
+    #[test]
+    fn expired_bets_are_swept_in_a_batch() {
+        let c = Chain::new();
+        let arena = c.open_arena("expiry");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                confirm_threshold: Some(500),
+                confirm_timeout: Some(60),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let stakes = [
+            (c.wallet(LAMPORTS_PER_SOL), 600),
+            (c.wallet(LAMPORTS_PER_SOL), 900),
+        ];
+        for (bettor, amount) in stakes {
+            c.bet(&arena, bettor, "alpha", amount, BetSide::Survive)
+                .unwrap();
+        }
+        let pairs: Vec<_> = stakes
+            .iter()
+            .flat_map(|&(bettor, _)| [bet_key(bettor, "alpha"), bettor])
+            .collect();
+        let sweep = || {
+            c.send_with(
+                crate::accounts::SweepExpiredBets {
+                    arena: arena.key,
+                    vault: arena.vault,
+                },
+                crate::instruction::SweepExpiredBets { count: 2 },
+                &pairs,
+            )
+        };
+        c.warp(59);
+        assert_eq!(
+            sweep().unwrap_err(),
+            code(ArenaError::ConfirmationWindowOpen)
+        );
+
+        c.warp(60);
+        let before: Vec<_> = stakes
+            .iter()
+            .map(|&(bettor, _)| c.lamports(bettor) + c.lamports(bet_key(bettor, "alpha")))
+            .collect();
+        let vault = c.lamports(arena.vault);
+        sweep().unwrap();
+        for (&(bettor, amount), before) in stakes.iter().zip(before) {
+            assert_eq!(c.lamports(bettor), before + amount);
+            assert!(!c.exists(bet_key(bettor, "alpha")));
+        }
+        assert_eq!(c.lamports(arena.vault), vault - 1_500);
+        assert_eq!(arena.state(&c).unconfirmed_escrow, 0);
+    }
+
+    #[test]
+    fn credit_bets_convert_at_the_credit_value() {
+        let c = Chain::new();
+        let arena = c.open_arena("credits");
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        let bet_credits = |credits| {
+            c.send(
+                c.place_bet_accounts(&arena, bettor, "alpha"),
+                crate::instruction::PlaceBetCredits {
+                    agent_id: "alpha".to_string(),
+                    credits,
+                    bet_side: BetSide::Survive,
+                    predicted_placement: 0,
+                },
+            )
+        };
+        assert_eq!(
+            bet_credits(5).unwrap_err(),
+            code(ArenaError::CreditsDisabled)
+        );
+
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                credit_value: Some(LAMPORTS_PER_SOL / 100),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let vault = c.lamports(arena.vault);
+        bet_credits(5).unwrap();
+        assert_eq!(c.get::<Bet>(bet_key(bettor, "alpha")).amount, 50_000_000);
+        assert_eq!(c.lamports(arena.vault), vault + 50_000_000);
+        assert_eq!(
+            bet_credits(u64::MAX).unwrap_err(),
+            code(ArenaError::MathOverflow)
+        );
+    }
+
+    #[test]
+    fn self_exclusion_blocks_bets_and_only_lengthens() {
+        let c = Chain::new();
+        let arena = c.open_arena("responsible");
+        let (wallet, other) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        let exclusion = pda(&[b"self-exclusion", wallet.as_ref()]);
+        let exclude = |until| {
+            c.send(
+                crate::accounts::SelfExclude {
+                    exclusion: c.ensure::<SelfExclusion>(exclusion),
+                    wallet,
+                    system_program: System::id(),
+                },
+                crate::instruction::SelfExclude { until },
+            )
+        };
+        c.warp(100);
+        exclude(1_000).unwrap();
+        assert_eq!(
+            c.bet(&arena, wallet, "alpha", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::SelfExcluded)
+        );
+        c.bet(&arena, other, "alpha", 100, BetSide::Survive)
+            .unwrap();
+
+        assert_eq!(
+            exclude(500).unwrap_err(),
+            code(ArenaError::InvalidExclusion)
+        );
+        exclude(2_000).unwrap();
+        c.warp(1_500);
+        assert_eq!(
+            c.bet(&arena, wallet, "alpha", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::SelfExcluded)
+        );
+        c.warp(2_000);
+        c.bet(&arena, wallet, "alpha", 100, BetSide::Survive)
+            .unwrap();
+    }
+
+    #[test]
+    fn tied_agents_go_to_the_earlier_backed() {
+        let c = Chain::new();
+        let arena = c.open_arena("tie-break");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        c.warp(100);
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            100,
+            BetSide::Survive,
+        )
+        .unwrap();
+        c.warp(200);
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            500,
+            BetSide::Survive,
+        )
+        .unwrap();
+        assert_eq!(c.get::<AgentPool>(arena.pool("beta")).first_bet_at, 100);
+
+        // alpha would win an id tie-break; the earlier first bet decides first
+        c.send_with(
+            crate::accounts::AutoDeclareWinner {
+                arena: arena.key,
+                vault: arena.vault,
+                resolver: arena.authority,
+                system_program: System::id(),
+            },
+            crate::instruction::AutoDeclareWinner {
+                count: 2,
+                sol_usd_price: 0,
+            },
+            &[
+                arena.agent("alpha"),
+                arena.pool("alpha"),
+                arena.agent("beta"),
+                arena.pool("beta"),
+            ],
+        )
+        .unwrap();
+        let state = arena.state(&c);
+        assert!(state.resolved());
+        assert_eq!(state.winner, "beta");
+    }
+
+    #[test]
+    fn positions_summarize_a_bettors_arena() {
+        let c = Chain::new();
+        let arena = c.open_arena("positions");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        let (bettor, rival) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        c.bet(&arena, bettor, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, bettor, "beta", 200, BetSide::Survive)
+            .unwrap();
+        c.bet(&arena, rival, "beta", 500, BetSide::Survive).unwrap();
+        c.declare(&arena, "alpha").unwrap();
+        let positions = || {
+            let state = arena.state(&c);
+            let accounts = MyPositions {
+                arena: c.account(arena.key),
+                profile: c.account(arena.profile(bettor)),
+                winner_bet: UncheckedAccount::try_from(
+                    c.leak_info(bet_key(bettor, &state.winner), false),
+                ),
+                first_dead_bet: UncheckedAccount::try_from(
+                    c.leak_info(bet_key(bettor, &state.first_dead), false),
+                ),
+            };
+            c.call(accounts, Default::default(), |ctx| {
+                arena_logger::my_positions(ctx, bettor)
+            })
+            .unwrap()
+        };
+
+        let position = positions();
+        assert_eq!(
+            (
+                position.bet_count,
+                position.total_staked,
+                position.total_won
+            ),
+            (2, 500, 0)
+        );
+        assert!(position.has_unclaimed);
+
+        c.send(
+            arena.claim_accounts(bettor, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        let position = positions();
+        assert_eq!((position.total_staked, position.total_won), (500, 1_000));
+        assert!(!position.has_unclaimed);
+    }
+
+    #[test]
+    fn batches_stop_at_the_cap_before_doing_work() {
+        let c = Chain::new();
+        let arena = c.open_arena("batch-cap");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                confirm_threshold: Some(100),
+                confirm_timeout: Some(60),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let bettors: Vec<_> = (0..=MAX_BATCH_LEN)
+            .map(|_| {
+                let bettor = c.wallet(LAMPORTS_PER_SOL);
+                c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+                    .unwrap();
+                bettor
+            })
+            .collect();
+        c.warp(60);
+        let sweep = |bettors: &[Pubkey]| {
+            let pairs: Vec<_> = bettors
+                .iter()
+                .flat_map(|&bettor| [bet_key(bettor, "alpha"), bettor])
+                .collect();
+            c.send_with(
+                crate::accounts::SweepExpiredBets {
+                    arena: arena.key,
+                    vault: arena.vault,
+                },
+                crate::instruction::SweepExpiredBets {
+                    count: bettors.len() as u8,
+                },
+                &pairs,
+            )
+        };
+
+        assert_eq!(
+            sweep(&bettors).unwrap_err(),
+            code(ArenaError::BatchTooLarge)
+        );
+        assert!(bettors.iter().all(|&b| c.exists(bet_key(b, "alpha"))));
+        assert_eq!(arena.state(&c).unconfirmed_escrow, 900);
+
+        sweep(&bettors[..MAX_BATCH_LEN]).unwrap();
+        assert_eq!(arena.state(&c).unconfirmed_escrow, 100);
+        assert!(c.exists(bet_key(bettors[MAX_BATCH_LEN], "alpha")));
+    }
+
+    #[test]
+    fn short_vault_blocks_resolution() {
+        let c = Chain::new();
+        let arena = c.open_arena("short-vault");
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            500,
+            BetSide::Survive,
+        )
+        .unwrap();
+        // Something outside the program's accounting drained a lamport
+        **c.info(arena.vault).try_borrow_mut_lamports().unwrap() -= 1;
+        assert_eq!(
+            c.declare(&arena, "alpha").unwrap_err(),
+            code(ArenaError::InsufficientReserves)
+        );
+        assert!(!arena.state(&c).resolved());
+
+        **c.info(arena.vault).try_borrow_mut_lamports().unwrap() += 1;
+        c.declare(&arena, "alpha").unwrap();
+    }
+
+    #[test]
+    fn lifecycle_allows_only_forward_transitions() {
+        let mut arena = blank_arena();
+        assert_eq!(arena.state, ArenaState::Open);
+        arena.transition(ArenaState::Resolved).unwrap();
+        arena.transition(ArenaState::Closed).unwrap();
+        assert_eq!(arena.state, ArenaState::Closed);
+        assert_eq!(
+            arena.transition(ArenaState::Paused).unwrap_err(),
+            ArenaError::InvalidStateTransition.into()
+        );
+
+        let mut arena = blank_arena();
+        arena.transition(ArenaState::Voided).unwrap();
+        assert_eq!(
+            arena.transition(ArenaState::Open).unwrap_err(),
+            ArenaError::InvalidStateTransition.into()
+        );
+        assert_eq!(arena.state, ArenaState::Voided);
+
+        // A pause resumes only to the stage it interrupted
+        let mut arena = blank_arena();
+        arena.transition(ArenaState::BettingClosed).unwrap();
+        arena.transition(ArenaState::Paused).unwrap();
+        assert_eq!(
+            arena.transition(ArenaState::Open).unwrap_err(),
+            ArenaError::InvalidStateTransition.into()
+        );
+        arena.transition(ArenaState::BettingClosed).unwrap();
+    }
+
+    #[test]
+    fn service_fees_override_the_arena_rate() {
+        let c = Chain::new();
+        let arena = c.open_arena("tiered");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                tx_fee_bps: Some(100),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        for (service_type, fee_bps) in [("compute", 500), ("storage", 50)] {
+            c.send(
+                crate::accounts::SetServiceFee {
+                    arena: arena.key,
+                    service_fee: c
+                        .ensure::<ServiceFeeConfig>(arena.pda(b"service-fee", service_type)),
+                    authority: arena.authority,
+                    system_program: System::id(),
+                },
+                crate::instruction::SetServiceFee {
+                    service_type: service_type.to_string(),
+                    fee_bps,
+                },
+            )
+            .unwrap();
+        }
+        let fees = || arena.state(&c).accumulated_tx_fees;
+
+        c.log_transaction(&arena, payment("tx-compute", 1_000, 0))
+            .unwrap();
+        assert_eq!(fees(), 50);
+        let log = |tx_id: &str, service_type: &str| {
+            c.log_transaction(
+                &arena,
+                crate::instruction::LogTransaction {
+                    service_type: service_type.to_string(),
+                    ..payment(tx_id, 1_000, 0)
+                },
+            )
+            .unwrap();
+        };
+        log("tx-storage", "storage");
+        assert_eq!(fees(), 55);
+        // A service without its own rate is booked at the arena's
+        log("tx-bandwidth", "bandwidth");
+        assert_eq!(fees(), 65);
+    }
+
+    #[test]
+    fn betting_reopens_unless_the_arena_is_settled() {
+        let c = Chain::new();
+        let arena = c.open_arena("reopened");
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        let close = || {
+            c.send(
+                crate::accounts::CloseBetting {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::CloseBetting {},
+            )
+        };
+        let reopen = |new_deadline| {
+            c.send(
+                crate::accounts::CloseBetting {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::ReopenBetting { new_deadline },
+            )
+        };
+
+        close().unwrap();
+        assert_eq!(
+            c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::BettingClosed)
+        );
+        c.warp(1_000);
+        assert_eq!(reopen(1_000).unwrap_err(), code(ArenaError::InvalidConfig));
+        reopen(2_000).unwrap();
+        let state = arena.state(&c);
+        assert_eq!(
+            (state.state, state.betting_closes_at),
+            (ArenaState::Open, 2_000)
+        );
+        c.bet(&arena, bettor, "alpha", 100, BetSide::Survive)
+            .unwrap();
+
+        c.declare(&arena, "alpha").unwrap();
+        assert_eq!(reopen(0).unwrap_err(), code(ArenaError::ArenaResolved));
+    }
+
+    #[test]
+    fn outstanding_claims_fall_as_backers_claim() {
+        let c = Chain::new();
+        let arena = c.open_arena("wind-down");
+        let (first, second) = (c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL));
+        for bettor in [first, second] {
+            c.bet(&arena, bettor, "alpha", 300, BetSide::Survive)
+                .unwrap();
+        }
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            400,
+            BetSide::Survive,
+        )
+        .unwrap();
+        let outstanding = |agent_id: &str| {
+            let accounts = MaxLiability {
+                arena: c.account(arena.key),
+                pool: UncheckedAccount::try_from(c.leak_info(arena.pool(agent_id), false)),
+            };
+            c.call(accounts, Default::default(), |ctx| {
+                arena_logger::outstanding_claims(ctx, agent_id.to_string())
+            })
+            .unwrap()
+        };
+
+        assert_eq!(outstanding("alpha"), 0);
+        c.declare(&arena, "alpha").unwrap();
+        assert_eq!((outstanding("alpha"), outstanding("beta")), (1_000, 0));
+        c.send(
+            arena.claim_accounts(first, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(outstanding("alpha"), 500);
+    }
+
+    #[test]
+    fn disputes_past_the_threshold_freeze_claims() {
+        let c = Chain::new();
+        let arena = c.open_arena("disputed");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                dispute_window: Some(3_600),
+                dispute_threshold: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let winner = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, winner, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        let disputers = [c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL)];
+        for bettor in disputers {
+            c.bet(&arena, bettor, "beta", 200, BetSide::Survive)
+                .unwrap();
+        }
+        c.declare(&arena, "alpha").unwrap();
+
+        for (filed, bettor) in disputers.into_iter().enumerate() {
+            let accounts = FileDispute {
+                arena: c.account(arena.key),
+                profile: c.account(arena.profile(bettor)),
+                dispute: c.account(pda(&[b"dispute", arena.key.as_ref(), bettor.as_ref()])),
+                bettor: c.signer(bettor),
+                system_program: c.system_program(),
+            };
+            c.call(accounts, Default::default(), arena_logger::file_dispute)
+                .unwrap();
+            let state = arena.state(&c);
+            assert_eq!(state.dispute_count, filed as u32 + 1);
+            assert_eq!(state.under_review, filed == 1);
+        }
+        assert_eq!(
+            c.send(
+                arena.claim_accounts(winner, "alpha"),
+                crate::instruction::ClaimWinnings {},
+            )
+            .unwrap_err(),
+            code(ArenaError::ResultUnderReview)
+        );
+    }
+
+    #[test]
+    fn arena_summaries_are_read_in_a_bounded_batch() {
+        let c = Chain::new();
+        let arenas = ["north", "south", "east"].map(|arena_id| c.open_arena(arena_id));
+        c.bet(
+            &arenas[1],
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            100,
+            BetSide::Survive,
+        )
+        .unwrap();
+        let summaries = |keys: Vec<Pubkey>| {
+            let remaining: Vec<_> = keys.into_iter().map(|key| c.info(key)).collect();
+            arena_logger::multi_arena_summary(Context::new(
+                &crate::ID,
+                &mut MultiArenaSummary {},
+                Box::leak(remaining.into_boxed_slice()),
+                Default::default(),
+            ))
+        };
+
+        let packed = summaries(arenas.iter().map(|arena| arena.key).collect())
+            .unwrap()
+            .try_to_vec()
+            .unwrap();
+        let decoded = Vec::<ArenaSummary>::try_from_slice(&packed).unwrap();
+        let read: Vec<_> = decoded
+            .iter()
+            .map(|summary| (summary.arena, summary.total_bets, summary.total_bet_volume))
+            .collect();
+        assert_eq!(
+            read,
+            [
+                (arenas[0].key, 0, 0),
+                (arenas[1].key, 1, 100),
+                (arenas[2].key, 0, 0)
+            ]
+        );
+
+        // A full batch still fits in return data; one more is refused
+        let full = summaries(vec![arenas[0].key; MAX_BATCH_LEN]).unwrap();
+        assert!(full.try_to_vec().unwrap().len() <= 1_024);
+        assert_eq!(
+            summaries(vec![arenas[0].key; MAX_BATCH_LEN + 1]).err(),
+            Some(ArenaError::BatchTooLarge.into())
+        );
+    }
+
+    #[test]
+    fn checkpoints_number_themselves_upward() {
+        use anchor_lang::Event;
+
+        let c = Chain::new();
+        let arena = c.open_arena("indexed");
+        let mut ids = Vec::new();
+        for block_height in [5_000, 5_100] {
+            c.log_transaction(&arena, payment(&format!("tx-{block_height}"), 100, 0))
+                .unwrap();
+            c.send(
+                crate::accounts::UpdateStats {
+                    arena: arena.key,
+                    authority: arena.authority,
+                },
+                crate::instruction::Checkpoint { block_height },
+            )
+            .unwrap();
+            let data = arena_checkpoint(arena.key, &arena.state(&c), block_height, 0).data();
+            assert_eq!(&data[..8], Checkpoint::DISCRIMINATOR);
+            let checkpoint = Checkpoint::try_from_slice(&data[8..]).unwrap();
+            ids.push((
+                checkpoint.checkpoint_id,
+                checkpoint.block_height,
+                checkpoint.total_transactions,
+            ));
+        }
+        assert_eq!(ids, [(1, 5_000, 1), (2, 5_100, 2)]);
+    }
+
+    #[test]
+    fn backer_payouts_accumulate_on_the_winners_career() {
+        let c = Chain::new();
+        let accounts = InitCareer {
+            career: c.account(career_key("alpha")),
+            payer: c.signer(c.wallet(LAMPORTS_PER_SOL)),
+            system_program: c.system_program(),
+        };
+        c.call(accounts, Default::default(), |ctx| {
+            arena_logger::init_career(ctx, identity("alpha"))
+        })
+        .unwrap();
+        let arena = c.open_arena("roi");
+        c.register(&arena, "alpha", 100).unwrap();
+        c.register(&arena, "beta", 100).unwrap();
+        let backers = [c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL)];
+        for (bettor, amount) in backers.into_iter().zip([300, 100]) {
+            c.bet(&arena, bettor, "alpha", amount, BetSide::Survive)
+                .unwrap();
+        }
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            400,
+            BetSide::Survive,
+        )
+        .unwrap();
+        let mut declare = c.declare_accounts(&arena, "alpha");
+        declare.career = Some(career_key("alpha"));
+        c.send(
+            declare,
+            crate::instruction::DeclareWinner {
+                agent_id: "alpha".to_string(),
+                sol_usd_price: 0,
+            },
+        )
+        .unwrap();
+
+        let mut paid = Vec::new();
+        for bettor in backers {
+            let mut claim = arena.claim_accounts(bettor, "alpha");
+            claim.career = Some(career_key("alpha"));
+            c.send(claim, crate::instruction::ClaimWinnings {}).unwrap();
+            paid.push(
+                c.get::<AgentCareer>(career_key("alpha"))
+                    .total_paid_to_backers,
+            );
+        }
+        // Backing alpha returned 800 on the 400 staked
+        assert_eq!(paid, [600, 800]);
+        assert_eq!(c.get::<AgentCareer>(career_key("alpha")).total_backed, 400);
+    }
+
+    #[test]
+    fn settled_bets_and_pools_return_their_rent() {
+        let c = Chain::new();
+        let arena = c.open_arena("wind-up");
+        let backers = [c.wallet(LAMPORTS_PER_SOL), c.wallet(LAMPORTS_PER_SOL)];
+        for bettor in backers {
+            c.bet(&arena, bettor, "alpha", 300, BetSide::Survive)
+                .unwrap();
+        }
+        c.declare(&arena, "alpha").unwrap();
+        let close_pool = || {
+            c.send(
+                crate::accounts::ClosePool {
+                    arena: arena.key,
+                    pool: arena.pool("alpha"),
+                    authority: arena.authority,
+                },
+                crate::instruction::ClosePool {
+                    agent_id: "alpha".to_string(),
+                },
+            )
+        };
+
+        for bettor in backers {
+            assert_eq!(
+                close_pool().unwrap_err(),
+                code(ArenaError::PoolHasUnclaimedBets)
+            );
+            let bet = bet_key(bettor, "alpha");
+            let (before, rent) = (c.lamports(bettor), c.lamports(bet));
+            assert!(rent > 0);
+            c.send(
+                arena.claim_accounts(bettor, "alpha"),
+                crate::instruction::ClaimWinnings {},
+            )
+            .unwrap();
+            // The bet is closed with its winnings, its rent going to the bettor
+            assert_eq!(c.lamports(bettor), before + 300 + rent);
+            assert!(!c.exists(bet) && c.lamports(bet) == 0);
+        }
+
+        let (before, rent) = (c.lamports(arena.authority), c.lamports(arena.pool("alpha")));
+        close_pool().unwrap();
+        assert_eq!(c.lamports(arena.authority), before + rent);
+        assert!(!c.exists(arena.pool("alpha")));
+    }
+
+    #[test]
+    fn bets_may_not_dominate_the_pool() {
+        let c = Chain::new();
+        let arena = c.open_arena("liquid");
+        for agent_id in ["alpha", "beta"] {
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                agent_id,
+                300,
+                BetSide::Survive,
+            )
+            .unwrap();
+        }
+        let ratio = |max_bet_ratio_bps| {
+            c.configure(
+                &arena,
+                ArenaConfigUpdate {
+                    max_bet_ratio_bps: Some(max_bet_ratio_bps),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let whale = c.wallet(LAMPORTS_PER_SOL);
+
+        // At most half the 600 already staked plus the bet itself
+        ratio(5_000);
+        assert_eq!(
+            c.bet(&arena, whale, "alpha", 700, BetSide::Survive)
+                .unwrap_err(),
+            code(ArenaError::BetTooLargeForPool)
+        );
+        c.bet(&arena, whale, "alpha", 600, BetSide::Survive)
+            .unwrap();
+        ratio(0);
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "beta",
+            10_000,
+            BetSide::Survive,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn external_references_tie_transactions_to_other_chains() {
+        let c = Chain::new();
+        let arena = c.open_arena("bridged");
+        // A hex Ethereum transaction hash fills the reference exactly
+        let eth_tx = *b"5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+        let bridged = |external_ref| crate::instruction::LogTransaction {
+            external_ref,
+            ..payment("tx-bridged", 400, 0)
+        };
+        c.log_transaction(&arena, bridged(Some(eth_tx))).unwrap();
+        let tx = pda(&[b"transaction", b"tx-bridged"]);
+        assert_eq!(c.get::<Transaction>(tx).external_ref, Some(eth_tx));
+
+        // A retry is a no-op only if it carries the same reference
+        c.log_transaction(&arena, bridged(Some(eth_tx))).unwrap();
+        assert_eq!(
+            c.log_transaction(&arena, bridged(Some([0; 64])))
+                .unwrap_err(),
+            code(ArenaError::DuplicateTransaction)
+        );
+        assert_eq!(arena.state(&c).total_transactions, 1);
+    }
+
+    #[test]
+    fn productive_winners_pay_their_backers_a_bonus() {
+        let c = Chain::new();
+        let winnings = |arena_id: &str, winner: &str| {
+            let arena = c.open_arena(arena_id);
+            c.configure(
+                &arena,
+                ArenaConfigUpdate {
+                    fee_bps: Some(1_000),
+                    productive_services: Some(2),
+                    productivity_bonus_bps: Some(1_000),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            c.register(&arena, "alpha", 1_000).unwrap();
+            c.register(&arena, "beta", 1_000).unwrap();
+            // Beta serves alpha three times; alpha completes nothing
+            for n in 0..3 {
+                c.log_transaction(&arena, payment(&format!("{arena_id}-{n}"), 10, 0))
+                    .unwrap();
+            }
+            let backer = c.wallet(LAMPORTS_PER_SOL);
+            c.bet(&arena, backer, winner, 500, BetSide::Survive)
+                .unwrap();
+            let loser = if winner == "alpha" { "beta" } else { "alpha" };
+            c.bet(
+                &arena,
+                c.wallet(LAMPORTS_PER_SOL),
+                loser,
+                500,
+                BetSide::Survive,
+            )
+            .unwrap();
+            c.declare(&arena, winner).unwrap();
+            c.send(
+                arena.claim_accounts(backer, winner),
+                crate::instruction::ClaimWinnings {},
+            )
+            .unwrap();
+            (
+                c.get::<UserProfile>(arena.profile(backer)).total_won,
+                arena.state(&c).withdrawable_fees,
+            )
+        };
+
+        // The 10% bonus on the 900 pot comes out of the 100 in fees
+        assert_eq!(winnings("productive", "beta"), (990, 10));
+        assert_eq!(winnings("idle", "alpha"), (900, 100));
+    }
+
+    #[test]
+    fn realloc_grows_an_agent_from_before_identities() {
+        let c = Chain::new();
+        let arena = c.open_arena("agents");
+        c.register(&arena, "alpha", 1_000).unwrap();
+        let agent = arena.agent("alpha");
+        // The same agent as written before the trailing `identity` field existed
+        let old_len = 8 + Agent::INIT_SPACE - 32;
+        let mut data = Agent::DISCRIMINATOR.to_vec();
+        data.extend(c.get::<Agent>(agent).try_to_vec().unwrap());
+        data.truncate(data.len() - 32);
+        data.resize(old_len, 0);
+        let rent = Rent::default().minimum_balance(old_len);
+        c.insert(leak_account(agent, crate::ID, rent, &data, false));
+
+        let realloc = || {
+            c.send(
+                crate::accounts::ReallocAgent {
+                    arena: arena.key,
+                    agent,
+                    authority: arena.authority,
+                    system_program: System::id(),
+                },
+                crate::instruction::ReallocAgent {
+                    agent_id: "alpha".to_string(),
+                },
+            )
+        };
+        let paid = c.lamports(arena.authority);
+        realloc().unwrap();
+        assert_eq!(c.info(agent).data_len(), 8 + Agent::INIT_SPACE);
+        let new_rent = Rent::default().minimum_balance(8 + Agent::INIT_SPACE);
+        assert_eq!(c.lamports(agent), new_rent);
+        assert_eq!(c.lamports(arena.authority), paid - (new_rent - rent));
+        let state: Agent = c.get(agent);
+        assert_eq!(
+            (state.agent_id.as_str(), state.name.as_str(), state.balance),
+            ("alpha", "ALPHA", 1_000)
+        );
+        assert_eq!(state.identity, Pubkey::default());
+
+        assert_eq!(realloc().unwrap_err(), code(ArenaError::AgentUpToDate));
+    }
+
+    #[test]
+    fn practice_bets_record_without_moving_lamports() {
+        let c = Chain::new();
+        let arena = c.open_arena("practice");
+        c.send(
+            crate::accounts::SetPractice {
+                arena: arena.key,
+                authority: arena.authority,
+            },
+            crate::instruction::SetPractice { practice: true },
+        )
+        .unwrap();
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        let (wallet, vault) = (c.lamports(bettor), c.lamports(arena.vault));
+        c.bet(&arena, bettor, "alpha", 500, BetSide::Survive)
+            .unwrap();
+
+        // The harness funds the bet's accounts, so the stake is all that could move
+        let bet = bet_key(bettor, "alpha");
+        assert_eq!(c.lamports(bettor), wallet);
+        assert_eq!(c.lamports(arena.vault), vault);
+        assert_eq!(c.get::<Bet>(bet).amount, 500);
+        assert_eq!(arena.state(&c).total_bet_volume, 500);
+
+        c.declare(&arena, "alpha").unwrap();
+        let (wallet, bet_rent) = (c.lamports(bettor), c.lamports(bet));
+        c.send(
+            arena.claim_accounts(bettor, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.lamports(bettor), wallet + bet_rent);
+        assert_eq!(c.lamports(arena.vault), vault);
+    }
+
+    #[test]
+    fn volume_events_carry_the_running_totals() {
+        use anchor_lang::Event;
+
+        let c = Chain::new();
+        let arena = c.open_arena("dashboard");
+        let mut totals = Vec::new();
+        for (tx_id, amount) in [("tx-first", 250), ("tx-second", 750)] {
+            c.log_transaction(&arena, payment(tx_id, amount, 0))
+                .unwrap();
+            let transaction: Transaction = c.get(pda(&[b"transaction", tx_id.as_bytes()]));
+            let data = volume_updated(arena.key, &arena.state(&c), &transaction).data();
+            assert_eq!(&data[..8], VolumeUpdated::DISCRIMINATOR);
+            let updated = VolumeUpdated::try_from_slice(&data[8..]).unwrap();
+            assert_eq!(
+                (updated.arena, updated.transaction_id.as_str()),
+                (arena.key, tx_id)
+            );
+            totals.push((
+                updated.amount,
+                updated.total_transactions,
+                updated.total_volume,
+            ));
+        }
+        assert_eq!(totals, [(250, 1, 250), (750, 2, 1_000)]);
+    }
+
+    #[test]
+    fn entry_fees_seed_the_prize_pool() {
+        let c = Chain::new();
+        let arena = c.open_arena("pay-to-enter");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                agent_entry_fee: Some(200),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (vault, wallet) = (c.lamports(arena.vault), c.lamports(arena.authority));
+        c.register(&arena, "alpha", 1_000).unwrap();
+        c.register(&arena, "beta", 1_000).unwrap();
+        assert_eq!(arena.state(&c).seeded_amount, 400);
+        assert_eq!(c.lamports(arena.vault), vault + 400);
+        assert_eq!(c.lamports(arena.authority), wallet - 400);
+
+        let accounts = RegisterAgent {
+            arena: c.account(arena.key),
+            agent: c.account(arena.agent("gamma")),
+            seen: c.account(arena.pda(b"seen", "gamma")),
+            vault: c.account(arena.vault),
+            authority: c.signer(arena.authority),
+            system_program: c.system_program(),
+        };
+        let underpaid = c.call(accounts, Default::default(), |ctx| {
+            arena_logger::register_agent(
+                ctx,
+                "gamma".to_string(),
+                "GAMMA".to_string(),
+                1_000,
+                100,
+                identity("gamma"),
+            )
+        });
+        assert_eq!(underpaid.unwrap_err(), ArenaError::InvalidEntryFee.into());
+        assert_eq!(arena.state(&c).seeded_amount, 400);
+
+        // The entry fees are paid out to the winner's backers on top of the stakes
+        let backer = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, backer, "alpha", 300, BetSide::Survive)
+            .unwrap();
+        c.declare(&arena, "alpha").unwrap();
+        c.send(
+            arena.claim_accounts(backer, "alpha"),
+            crate::instruction::ClaimWinnings {},
+        )
+        .unwrap();
+        assert_eq!(c.get::<UserProfile>(arena.profile(backer)).total_won, 700);
+    }
+
+    #[test]
+    fn arena_config_reports_the_configured_limits() {
+        let c = Chain::new();
+        let arena = c.open_arena("rules");
+        c.configure(
+            &arena,
+            ArenaConfigUpdate {
+                fee_bps: Some(250),
+                lamport_granularity: Some(1_000),
+                bet_cooldown: Some(30),
+                max_bet_ratio_bps: Some(4_000),
+                min_pool_to_payout: Some(50_000),
+                agent_entry_fee: Some(10_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let accounts = ReadArenaConfig {
+            arena: c.account(arena.key),
+        };
+        let config = c
+            .call(accounts, Default::default(), arena_logger::arena_config)
+            .unwrap();
+
+        // Decode the packed struct as a client reads it from return data
+        let packed = config.try_to_vec().unwrap();
+        assert!(packed.len() <= ArenaConfig::INIT_SPACE);
+        let config = ArenaConfig::try_from_slice(&packed).unwrap();
+        assert_eq!(
+            (
+                config.fee_bps,
+                config.lamport_granularity,
+                config.bet_cooldown,
+                config.max_bet_ratio_bps
+            ),
+            (250, 1_000, 30, 4_000)
+        );
+        assert_eq!(
+            (config.min_pool_to_payout, config.agent_entry_fee),
+            (50_000, 10_000)
+        );
+        // Limits left alone read back at their defaults
+        assert_eq!((config.cancel_fee_bps, config.credit_value), (0, 0));
+    }
+
+    #[test]
+    fn proportional_results_pay_by_stake_and_share() {
+        let c = Chain::new();
+        let arena = c.open_arena("split");
+        let mut backers = Vec::new();
+        for (agent_id, amount) in [
+            ("alpha", 300),
+            ("alpha", 100),
+            ("beta", 100),
+            ("gamma", 500),
+        ] {
+            let bettor = c.wallet(LAMPORTS_PER_SOL);
+            c.bet(&arena, bettor, agent_id, amount, BetSide::Survive)
+                .unwrap();
+            backers.push((bettor, agent_id));
+        }
+        // Alpha finished with three times beta's balance
+        let shares = [("alpha", 3_000), ("beta", 1_000)];
+        let pools: Vec<_> = shares.iter().map(|(id, _)| arena.pool(id)).collect();
+        c.send_with(
+            crate::accounts::DeclarePodium {
+                arena: arena.key,
+                vault: arena.vault,
+                resolver: arena.authority,
+                system_program: System::id(),
+            },
+            crate::instruction::DeclareProportional {
+                shares: shares
+                    .map(|(agent_id, weight)| AgentShare {
+                        agent_id: agent_id.to_string(),
+                        weight,
+                    })
+                    .to_vec(),
+                sol_usd_price: 0,
+            },
+            &pools,
+        )
+        .unwrap();
+
+        let won: Vec<_> = backers
+            .into_iter()
+            .map(|(bettor, agent_id)| {
+                c.send(
+                    arena.claim_accounts(bettor, agent_id),
+                    crate::instruction::ClaimWinnings {},
+                )
+                .map(|()| c.get::<UserProfile>(arena.profile(bettor)).total_won)
+            })
+            .collect();
+        // Alpha's 750 slice of the 1_000 pot is split 3:1 by stake; beta's 250 is whole
+        assert_eq!(won[..3], [Ok(562), Ok(187), Ok(250)]);
+        assert_eq!(won[3], Err(code(ArenaError::NotWinningBet)));
+    }
+
+    #[test]
+    fn death_records_are_amended_in_their_own_arena() {
+        let c = Chain::new();
+        let (arena, other) = (c.open_arena("amended"), c.open_arena("bystander"));
+        for arena in [&arena, &other] {
+            c.register(arena, "alpha", 100).unwrap();
+            c.register(arena, "beta", 100).unwrap();
+        }
+        c.warp(60);
+        c.kill(&arena, "alpha").unwrap();
+        c.kill(&other, "alpha").unwrap();
+        let amend = |death, final_balance, services_completed| {
+            c.send(
+                crate::accounts::AmendDeath {
+                    arena: arena.key,
+                    death,
+                    authority: arena.authority,
+                },
+                crate::instruction::AmendDeath {
+                    agent_id: "alpha".to_string(),
+                    final_balance,
+                    services_completed,
+                },
+            )
+        };
+
+        amend(arena.pda(b"death", "alpha"), 750, 12).unwrap();
+        let death: AgentDeath = c.get(arena.pda(b"death", "alpha"));
+        assert_eq!((death.final_balance, death.services_completed), (750, 12));
+
+        // Another arena's record for the same agent id lives at another address
+        assert_eq!(
+            amend(other.pda(b"death", "alpha"), 1, 1).unwrap_err(),
+            code(ErrorCode::ConstraintSeeds)
+        );
+        let death: AgentDeath = c.get(other.pda(b"death", "alpha"));
+        assert_eq!((death.final_balance, death.services_completed), (0, 0));
+    }
+
+    #[test]
+    fn protected_increases_reject_a_ballooned_pool() {
+        let c = Chain::new();
+        let arena = c.open_arena("slippage");
+        let bettor = c.wallet(LAMPORTS_PER_SOL);
+        c.bet(&arena, bettor, "alpha", 200, BetSide::Survive)
+            .unwrap();
+        let increase = |max_pool_after| {
+            c.send(
+                crate::accounts::IncreaseBet {
+                    bet: bet_key(bettor, "alpha"),
+                    arena: arena.key,
+                    vault: arena.vault,
+                    pool: arena.pool("alpha"),
+                    profile: arena.profile(bettor),
+                    blacklisted: pda(&[b"blacklist", arena.key.as_ref(), bettor.as_ref()]),
+                    self_exclusion: pda(&[b"self-exclusion", bettor.as_ref()]),
+                    bettor,
+                    system_program: System::id(),
+                },
+                crate::instruction::IncreaseBet {
+                    amount: 100,
+                    max_pool_after,
+                },
+            )
+        };
+        // The bettor quoted a 200 pool, but a whale lands first
+        c.bet(
+            &arena,
+            c.wallet(LAMPORTS_PER_SOL),
+            "alpha",
+            1_000,
+            BetSide::Survive,
+        )
+        .unwrap();
+
+        assert_eq!(
+            increase(300).unwrap_err(),
+            code(ArenaError::PoolMovedTooFar)
+        );
+        assert_eq!(c.get::<Bet>(bet_key(bettor, "alpha")).amount, 200);
+        increase(1_300).unwrap();
+        increase(0).unwrap();
+        assert_eq!(c.get::<Bet>(bet_key(bettor, "alpha")).amount, 400);
+        assert_eq!(c.get::<AgentPool>(arena.pool("alpha")).total_staked, 1_400);
+    }
+
+    #[test]
+    fn service_types_are_counted_once_each() {
+        let c = Chain::new();
+        let arena = c.open_arena("breadth");
+        for (tx_id, service_type) in [
+            ("tx-compute", "compute"),
+            ("tx-storage", "storage"),
+            ("tx-compute-again", "compute"),
+        ] {
+            c.log_transaction(
+                &arena,
+                crate::instruction::LogTransaction {
+                    service_type: service_type.to_string(),
+                    ..payment(tx_id, 100, 0)
+                },
+            )
+            .unwrap();
+        }
+        let state = arena.state(&c);
+        assert_eq!(
+            (state.total_transactions, state.distinct_service_types),
+            (3, 2)
+        );
+        let stats: ServiceStats = c.get(arena.pda(b"service-stats", "compute"));
+        assert_eq!((stats.transaction_count, stats.volume), (2, 200));
+    }
 }
@@ -49,6 +49,11 @@ export class ArenaEngine extends EventEmitter {
       const id = `agent-${Date.now()}-${i}`;
       const agent = createAgent(id, type, 1.0);
       this.agents.set(id, agent);
+
+      // Register on-chain so the agent's death can be logged later (fire-and-forget)
+      getSolanaLogger()
+        .registerAgent(id, agent.getState().name, 1.0)
+        .catch(() => {});
     }
 
     console.log(`${this.agents.size} agents created`);
@@ -135,16 +135,24 @@ export class SolanaLogger {
       const arenaIdLen = Buffer.alloc(4);
       arenaIdLen.writeUInt32LE(arenaIdBytes.length);
 
-      const data = Buffer.concat([discriminator, arenaIdLen, arenaIdBytes]);
+      // Guardian (pause-only key) defaults to the logger wallet
+      const guardian = this.wallet.publicKey.toBuffer();
+
+      const data = Buffer.concat([discriminator, arenaIdLen, arenaIdBytes, guardian]);
 
       const [arenaPda, bump] = PublicKey.findProgramAddressSync(
         [Buffer.from('arena'), Buffer.from(ARENA_ID)],
         this.programId,
       );
+      const [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('vault'), arenaPda.toBuffer()],
+        this.programId,
+      );
 
       const ix = {
         keys: [
           { pubkey: arenaPda, isSigner: false, isWritable: true },
+          { pubkey: vaultPda, isSigner: false, isWritable: true },
           { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
           { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
         ],
@@ -208,6 +216,10 @@ export class SolanaLogger {
       const amountBuf = Buffer.alloc(8);
       amountBuf.writeBigUInt64LE(amountLamports);
 
+      // Hourly volume bucket; must be within an hour of the cluster clock's hour
+      const hourBuf = Buffer.alloc(8);
+      hourBuf.writeBigInt64LE(BigInt(Math.floor(Date.now() / 3_600_000)));
+
       const encodeString = (buf: Buffer) => {
         const len = Buffer.alloc(4);
         len.writeUInt32LE(buf.length);
@@ -221,6 +233,9 @@ export class SolanaLogger {
         encodeString(toBytes),
         amountBuf,
         encodeString(serviceBytes),
+        hourBuf,
+        Buffer.from([0]), // memo: None
+        Buffer.from([0]), // external_ref: None
       ]);
 
       // Derive transaction PDA (seeds max 32 bytes each)
@@ -229,12 +244,44 @@ export class SolanaLogger {
         this.programId,
       );
 
+      // First-seen markers let the program count distinct agents on-chain
+      const [fromSeenPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('seen'), this.arenaPda!.toBuffer(), fromBytes],
+        this.programId,
+      );
+      const [toSeenPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('seen'), this.arenaPda!.toBuffer(), toBytes],
+        this.programId,
+      );
+      const [bucketPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('bucket'), this.arenaPda!.toBuffer(), hourBuf],
+        this.programId,
+      );
+      // Per-service fee rate; may not exist, in which case the arena default applies
+      const [serviceFeePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('service-fee'), this.arenaPda!.toBuffer(), serviceBytes],
+        this.programId,
+      );
+      // Per-service running totals, created on the type's first transaction
+      const [serviceStatsPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('service-stats'), this.arenaPda!.toBuffer(), serviceBytes],
+        this.programId,
+      );
+
       const ix = {
         keys: [
           { pubkey: txPda, isSigner: false, isWritable: true },
           { pubkey: this.arenaPda!, isSigner: false, isWritable: true },
+          { pubkey: fromSeenPda, isSigner: false, isWritable: true },
+          { pubkey: toSeenPda, isSigner: false, isWritable: true },
+          { pubkey: bucketPda, isSigner: false, isWritable: true },
+          { pubkey: serviceFeePda, isSigner: false, isWritable: false },
+          { pubkey: serviceStatsPda, isSigner: false, isWritable: true },
           { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
           { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          // Optional sender and receiver Agent accounts: not tracked on-chain by the logger
+          { pubkey: this.programId, isSigner: false, isWritable: false },
+          { pubkey: this.programId, isSigner: false, isWritable: false },
         ],
         programId: this.programId,
         data,
@@ -263,6 +310,94 @@ export class SolanaLogger {
     }
   }
 
+  async registerAgent(
+    agentId: string,
+    agentName: string,
+    startingBalance: number,
+  ): Promise<string | null> {
+    if (!this.enabled || !this.wallet) {
+      return null;
+    }
+
+    try {
+      const { Transaction, SystemProgram } = await import('@solana/web3.js');
+      const { createHash } = await import('crypto');
+
+      const discriminator = createHash('sha256')
+        .update('global:register_agent')
+        .digest()
+        .slice(0, 8);
+
+      const agentIdBytes = Buffer.from(agentId.slice(0, 20));
+      const nameBytes = Buffer.from(agentName.slice(0, 20));
+
+      const balanceBuf = Buffer.alloc(8);
+      balanceBuf.writeBigUInt64LE(BigInt(Math.floor(startingBalance * 1_000_000_000)));
+
+      // The logger's arena charges no agent entry fee
+      const entryFeeBuf = Buffer.alloc(8);
+
+      // Career identity derived from the agent's name, so it carries across arenas
+      const identity = createHash('sha256').update(agentName).digest();
+
+      const encodeString = (buf: Buffer) => {
+        const len = Buffer.alloc(4);
+        len.writeUInt32LE(buf.length);
+        return Buffer.concat([len, buf]);
+      };
+
+      const data = Buffer.concat([
+        discriminator,
+        encodeString(agentIdBytes),
+        encodeString(nameBytes),
+        balanceBuf,
+        entryFeeBuf,
+        identity,
+      ]);
+
+      const [agentPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('agent'), this.arenaPda!.toBuffer(), agentIdBytes],
+        this.programId,
+      );
+      const [seenPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('seen'), this.arenaPda!.toBuffer(), agentIdBytes],
+        this.programId,
+      );
+      const [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('vault'), this.arenaPda!.toBuffer()],
+        this.programId,
+      );
+
+      const ix = {
+        keys: [
+          { pubkey: this.arenaPda!, isSigner: false, isWritable: true },
+          { pubkey: agentPda, isSigner: false, isWritable: true },
+          { pubkey: seenPda, isSigner: false, isWritable: true },
+          { pubkey: vaultPda, isSigner: false, isWritable: true },
+          { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
+          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        ],
+        programId: this.programId,
+        data,
+      };
+
+      const tx = new Transaction().add(ix);
+      tx.feePayer = this.wallet.publicKey;
+      tx.recentBlockhash = await this.getBlockhash();
+
+      tx.sign(this.wallet);
+      const sig = await this.connection.sendRawTransaction(tx.serialize(), {
+        skipPreflight: true,
+      });
+
+      this.connection.confirmTransaction(sig, 'confirmed').catch(() => {});
+      return sig;
+    } catch (error: any) {
+      console.error(`[Solana] Failed to register agent: ${error.message}`);
+      return null;
+    }
+  }
+
   async logDeath(
     agentId: string,
     agentName: string,
@@ -306,8 +441,19 @@ export class SolanaLogger {
         servicesBuf,
       ]);
 
+      // Death records are per arena, so an agent id can die in every arena it joins
       const [deathPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from('death'), Buffer.from(agentId.slice(0, 20))],
+        [Buffer.from('death'), this.arenaPda!.toBuffer(), agentIdBytes],
+        this.programId,
+      );
+      // The agent's bet pool, frozen by the program at death
+      const [poolPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('pool'), this.arenaPda!.toBuffer(), agentIdBytes],
+        this.programId,
+      );
+      // The agent record created by registerAgent; the program rejects unregistered deaths
+      const [agentPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from('agent'), this.arenaPda!.toBuffer(), agentIdBytes],
         this.programId,
       );
 
@@ -315,8 +461,12 @@ export class SolanaLogger {
         keys: [
           { pubkey: deathPda, isSigner: false, isWritable: true },
           { pubkey: this.arenaPda!, isSigner: false, isWritable: true },
+          { pubkey: poolPda, isSigner: false, isWritable: true },
+          { pubkey: agentPda, isSigner: false, isWritable: true },
           { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
           { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          // Optional AgentCareer account: not tracked on-chain by the logger
+          { pubkey: this.programId, isSigner: false, isWritable: false },
         ],
         programId: this.programId,
         data,